@@ -0,0 +1,157 @@
+//! Capture buffer storage: `SampleRing` against the `BTreeMap<u64, f64>` it replaced, at the
+//! sizes a 1 kHz column reaches in the 180 s live window.
+//!
+//! `cargo bench --bench capture`
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use std::collections::BTreeMap;
+use trendline_lib::shared::Point;
+use trendline_lib::state::pyramid::TilePyramid;
+use trendline_lib::state::ring::SampleRing;
+
+const RATE: f64 = 1000.0;
+const CAP: usize = 180_000;
+/// Samples per `InsertBatch` from a 1 kHz stream.
+const BATCH: usize = 20;
+
+fn sample(i: usize) -> Point {
+    let x = i as f64 / RATE;
+    Point {
+        x,
+        y: (x * 7.0).sin(),
+    }
+}
+
+/// The previous storage: one map node per sample, evicted a key at a time.
+fn btree_push(map: &mut BTreeMap<u64, f64>, pts: &[Point]) {
+    for p in pts {
+        map.insert(p.x.to_bits(), p.y);
+    }
+    while map.len() > CAP {
+        if let Some(oldest) = map.keys().next().copied() {
+            map.remove(&oldest);
+        } else {
+            break;
+        }
+    }
+}
+
+fn ring_push(ring: &mut SampleRing, pts: &[Point]) {
+    for p in pts {
+        ring.push(p.x, p.y);
+    }
+    ring.evict_to(CAP);
+}
+
+fn full_btree() -> BTreeMap<u64, f64> {
+    let mut map = BTreeMap::new();
+    for i in 0..CAP {
+        let p = sample(i);
+        map.insert(p.x.to_bits(), p.y);
+    }
+    map
+}
+
+fn full_ring() -> SampleRing {
+    let mut ring = SampleRing::with_capacity(CAP);
+    for i in 0..CAP {
+        let p = sample(i);
+        ring.push(p.x, p.y);
+    }
+    ring
+}
+
+/// Steady state: the window is full, so every batch evicts as many samples as it adds.
+fn insert(c: &mut Criterion) {
+    let batches: Vec<Vec<Point>> = (0..1000)
+        .map(|b| (0..BATCH).map(|i| sample(CAP + b * BATCH + i)).collect())
+        .collect();
+    let mut group = c.benchmark_group("insert_1000_batches_full_window");
+    group.bench_function("btree", |b| {
+        b.iter_batched_ref(
+            full_btree,
+            |map| {
+                for batch in &batches {
+                    btree_push(map, batch);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("ring", |b| {
+        b.iter_batched_ref(
+            full_ring,
+            |ring| {
+                for batch in &batches {
+                    ring_push(ring, batch);
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+/// Reading the last 10 s, as a hydrate or trigger frame does.
+fn range(c: &mut Criterion) {
+    let (lo, hi) = (sample(CAP - 10_000).x, sample(CAP - 1).x);
+    let map = full_btree();
+    let ring = full_ring();
+    let mut group = c.benchmark_group("read_last_10s");
+    group.bench_function("btree", |b| {
+        b.iter(|| {
+            let points: Vec<Point> = map
+                .range(lo.to_bits()..=hi.to_bits())
+                .map(|(t, y)| Point {
+                    x: f64::from_bits(*t),
+                    y: *y,
+                })
+                .collect();
+            black_box(points)
+        })
+    });
+    group.bench_function("ring", |b| {
+        b.iter(|| {
+            let points: Vec<Point> = ring.points(ring.range(lo, hi)).collect();
+            black_box(points)
+        })
+    });
+    group.finish();
+}
+
+/// The whole window as 1000 min/max buckets, from raw samples and from the tiles.
+fn envelope(c: &mut Criterion) {
+    let ring = full_ring();
+    let mut tiles = TilePyramid::default();
+    tiles.rebuild(&ring);
+    let bucket = CAP / 1000;
+    let mut group = c.benchmark_group("envelope_full_window_1000_buckets");
+    group.bench_function("raw", |b| {
+        b.iter(|| {
+            let mut out = Vec::with_capacity(2000);
+            let (mut min, mut max) = (sample(0), sample(0));
+            for (i, p) in ring.points(0..ring.len()).enumerate() {
+                if i % bucket == 0 || p.y < min.y {
+                    min = p;
+                }
+                if i % bucket == 0 || p.y > max.y {
+                    max = p;
+                }
+                if (i + 1) % bucket == 0 {
+                    out.extend([min, max]);
+                }
+            }
+            black_box(out)
+        })
+    });
+    group.bench_function("tiles", |b| {
+        b.iter(|| {
+            let mut out = Vec::with_capacity(2000);
+            tiles.envelope(&ring, 0..ring.len(), bucket, 0.0, &mut out);
+            black_box(out)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, insert, range, envelope);
+criterion_main!(benches);
@@ -1,4 +1,5 @@
 use crate::pipeline::manager::ProcessingManager;
+use crate::shared::{CaptureMemoryStatus, RetentionConfig};
 use crate::state::capture::{CaptureCommand, CaptureState};
 use std::sync::{Arc, Mutex};
 use tauri::State;
@@ -39,3 +40,17 @@ pub fn unpause_plot(plot_id: String, capture_state: State<CaptureState>) -> Resu
         .send(command)
         .map_err(|e| format!("Failed to send clear snapshot command: {}", e))
 }
+
+#[tauri::command]
+pub fn get_capture_memory(capture_state: State<CaptureState>) -> CaptureMemoryStatus {
+    capture_state.memory_status()
+}
+
+#[tauri::command]
+pub fn set_capture_retention(
+    config: RetentionConfig,
+    capture_state: State<CaptureState>,
+) -> Result<CaptureMemoryStatus, String> {
+    capture_state.set_retention(config)?;
+    Ok(capture_state.memory_status())
+}
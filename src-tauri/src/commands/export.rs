@@ -44,18 +44,9 @@ fn format_plot_data_to_csv_string(
     };
 
     for data_col_id in data_column_ids {
-        let port_manager = registry
-            .get(&data_col_id.port_url)
-            .ok_or("Port not found")?;
-        let devices_map = port_manager
-            .devices
-            .read()
-            .map_err(|e| format!("Lock error: {}", e))?;
-        let device_entry = devices_map
-            .get(&data_col_id.device_route)
+        let ui_device = registry
+            .find_ui_device(&data_col_id.port_url, &data_col_id.device_route.to_string())
             .ok_or("Device not found")?;
-        let device_tuple = device_entry.lock().unwrap();
-        let (_device, ui_device) = &*device_tuple;
         let ui_stream = ui_device
             .streams
             .iter()
@@ -229,7 +220,7 @@ pub async fn save_raw_plot_data_to_file(
         let raw_data_vecs =
             capture_state.get_data_across_sessions_for_keys(&data_column_ids, start_time, end_time);
         if raw_data_vecs.get(0).map_or(true, |v| v.is_empty()) {
-            return Err("The requested time range is no longer in the live data buffer. To save raw data, please act more quickly.".to_string());
+            return Err("The requested time range is no longer in the live data buffer. Raise the stream's retention or turn on Spill to disk under Capture Buffers to keep more history.".to_string());
         }
         let mut individual_plot_data = Vec::with_capacity(data_column_ids.len());
         for points in raw_data_vecs {
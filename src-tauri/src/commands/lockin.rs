@@ -0,0 +1,25 @@
+use crate::shared::{DataColumnId, LockInConfig, LockInInfo};
+use crate::state::proxy_register::ProxyRegister;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn create_lock_in(
+    config: LockInConfig,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<LockInInfo, String> {
+    registry.lockins.create(config)
+}
+
+#[tauri::command]
+pub fn remove_lock_in(
+    key: DataColumnId,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<(), String> {
+    registry.lockins.remove(&key)
+}
+
+#[tauri::command]
+pub fn get_lock_ins(registry: State<'_, Arc<ProxyRegister>>) -> Vec<LockInInfo> {
+    registry.lockins.list()
+}
@@ -0,0 +1,25 @@
+use crate::shared::{DataColumnId, MathChannelConfig, MathChannelInfo};
+use crate::state::proxy_register::ProxyRegister;
+use std::sync::Arc;
+use tauri::State;
+
+#[tauri::command]
+pub fn create_math_channel(
+    config: MathChannelConfig,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<MathChannelInfo, String> {
+    registry.math.create(config)
+}
+
+#[tauri::command]
+pub fn remove_math_channel(
+    key: DataColumnId,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<(), String> {
+    registry.math.remove(&key)
+}
+
+#[tauri::command]
+pub fn get_math_channels(registry: State<'_, Arc<ProxyRegister>>) -> Vec<MathChannelInfo> {
+    registry.math.list()
+}
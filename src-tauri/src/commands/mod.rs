@@ -1,5 +1,8 @@
 pub mod capture;
 pub mod export;
+pub mod lockin;
+pub mod math;
 pub mod pipeline;
 pub mod port;
+pub mod recording;
 pub mod settings;
@@ -2,7 +2,10 @@ use std::sync::{Arc, Mutex};
 
 use crate::{
     pipeline::manager::ProcessingManager,
-    shared::{DataColumnId, PipelineId, PlotData, SharedPlotConfig, ColumnStatistics},
+    shared::{
+        AllanStatus, ColumnStatistics, DataColumnId, HistogramStatus, PipelineId, PlotData,
+        SharedPlotConfig, SpectrogramData, SpectrumStatus, TriggerStatus,
+    },
 };
 use tauri::{ipc::Channel, State};
 
@@ -24,6 +27,46 @@ pub fn destroy_plot_pipeline(
     Ok(())
 }
 
+#[tauri::command]
+pub fn get_spectrum_status(
+    plot_id: String,
+    manager: State<Arc<Mutex<ProcessingManager>>>,
+) -> Vec<SpectrumStatus> {
+    manager.lock().unwrap().get_spectrum_status(&plot_id)
+}
+
+#[tauri::command]
+pub fn get_allan_status(
+    plot_id: String,
+    manager: State<Arc<Mutex<ProcessingManager>>>,
+) -> Vec<AllanStatus> {
+    manager.lock().unwrap().get_allan_status(&plot_id)
+}
+
+#[tauri::command]
+pub fn get_histogram_status(
+    plot_id: String,
+    manager: State<Arc<Mutex<ProcessingManager>>>,
+) -> Vec<HistogramStatus> {
+    manager.lock().unwrap().get_histogram_status(&plot_id)
+}
+
+#[tauri::command]
+pub fn get_trigger_status(
+    plot_id: String,
+    manager: State<Arc<Mutex<ProcessingManager>>>,
+) -> Option<TriggerStatus> {
+    manager.lock().unwrap().get_trigger_status(&plot_id)
+}
+
+#[tauri::command]
+pub fn rearm_trigger(
+    plot_id: String,
+    manager: State<Arc<Mutex<ProcessingManager>>>,
+) -> Result<(), String> {
+    manager.lock().unwrap().rearm_trigger(&plot_id)
+}
+
 #[tauri::command]
 pub fn create_statistics_provider(
     source_key: DataColumnId,
@@ -49,6 +92,19 @@ pub async fn listen_to_plot_data(
     Ok(())
 }
 
+#[tauri::command]
+pub async fn listen_to_spectrogram_data(
+    plot_id: String,
+    on_event: Channel<SpectrogramData>,
+    manager: tauri::State<'_, Arc<Mutex<ProcessingManager>>>,
+) -> Result<(), String> {
+    manager
+        .lock()
+        .unwrap()
+        .register_spectrogram_channel(plot_id, on_event);
+    Ok(())
+}
+
 #[tauri::command]
 pub async fn listen_to_statistics(
     id: PipelineId,
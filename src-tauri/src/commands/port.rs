@@ -1,4 +1,5 @@
-use crate::shared::{DataColumnId, PortState};
+use crate::proxy::tcp_proxy::DEFAULT_BIND;
+use crate::shared::{DataColumnId, PortState, TcpProxyStatus};
 use crate::state::capture::CaptureState;
 use crate::state::proxy_register::ProxyRegister;
 use std::sync::Arc;
@@ -10,7 +11,7 @@ pub fn connect_to_port(
     port_url: String,
     registry: State<'_, Arc<ProxyRegister>>,
 ) -> Result<(), String> {
-    registry.ensure(port_url.clone());
+    registry.ensure_manual(port_url.clone());
     if let Some(pm) = registry.get(&port_url) {
         if matches!(*pm.state.lock().unwrap(), PortState::Error(_)) {
             pm.connect();
@@ -42,11 +43,9 @@ pub fn confirm_selection(
         "[{}] Confirming selection with child routes: {:?}",
         port_url, children_routes
     );
-    let port_manager = registry
-        .get(&port_url)
+    let current_state = registry
+        .port_state(&port_url)
         .ok_or_else(|| format!("Could not find PortManager for URL: {}", port_url))?;
-
-    let current_state = port_manager.state.lock().unwrap().clone();
     if !matches!(current_state, PortState::Streaming) {
         return Err(format!(
             "Cannot confirm selection: port '{}' is not streaming. Current state: {:?}",
@@ -58,19 +57,15 @@ pub fn confirm_selection(
     let mut all_selected_routes = children_routes;
     all_selected_routes.push("".to_string());
 
-    let devices_map = port_manager
-        .devices
-        .read()
-        .map_err(|e| format!("Failed to access device list: {}", e))?;
+    let port_devices = registry.port_ui_devices(&port_url);
 
     for route_str in all_selected_routes {
         let route = match DeviceRoute::from_str(&route_str) {
             Ok(r) => r,
             Err(_) => continue,
         };
-        if let Some(device_entry) = devices_map.get(&route) {
-            let device_tuple = device_entry.lock().unwrap();
-            let (_device, cached_ui_device) = &*device_tuple;
+        let route_key = route.to_string();
+        if let Some(cached_ui_device) = port_devices.iter().find(|d| d.route == route_key) {
             for stream in &cached_ui_device.streams {
                 for column in &stream.columns {
                     let key = DataColumnId {
@@ -103,8 +98,76 @@ pub fn confirm_selection(
     registry
         .active_selections
         .insert(port_url.clone(), keys_to_activate);
-    registry.set_selected_port(Some(port_url.clone()));
-    registry.shutdown_all_except(&port_url);
-    
+    registry.select_port(port_url);
+
+    Ok(())
+}
+
+/// Stops streaming a previously confirmed port; other selected ports keep running.
+#[tauri::command]
+pub fn deselect_port(
+    port_url: String,
+    capture: State<CaptureState>,
+    registry: State<Arc<ProxyRegister>>,
+) -> Result<(), String> {
+    if !registry.deselect_port(&port_url) {
+        return Err(format!("Port '{}' is not selected", port_url));
+    }
+    capture
+        .inner
+        .command_tx
+        .send(crate::state::capture::CaptureCommand::SetActiveColumns {
+            port_url: port_url.clone(),
+            keys_for_port: Vec::new(),
+        })
+        .map_err(|e| e.to_string())?;
+    registry.active_selections.remove(&port_url);
+    println!("[{}] Deselected.", port_url);
+    registry.shutdown_unselected();
     Ok(())
 }
+
+/// The device dialog reports when it opens and closes; see `ProxyRegister::set_discovery_open`.
+#[tauri::command]
+pub fn set_discovery_open(open: bool, registry: State<Arc<ProxyRegister>>) {
+    registry.set_discovery_open(open);
+}
+
+#[tauri::command]
+pub fn get_selected_ports(registry: State<Arc<ProxyRegister>>) -> Vec<String> {
+    registry.selected_ports()
+}
+
+/// Shares a connected port with `tio-proxy` clients on `bind` (default `127.0.0.1:7855`).
+#[tauri::command]
+pub fn set_tcp_proxy(
+    port_url: String,
+    enabled: bool,
+    bind: Option<String>,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<TcpProxyStatus, String> {
+    let pm = registry
+        .get(&port_url)
+        .ok_or_else(|| format!("Port '{}' not found", port_url))?;
+    let bind = if enabled {
+        let bind = bind.unwrap_or_else(|| DEFAULT_BIND.to_string());
+        Some(
+            bind.parse()
+                .map_err(|e| format!("Invalid bind address '{}': {}", bind, e))?,
+        )
+    } else {
+        None
+    };
+    pm.set_tcp_proxy(bind)
+}
+
+#[tauri::command]
+pub fn get_tcp_proxy_status(
+    port_url: String,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<TcpProxyStatus, String> {
+    let pm = registry
+        .get(&port_url)
+        .ok_or_else(|| format!("Port '{}' not found", port_url))?;
+    Ok(pm.tcp_proxy_status())
+}
@@ -0,0 +1,151 @@
+use crate::recording::recorder::Recorder;
+use crate::pipeline::manager::ProcessingManager;
+use crate::proxy::tio_server::{ServeOptions, TioServer};
+use crate::recording::replay::ReplayPort;
+use crate::recording::session;
+use crate::shared::{RecordingStatus, ReplayStatus};
+use crate::state::capture::CaptureState;
+use crate::state::proxy_register::ProxyRegister;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tauri::State;
+
+#[tauri::command]
+pub fn start_recording(
+    path: String,
+    recorder: State<'_, Arc<Recorder>>,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<RecordingStatus, String> {
+    recorder.start(PathBuf::from(path), registry.ui_devices())
+}
+
+#[tauri::command]
+pub fn stop_recording(recorder: State<'_, Arc<Recorder>>) -> Result<RecordingStatus, String> {
+    recorder.stop()
+}
+
+#[tauri::command]
+pub fn get_recording_status(recorder: State<'_, Arc<Recorder>>) -> RecordingStatus {
+    recorder.status()
+}
+
+fn replay(registry: &ProxyRegister, url: &str) -> Result<Arc<ReplayPort>, String> {
+    registry
+        .get_replay(url)
+        .ok_or_else(|| format!("Recording '{}' is not open.", url))
+}
+
+/// Opens a log for playback and returns its `file://` port URL.
+#[tauri::command]
+pub fn open_recording(
+    path: String,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<String, String> {
+    registry.open_replay(PathBuf::from(path))
+}
+
+#[tauri::command]
+pub fn close_recording(
+    port_url: String,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<(), String> {
+    registry.close_recording(&port_url)
+}
+
+#[tauri::command]
+pub fn replay_play(port_url: String, registry: State<'_, Arc<ProxyRegister>>) -> Result<(), String> {
+    replay(&registry, &port_url)?.play();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn replay_pause(
+    port_url: String,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<(), String> {
+    replay(&registry, &port_url)?.pause();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn replay_seek(
+    port_url: String,
+    position: f64,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<(), String> {
+    replay(&registry, &port_url)?.seek(position);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn replay_set_speed(
+    port_url: String,
+    speed: f64,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<(), String> {
+    replay(&registry, &port_url)?.set_speed(speed)
+}
+
+#[tauri::command]
+pub fn get_replay_status(
+    port_url: String,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<ReplayStatus, String> {
+    Ok(replay(&registry, &port_url)?.status())
+}
+
+#[tauri::command]
+pub fn save_session(
+    path: String,
+    capture: State<'_, CaptureState>,
+    registry: State<'_, Arc<ProxyRegister>>,
+    manager: State<'_, Arc<Mutex<ProcessingManager>>>,
+) -> Result<(), String> {
+    session::save_current(&PathBuf::from(path), &capture, &registry, &manager)
+}
+
+/// Loads a session file and returns the URLs of its ports; its plots arrive through the
+/// `session-restored` event.
+#[tauri::command]
+pub fn restore_session(
+    path: String,
+    registry: State<'_, Arc<ProxyRegister>>,
+) -> Result<Vec<String>, String> {
+    Ok(registry.restore_session(PathBuf::from(path))?.urls.clone())
+}
+
+/// Serves a log as a TIO device on a local TCP port and returns its `tcp://` URL.
+#[tauri::command]
+pub fn start_tio_server(
+    path: String,
+    bind: Option<String>,
+    speed: Option<f64>,
+    looped: Option<bool>,
+    server: State<'_, Arc<Mutex<Option<TioServer>>>>,
+) -> Result<String, String> {
+    let mut options = ServeOptions::default();
+    if let Some(bind) = bind {
+        options.bind = bind
+            .parse()
+            .map_err(|e| format!("Invalid bind address '{}': {}", bind, e))?;
+    }
+    options.speed = speed.unwrap_or(options.speed);
+    options.looped = looped.unwrap_or(options.looped);
+
+    let mut slot = server.lock().unwrap();
+    if let Some(previous) = slot.take() {
+        previous.stop();
+    }
+    let started = TioServer::start(PathBuf::from(&path), options)
+        .map_err(|e| format!("Failed to serve '{}': {}", path, e))?;
+    let url = format!("tcp://{}", started.local_addr());
+    *slot = Some(started);
+    Ok(url)
+}
+
+#[tauri::command]
+pub fn stop_tio_server(server: State<'_, Arc<Mutex<Option<TioServer>>>>) {
+    if let Some(running) = server.lock().unwrap().take() {
+        running.stop();
+    }
+}
@@ -8,38 +8,7 @@ use crate::state::proxy_register::ProxyRegister;
 
 #[tauri::command]
 pub fn get_all_devices(registry: State<Arc<ProxyRegister>>) -> Vec<UiDevice> {
-    let mut all_devices = Vec::new();
-
-    for entry in registry.ports.iter() {
-        let port_manager = entry.value();
-
-        let devices_map = match port_manager.devices.read() {
-            Ok(guard) => guard,
-            Err(e) => {
-                eprintln!(
-                    "[{}] Could not acquire device lock for state hydration (poisoned: {}). Skipping.",
-                    port_manager.url, e
-                );
-                continue;
-            }
-        };
-
-        for device_entry in devices_map.values() {
-            let device_tuple = match device_entry.lock() {
-                Ok(guard) => guard,
-                Err(e) => {
-                    eprintln!(
-                        "[{}] A device lock was poisoned during state hydration (poisoned: {}). Skipping device.",
-                        port_manager.url, e
-                    );
-                    continue;
-                }
-            };
-
-            let (_device, ui_device) = &*device_tuple;
-            all_devices.push(ui_device.clone());
-        }
-    }
+    let all_devices = registry.ui_devices();
 
     println!(
         "[Command] get_all_devices returning {} devices.",
@@ -53,6 +22,11 @@ pub fn get_port_state(
     port_url: String,
     registry: State<'_, Arc<ProxyRegister>>,
 ) -> Result<PortState, String> {
+    if !registry.ports.contains_key(&port_url) {
+        if let Some(state) = registry.port_state(&port_url) {
+            return Ok(state);
+        }
+    }
     if let Some(port_manager) = registry.get(&port_url) {
         match port_manager.state.lock() {
             Ok(guard) => Ok(guard.clone()),
@@ -2,6 +2,7 @@ pub mod commands;
 pub mod menu;
 pub mod pipeline;
 pub mod proxy;
+pub mod recording;
 pub mod shared;
 pub mod state;
 pub mod util;
@@ -9,15 +9,63 @@ use mimalloc::MiMalloc;
 #[global_allocator]
 static GLOBAL: MiMalloc = MiMalloc;
 
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 use tauri::Manager;
 
 use trendline_lib::pipeline::manager::ProcessingManager;
+use trendline_lib::proxy::tio_server::{ServeOptions, TioServer};
+use trendline_lib::recording::recorder::Recorder;
 use trendline_lib::state::capture::CaptureState;
 use trendline_lib::state::proxy_register::ProxyRegister;
 use trendline_lib::{commands, menu, proxy};
 
+const SERVE_USAGE: &str =
+    "usage: trendline --serve <log.tlrec> [--bind 127.0.0.1:7855] [--speed 1.0] [--loop]";
+
+/// `--serve` runs without a window: the log is exposed as a TIO device until it ends (or forever
+/// with `--loop`).
+fn serve_headless(args: &[String]) -> Result<(), String> {
+    let mut path: Option<PathBuf> = None;
+    let mut options = ServeOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--serve" => path = Some(PathBuf::from(value()?)),
+            "--bind" => {
+                let bind = value()?;
+                options.bind = bind
+                    .parse()
+                    .map_err(|e| format!("Invalid bind address '{}': {}", bind, e))?;
+            }
+            "--speed" => {
+                let speed = value()?;
+                options.speed = speed
+                    .parse()
+                    .map_err(|_| format!("Invalid speed '{}'", speed))?;
+            }
+            "--loop" => options.looped = true,
+            other => return Err(format!("Unknown argument '{}'", other)),
+        }
+    }
+    let path = path.ok_or_else(|| "--serve needs a log file".to_string())?;
+    let server = TioServer::start(path.clone(), options)
+        .map_err(|e| format!("Failed to serve '{}': {}", path.to_string_lossy(), e))?;
+    server.wait();
+    Ok(())
+}
+
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--serve") {
+        if let Err(e) = serve_headless(&args) {
+            eprintln!("{}\n{}", e, SERVE_USAGE);
+            std::process::exit(2);
+        }
+        return;
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_prevent_default::debug())
         .plugin(tauri_plugin_opener::init())
@@ -35,10 +83,13 @@ fn main() {
             let capture = CaptureState::new();
             let registry = Arc::new(ProxyRegister::new(app.handle().clone(), capture.clone()));
             let processing_manager = ProcessingManager::new_with_ticker(capture.clone());
+            let recorder = Arc::new(Recorder::new(capture.clone(), app.handle().clone()));
 
             app.manage(capture);
             app.manage(registry.clone());
             app.manage(processing_manager);
+            app.manage(recorder);
+            app.manage(Arc::new(Mutex::new(None::<TioServer>)));
 
             proxy::discovery::spawn(registry);
             Ok(())
@@ -46,22 +97,43 @@ fn main() {
         .invoke_handler(tauri::generate_handler![
             // --- Port Management Commands ---
             commands::port::confirm_selection,
+            commands::port::deselect_port,
+            commands::port::set_discovery_open,
+            commands::port::get_selected_ports,
             commands::port::connect_to_port,
             commands::port::refresh_port,
+            commands::port::set_tcp_proxy,
+            commands::port::get_tcp_proxy_status,
             // --- Capture Management Commands ---
             commands::capture::pause_plot,
             commands::capture::unpause_plot,
+            commands::capture::get_capture_memory,
+            commands::capture::set_capture_retention,
             // --- Pipeline Commands ---
             commands::pipeline::update_plot_pipeline,
             commands::pipeline::destroy_plot_pipeline,
             commands::pipeline::create_statistics_provider,
             commands::pipeline::destroy_processor,
             commands::pipeline::listen_to_plot_data,
+            commands::pipeline::listen_to_spectrogram_data,
+            commands::pipeline::get_spectrum_status,
+            commands::pipeline::get_allan_status,
+            commands::pipeline::get_histogram_status,
+            commands::pipeline::get_trigger_status,
+            commands::pipeline::rearm_trigger,
             commands::pipeline::listen_to_statistics,
             commands::pipeline::reset_by_pipeline_id,
             commands::pipeline::reset_by_column,
             commands::pipeline::reset_by_stream,
             commands::pipeline::reset_by_device,
+            // --- Math Channel Commands ---
+            commands::math::create_math_channel,
+            commands::math::remove_math_channel,
+            commands::math::get_math_channels,
+            // --- Lock-in Commands ---
+            commands::lockin::create_lock_in,
+            commands::lockin::remove_lock_in,
+            commands::lockin::get_lock_ins,
             // --- Pipeline Management Commands ---
             commands::settings::get_all_devices,
             commands::settings::get_port_state,
@@ -70,6 +142,21 @@ fn main() {
             commands::export::export_plot_data_to_clipboard,
             commands::export::save_plot_data_to_file,
             commands::export::save_raw_plot_data_to_file,
+            // --- Recording Commands ---
+            commands::recording::start_recording,
+            commands::recording::stop_recording,
+            commands::recording::get_recording_status,
+            commands::recording::open_recording,
+            commands::recording::close_recording,
+            commands::recording::replay_play,
+            commands::recording::replay_pause,
+            commands::recording::replay_seek,
+            commands::recording::replay_set_speed,
+            commands::recording::get_replay_status,
+            commands::recording::save_session,
+            commands::recording::restore_session,
+            commands::recording::start_tio_server,
+            commands::recording::stop_tio_server,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -7,6 +7,13 @@ use tauri::{
 };
 use tauri_plugin_dialog::DialogExt;
 
+use crate::pipeline::manager::ProcessingManager;
+use crate::proxy::tcp_proxy;
+use crate::recording::{format, recorder::Recorder, session};
+use crate::state::capture::CaptureState;
+use crate::state::proxy_register::ProxyRegister;
+use std::sync::{Arc, Mutex};
+
 pub fn create_app_menu<R: Runtime>(app_handle: &AppHandle<R>) -> tauri::Result<Menu<R>> {
     let about_metadata = AboutMetadataBuilder::new()
         .name(Some("Trendline"))
@@ -67,6 +74,7 @@ pub fn create_app_menu<R: Runtime>(app_handle: &AppHandle<R>) -> tauri::Result<M
                 .accelerator("CmdOrCtrl+Space")
                 .build(app_handle)?,
         )
+        .item(&MenuItemBuilder::with_id("toggle_tcp_proxy", "Start TCP Proxy").build(app_handle)?)
         .separator()
         .item(&MenuItemBuilder::with_id("connect_device", "Connect Device...").build(app_handle)?)
         .item(&MenuItemBuilder::with_id("rpc_settings", "RPC Settings...").build(app_handle)?)
@@ -87,25 +95,94 @@ pub fn handle_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
     let window = app.get_webview_window("main").unwrap();
     match event.id().as_ref() {
         "open_recording" => {
-            app.dialog().file().pick_file(move |path_buf| {
-                if let Some(path) = path_buf {
+            let registry = app.state::<Arc<ProxyRegister>>().inner().clone();
+            app.dialog()
+                .file()
+                .add_filter("Trendline Recording", &[format::EXTENSION, session::EXTENSION])
+                .pick_file(move |path_buf| {
+                    let Some(path) = path_buf else { return };
                     println!("File selected for opening: {}", path.to_string());
-                    window.emit("file-opened", path.to_string()).unwrap();
-                }
-            });
+                    let path = match path.into_path() {
+                        Ok(path) => path,
+                        Err(e) => {
+                            eprintln!("[Menu] Failed to resolve recording path: {}", e);
+                            return;
+                        }
+                    };
+                    let is_session = path
+                        .extension()
+                        .is_some_and(|ext| ext == session::EXTENSION);
+                    if is_session {
+                        if let Err(e) = registry.restore_session(path) {
+                            eprintln!("[Menu] Failed to restore session: {}", e);
+                        }
+                        return;
+                    }
+                    match registry.open_replay(path) {
+                        Ok(url) => window.emit("file-opened", url).unwrap(),
+                        Err(e) => eprintln!("[Menu] Failed to open recording: {}", e),
+                    }
+                });
         }
         "save_recording" => {
-            // Using the new dialog plugin API
+            let capture = app.state::<CaptureState>().inner().clone();
+            let registry = app.state::<Arc<ProxyRegister>>().inner().clone();
+            let manager = app.state::<Arc<Mutex<ProcessingManager>>>().inner().clone();
             app.dialog()
                 .file()
-                .add_filter("Trendline Recording", &["json"])
-                .set_file_name("recording.json")
+                .add_filter("Trendline Recording", &[session::EXTENSION])
+                .set_file_name(format!("recording.{}", session::EXTENSION))
                 .save_file(move |path_buf| {
-                    if let Some(path) = path_buf {
-                        println!("File selected for saving: {}", path.to_string());
+                    let Some(path) = path_buf else { return };
+                    println!("File selected for saving: {}", path.to_string());
+                    let result = path.into_path().map_err(|e| e.to_string()).and_then(|path| {
+                        session::save_current(&path, &capture, &registry, &manager)
+                    });
+                    if let Err(e) = result {
+                        eprintln!("[Menu] Failed to save session: {}", e);
                     }
                 });
         }
+        "toggle_logging" => {
+            let recorder = app.state::<Arc<Recorder>>().inner().clone();
+            if recorder.is_recording() {
+                if let Err(e) = recorder.stop() {
+                    eprintln!("[Menu] Failed to stop recording: {}", e);
+                }
+                return;
+            }
+            let registry = app.state::<Arc<ProxyRegister>>().inner().clone();
+            app.dialog()
+                .file()
+                .add_filter("Trendline Log", &[format::EXTENSION])
+                .set_file_name(format!("session.{}", format::EXTENSION))
+                .save_file(move |path_buf| {
+                    let Some(path) = path_buf else { return };
+                    match path.into_path() {
+                        Ok(path) => {
+                            if let Err(e) = recorder.start(path, registry.ui_devices()) {
+                                eprintln!("[Menu] Failed to start recording: {}", e);
+                            }
+                        }
+                        Err(e) => eprintln!("[Menu] Failed to resolve log path: {}", e),
+                    }
+                });
+        }
+        "toggle_tcp_proxy" => {
+            let registry = app.state::<Arc<ProxyRegister>>();
+            let Some(pm) = registry.selected().and_then(|url| registry.get(&url)) else {
+                eprintln!("[Menu] No connected port to proxy.");
+                return;
+            };
+            let bind = if pm.tcp_proxy_status().address.is_some() {
+                None
+            } else {
+                tcp_proxy::DEFAULT_BIND.parse().ok()
+            };
+            if let Err(e) = pm.set_tcp_proxy(bind) {
+                eprintln!("[Menu] {}", e);
+            }
+        }
         // ... other handlers remain the same
         _ => {}
     }
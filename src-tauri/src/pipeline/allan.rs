@@ -0,0 +1,665 @@
+//! Allan deviation of one column, for the stability (τ versus σ) view.
+//!
+//! Samples are kept on the column's sample grid; every dropped sample, NaN or session change
+//! leaves a NaN, and no average that covers one is used. Long histories are pre-averaged in pairs
+//! (which is what the deviation does at twice the period anyway), so `MAX_VALUES` bounds memory
+//! and `tau0` grows instead.
+//!
+//! A live column is accumulated from capture for `window_seconds`, well past the capture buffer.
+//! A column of an open recording is read from the whole `.tlrec` log on a loader thread, which
+//! publishes partial results as it goes.
+use super::{Pipeline, PipelineCommand};
+use crate::recording::reader::{LogEvent, LogReader};
+use crate::recording::replay;
+use crate::shared::{
+    AllanConfig, AllanDeviation, AllanSource, AllanStatus, DataColumnId, PipelineId, PlotData,
+    Point,
+};
+use crate::state::capture::{BatchedData, CaptureState};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Values kept before the history is pre-averaged in pairs.
+const MAX_VALUES: usize = 1 << 22;
+/// How often the deviation is recomputed from live data, or published while loading a log.
+const RECOMPUTE_INTERVAL: Duration = Duration::from_secs(1);
+/// Live recomputes are also spaced by this multiple of the time the last one took, so a long
+/// window doesn't keep a core busy.
+const RECOMPUTE_DUTY: u32 = 10;
+
+/// A column's samples on its sample grid, pre-averaged by `factor`.
+struct Accumulator {
+    rate: f64,
+    /// Raw samples kept for a live window; `None` keeps everything (up to pre-averaging).
+    capacity: Option<usize>,
+    factor: usize,
+    pending_sum: f64,
+    pending_count: usize,
+    values: VecDeque<f64>,
+    last_time: Option<f64>,
+    missing: u64,
+}
+
+impl Accumulator {
+    fn new(rate: f64, window_seconds: Option<f64>) -> Self {
+        Self {
+            rate,
+            capacity: window_seconds.map(|w| ((w * rate).ceil() as usize).max(2)),
+            factor: 1,
+            pending_sum: 0.0,
+            pending_count: 0,
+            values: VecDeque::new(),
+            last_time: None,
+            missing: 0,
+        }
+    }
+
+    fn clear(&mut self) {
+        *self = Self {
+            capacity: self.capacity,
+            ..Self::new(self.rate, None)
+        };
+    }
+
+    fn tau0(&self) -> f64 {
+        self.factor as f64 / self.rate
+    }
+
+    fn push(&mut self, p: Point) {
+        if let Some(last) = self.last_time {
+            let periods = ((p.x - last) * self.rate).round();
+            if periods == 0.0 {
+                return;
+            }
+            if periods > 1.0 {
+                // One NaN is enough: averages are contiguous, so none can reach across it.
+                self.missing += periods as u64 - 1;
+                self.push_raw(f64::NAN);
+            } else if periods < 0.0 {
+                self.separate();
+            }
+        }
+        self.last_time = Some(p.x);
+        if !p.y.is_finite() {
+            self.missing += 1;
+        }
+        self.push_raw(p.y);
+    }
+
+    /// Breaks the grid, e.g. where a new session starts.
+    fn separate(&mut self) {
+        if self.values.back().is_some_and(|v| v.is_finite()) || self.pending_count > 0 {
+            self.push_raw(f64::NAN);
+        }
+    }
+
+    fn push_raw(&mut self, y: f64) {
+        // NaN propagates through the sum, so a block with a gap in it is a gap too.
+        self.pending_sum += y;
+        self.pending_count += 1;
+        if self.pending_count < self.factor {
+            return;
+        }
+        self.values.push_back(self.pending_sum / self.factor as f64);
+        self.pending_sum = 0.0;
+        self.pending_count = 0;
+
+        if let Some(capacity) = self.capacity {
+            let kept = capacity.div_ceil(self.factor);
+            while self.values.len() > kept {
+                self.values.pop_front();
+            }
+        }
+        if self.values.len() > MAX_VALUES {
+            if self.values.len() % 2 == 1 {
+                self.values.pop_front();
+            }
+            let halved: VecDeque<f64> = self
+                .values
+                .make_contiguous()
+                .chunks_exact(2)
+                .map(|pair| 0.5 * (pair[0] + pair[1]))
+                .collect();
+            self.values = halved;
+            self.factor *= 2;
+        }
+    }
+
+    fn span_seconds(&self) -> f64 {
+        let valid = self.values.iter().filter(|v| v.is_finite()).count();
+        valid as f64 * self.tau0()
+    }
+
+    fn output(&mut self, config: &AllanConfig) -> PlotData {
+        let tau0 = self.tau0();
+        let (taus, sigmas) = deviations(self.values.make_contiguous(), tau0, config);
+        if taus.is_empty() {
+            return PlotData::empty();
+        }
+        PlotData {
+            timestamps: taus,
+            series_data: vec![sigmas],
+        }
+    }
+}
+
+/// (τ, σ) of `values`, sampled every `tau0`, at log-spaced multiples of `tau0`.
+fn deviations(values: &[f64], tau0: f64, config: &AllanConfig) -> (Vec<f64>, Vec<f64>) {
+    let n = values.len();
+    let max_m = match config.deviation {
+        AllanDeviation::Overlapping => n.saturating_sub(1) / 2,
+        AllanDeviation::Modified | AllanDeviation::Time => n.saturating_sub(1) / 3,
+    };
+    if max_m == 0 {
+        return (Vec::new(), Vec::new());
+    }
+
+    // Prefix sums of the mean-removed values, and of the NaNs among them. Removing the mean
+    // keeps the sums small, so differences of them keep their precision.
+    let finite = values.iter().filter(|v| v.is_finite());
+    let count = finite.clone().count();
+    let mean = finite.sum::<f64>() / count.max(1) as f64;
+    let mut sums = Vec::with_capacity(n + 1);
+    let mut gaps = Vec::with_capacity(n + 1);
+    let (mut s, mut g) = (0.0, 0u32);
+    sums.push(s);
+    gaps.push(g);
+    for &v in values {
+        if v.is_finite() {
+            s += v - mean;
+        } else {
+            g += 1;
+        }
+        sums.push(s);
+        gaps.push(g);
+    }
+    // Mean of values[j..j + m], if it has no gaps.
+    let average =
+        |j: usize, m: usize| (gaps[j + m] == gaps[j]).then(|| (sums[j + m] - sums[j]) / m as f64);
+
+    let per_decade = config.taus_per_decade.max(1) as f64;
+    let mut taus = Vec::new();
+    let mut sigmas = Vec::new();
+    let mut last_m = 0;
+    for k in 0.. {
+        let m = 10f64.powf(k as f64 / per_decade).round() as usize;
+        if m > max_m {
+            break;
+        }
+        if m == last_m {
+            continue;
+        }
+        last_m = m;
+
+        let mut total = 0.0;
+        let mut terms = 0usize;
+        match config.deviation {
+            AllanDeviation::Overlapping => {
+                for j in 0..=n - 2 * m {
+                    if let (Some(a), Some(b)) = (average(j, m), average(j + m, m)) {
+                        total += (b - a).powi(2);
+                        terms += 1;
+                    }
+                }
+                total /= 2.0;
+            }
+            AllanDeviation::Modified | AllanDeviation::Time => {
+                // Running sum of m consecutive differences of adjacent averages.
+                let mut window = 0.0;
+                let mut valid = 0usize;
+                let difference = |i: usize| Some(average(i + m, m)? - average(i, m)?);
+                for i in 0..=n - 2 * m {
+                    match difference(i) {
+                        Some(d) => {
+                            window += d;
+                            valid += 1;
+                        }
+                        None => {
+                            window = 0.0;
+                            valid = 0;
+                        }
+                    }
+                    if valid > m {
+                        if let Some(d) = difference(i - m) {
+                            window -= d;
+                        }
+                        valid -= 1;
+                    }
+                    if valid == m {
+                        total += window.powi(2);
+                        terms += 1;
+                    }
+                }
+                total /= 2.0 * (m * m) as f64;
+            }
+        }
+        if terms == 0 {
+            continue;
+        }
+        let tau = m as f64 * tau0;
+        let sigma = (total / terms as f64).sqrt();
+        taus.push(tau);
+        sigmas.push(match config.deviation {
+            AllanDeviation::Time => tau / 3f64.sqrt() * sigma,
+            _ => sigma,
+        });
+    }
+    (taus, sigmas)
+}
+
+#[derive(Default)]
+struct Published {
+    output: PlotData,
+    status: Option<AllanStatus>,
+}
+
+pub struct AllanPipeline {
+    id: PipelineId,
+    key: DataColumnId,
+    config: AllanConfig,
+    source: AllanSource,
+    /// Created once the column's sampling rate is known.
+    accumulator: Option<Accumulator>,
+    capture: CaptureState,
+    published: Arc<Mutex<Published>>,
+    /// When the live deviation may next be recomputed.
+    next_compute: Option<Instant>,
+    /// Set to stop the recording loader when the pipeline goes away.
+    cancel: Arc<AtomicBool>,
+    loading: bool,
+}
+
+impl AllanPipeline {
+    pub fn new(key: DataColumnId, config: AllanConfig, capture: CaptureState) -> Self {
+        let source = if key.port_url.starts_with(replay::URL_SCHEME) {
+            AllanSource::Recording
+        } else {
+            AllanSource::Live
+        };
+        Self {
+            id: PipelineId(Uuid::new_v4()),
+            key,
+            config,
+            source,
+            accumulator: None,
+            capture,
+            published: Arc::new(Mutex::new(Published::default())),
+            next_compute: None,
+            cancel: Arc::new(AtomicBool::new(false)),
+            loading: false,
+        }
+    }
+
+    /// The live accumulator, created on first use once capture knows the column's rate.
+    fn live_accumulator(&mut self) -> Option<&mut Accumulator> {
+        if self.accumulator.is_none() {
+            let rate = self
+                .capture
+                .get_effective_sampling_rate(&self.key)
+                .filter(|r| *r > 0.0)?;
+            self.accumulator = Some(Accumulator::new(rate, Some(self.config.window_seconds)));
+        }
+        self.accumulator.as_mut()
+    }
+
+    fn publish(&mut self) {
+        let Some(accumulator) = self.accumulator.as_mut() else {
+            return;
+        };
+        let started = Instant::now();
+        publish(
+            &self.published,
+            &self.key,
+            self.source,
+            &self.config,
+            accumulator,
+        );
+        let cost = started.elapsed() * RECOMPUTE_DUTY;
+        self.next_compute = Some(started + cost.max(RECOMPUTE_INTERVAL));
+    }
+
+    fn start_loader(&mut self, capture: &CaptureState) {
+        if self.loading {
+            return;
+        }
+        self.loading = true;
+        let path = PathBuf::from(&self.key.port_url[replay::URL_SCHEME.len()..]);
+        let rate = capture.get_effective_sampling_rate(&self.key);
+        let key = self.key.clone();
+        let config = self.config.clone();
+        let published = self.published.clone();
+        let cancel = self.cancel.clone();
+        let spawned = thread::Builder::new()
+            .name(format!("allan-loader-{:?}", self.id))
+            .spawn(move || {
+                if let Err(e) = load_recording(path, key, rate, config, published, cancel) {
+                    eprintln!("[Allan] Failed to read recording: {}", e);
+                }
+            });
+        if let Err(e) = spawned {
+            eprintln!("[Allan] Failed to spawn loader thread: {}", e);
+        }
+    }
+}
+
+impl Drop for AllanPipeline {
+    fn drop(&mut self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+fn publish(
+    published: &Mutex<Published>,
+    key: &DataColumnId,
+    source: AllanSource,
+    config: &AllanConfig,
+    accumulator: &mut Accumulator,
+) {
+    let output = accumulator.output(config);
+    let status = AllanStatus {
+        key: key.clone(),
+        source,
+        span_seconds: accumulator.span_seconds(),
+        tau0: accumulator.tau0(),
+        missing_samples: u32::try_from(accumulator.missing).unwrap_or(u32::MAX),
+    };
+    let mut published = published.lock().unwrap();
+    published.output = output;
+    published.status = Some(status);
+}
+
+/// Reads every batch of `key` (re-homed under its replay URL) from the log at `path`.
+fn load_recording(
+    path: PathBuf,
+    key: DataColumnId,
+    rate: Option<f64>,
+    config: AllanConfig,
+    published: Arc<Mutex<Published>>,
+    cancel: Arc<AtomicBool>,
+) -> std::io::Result<()> {
+    let started = Instant::now();
+    let mut reader = LogReader::open(&path)?;
+    let route = key.device_route.to_string();
+    let mut rate = rate.filter(|r| *r > 0.0);
+    let mut accumulator: Option<Accumulator> = None;
+    let mut session = None;
+    let mut last_publish = Instant::now();
+
+    while let Some(event) = reader.next_event()? {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let batch = match event {
+            LogEvent::Device(device) => {
+                if rate.is_none() && device.route == route {
+                    rate = device
+                        .streams
+                        .iter()
+                        .find(|s| s.meta.stream_id == key.stream_id)
+                        .map(|s| s.effective_sampling_rate)
+                        .filter(|r| *r > 0.0);
+                }
+                continue;
+            }
+            LogEvent::Batch(batch) => batch,
+        };
+        let same_column = batch.key.device_route == key.device_route
+            && batch.key.stream_id == key.stream_id
+            && batch.key.column_index == key.column_index;
+        let Some(rate) = rate.filter(|_| same_column) else {
+            continue;
+        };
+        let accumulator = accumulator.get_or_insert_with(|| Accumulator::new(rate, None));
+        if session
+            .replace(batch.session_id)
+            .is_some_and(|s| s != batch.session_id)
+        {
+            accumulator.separate();
+        }
+        for p in batch.points {
+            accumulator.push(p);
+        }
+        if last_publish.elapsed() >= RECOMPUTE_INTERVAL {
+            publish(
+                &published,
+                &key,
+                AllanSource::Recording,
+                &config,
+                accumulator,
+            );
+            last_publish = Instant::now();
+        }
+    }
+
+    if let Some(accumulator) = accumulator.as_mut() {
+        publish(
+            &published,
+            &key,
+            AllanSource::Recording,
+            &config,
+            accumulator,
+        );
+        println!(
+            "[Allan] Read {:.0}s of {:?} from '{}' in {:.1}s",
+            accumulator.span_seconds(),
+            key,
+            path.to_string_lossy(),
+            started.elapsed().as_secs_f64()
+        );
+    }
+    Ok(())
+}
+
+impl Pipeline for AllanPipeline {
+    fn id(&self) -> PipelineId {
+        self.id
+    }
+
+    fn get_output(&self) -> PlotData {
+        self.published.lock().unwrap().output.clone()
+    }
+
+    fn allan_status(&self) -> Option<AllanStatus> {
+        self.published.lock().unwrap().status.clone()
+    }
+
+    fn process_batch(&mut self, batch: Arc<BatchedData>) {
+        if batch.key != self.key || self.source == AllanSource::Recording {
+            return;
+        }
+        let Some(accumulator) = self.live_accumulator() else {
+            return;
+        };
+        for p in batch.points.iter() {
+            accumulator.push(*p);
+        }
+        if self.next_compute.is_none_or(|t| Instant::now() >= t) {
+            self.publish();
+        }
+    }
+
+    fn process_command(&mut self, cmd: PipelineCommand, capture_state: &CaptureState) {
+        match cmd {
+            // A recording's log doesn't change when its replay seeks.
+            PipelineCommand::ResetSelf if self.source == AllanSource::Live => {
+                println!("[Allan Pipeline {:?}] Received ResetSelf command", self.id);
+                if let Some(accumulator) = self.accumulator.as_mut() {
+                    accumulator.clear();
+                }
+                *self.published.lock().unwrap() = Published::default();
+            }
+            PipelineCommand::Hydrate => {
+                println!("[Allan Pipeline {:?}] Received Hydrate command.", self.id);
+                if self.source == AllanSource::Recording {
+                    self.start_loader(capture_state);
+                    return;
+                }
+                // Left for `process_batch` to create if the rate isn't known yet.
+                self.accumulator = None;
+                let key = std::slice::from_ref(&self.key);
+                let Some(latest_time) = capture_state.get_latest_unified_timestamp(key) else {
+                    return;
+                };
+                let history = capture_state.get_data_across_sessions_for_keys(
+                    key,
+                    latest_time - self.config.window_seconds,
+                    latest_time,
+                );
+                let Some(accumulator) = self.live_accumulator() else {
+                    return;
+                };
+                for p in history.into_iter().next().unwrap_or_default() {
+                    accumulator.push(p);
+                }
+                self.publish();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::f64::consts::PI;
+
+    fn config(deviation: AllanDeviation) -> AllanConfig {
+        AllanConfig {
+            window_seconds: 0.0,
+            deviation,
+            taus_per_decade: 5,
+        }
+    }
+
+    /// Gaussian noise, by Box-Muller.
+    fn white_noise(n: usize, sigma: f64) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(3);
+        (0..n)
+            .map(|_| {
+                let u: f64 = 1.0 - rng.random::<f64>();
+                let v: f64 = rng.random();
+                sigma * (-2.0 * u.ln()).sqrt() * (2.0 * PI * v).cos()
+            })
+            .collect()
+    }
+
+    /// Slope of log σ against log τ, by least squares.
+    fn slope(taus: &[f64], sigmas: &[f64]) -> f64 {
+        let (x, y): (Vec<f64>, Vec<f64>) = taus
+            .iter()
+            .zip(sigmas)
+            .map(|(t, s)| (t.ln(), s.ln()))
+            .unzip();
+        let n = x.len() as f64;
+        let (mx, my) = (x.iter().sum::<f64>() / n, y.iter().sum::<f64>() / n);
+        let sxy: f64 = x.iter().zip(&y).map(|(a, b)| (a - mx) * (b - my)).sum();
+        let sxx: f64 = x.iter().map(|a| (a - mx).powi(2)).sum();
+        sxy / sxx
+    }
+
+    #[test]
+    fn white_noise_adev_falls_as_one_over_root_tau() {
+        let values = white_noise(1 << 16, 2.0);
+        let (taus, sigmas) = deviations(&values, 0.01, &config(AllanDeviation::Overlapping));
+        assert!((taus[0] - 0.01).abs() < 1e-12);
+        assert!((sigmas[0] / 2.0 - 1.0).abs() < 0.02, "{}", sigmas[0]);
+        // Long τ have few independent averages; fit up to m = 1000.
+        let fit = taus.iter().take_while(|t| **t <= 10.0).count();
+        let slope = slope(&taus[..fit], &sigmas[..fit]);
+        assert!((slope + 0.5).abs() < 0.03, "{}", slope);
+        for (tau, sigma) in taus.iter().zip(&sigmas).take(fit) {
+            let expected = 2.0 * (0.01 / tau).sqrt();
+            assert!(
+                (sigma / expected - 1.0).abs() < 0.15,
+                "τ {}: {}",
+                tau,
+                sigma
+            );
+        }
+    }
+
+    #[test]
+    fn white_noise_mdev_is_adev_over_root_two() {
+        let values = white_noise(1 << 16, 1.0);
+        let (taus, adev) = deviations(&values, 1.0, &config(AllanDeviation::Overlapping));
+        let (mod_taus, mdev) = deviations(&values, 1.0, &config(AllanDeviation::Modified));
+        // MDEV reaches τ up to n / 3 only.
+        assert_eq!(taus[..mod_taus.len()], mod_taus[..]);
+        assert!((mdev[0] - adev[0]).abs() < 1e-12);
+        for (i, tau) in mod_taus
+            .iter()
+            .enumerate()
+            .filter(|(_, t)| (10.0..=100.0).contains(*t))
+        {
+            let ratio = mdev[i] / adev[i];
+            assert!((ratio - 0.5f64.sqrt()).abs() < 0.05, "τ {}: {}", tau, ratio);
+        }
+    }
+
+    #[test]
+    fn linear_drift_gives_known_deviations() {
+        // y = a·t: adjacent τ-averages differ by a·τ, so ADEV = MDEV = a·τ/√2.
+        let (a, tau0) = (3.0, 0.5);
+        let values: Vec<f64> = (0..3000).map(|i| a * i as f64 * tau0).collect();
+        for deviation in [
+            AllanDeviation::Overlapping,
+            AllanDeviation::Modified,
+            AllanDeviation::Time,
+        ] {
+            let (taus, sigmas) = deviations(&values, tau0, &config(deviation.clone()));
+            assert!(!taus.is_empty());
+            for (tau, sigma) in taus.iter().zip(&sigmas) {
+                let mut expected = a * tau / 2f64.sqrt();
+                if deviation == AllanDeviation::Time {
+                    expected *= tau / 3f64.sqrt();
+                }
+                assert!(
+                    (sigma / expected - 1.0).abs() < 1e-9,
+                    "{:?} τ {}: {} vs {}",
+                    deviation,
+                    tau,
+                    sigma,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn averages_never_span_a_gap() {
+        // Two drifts with an offset between them: only averages across the NaN would see it.
+        let mut values: Vec<f64> = (0..1000).map(|i| i as f64).collect();
+        values.push(f64::NAN);
+        values.extend((0..1000).map(|i| 1e6 + i as f64));
+        let (taus, sigmas) = deviations(&values, 1.0, &config(AllanDeviation::Overlapping));
+        for (tau, sigma) in taus.iter().zip(&sigmas) {
+            assert!(
+                (sigma / (tau / 2f64.sqrt()) - 1.0).abs() < 1e-9,
+                "τ {}",
+                tau
+            );
+        }
+    }
+
+    #[test]
+    fn accumulator_marks_dropped_samples() {
+        let mut acc = Accumulator::new(10.0, None);
+        for i in [0, 1, 2, 5, 6] {
+            acc.push(Point {
+                x: i as f64 / 10.0,
+                y: 1.0,
+            });
+        }
+        assert_eq!(acc.missing, 2);
+        assert_eq!(acc.values.len(), 6);
+        assert!(acc.values[3].is_nan());
+        assert!((acc.span_seconds() - 0.5).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,147 @@
+//! Cross-spectral analysis between a reference column (x) and another column (y), fed by a
+//! `DetrendPipeline` on each.
+use super::spectral::{fill_gaps, FrameAverager, Welch};
+use super::{Pipeline, PipelineCommand};
+use crate::shared::{CrossQuantity, CrossSpectrumConfig, PipelineId, PlotData};
+use crate::state::capture::CaptureState;
+use crate::util::resample_linear;
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Inputs of a `CrossSpectralPipeline`, as passed to `Pipeline::process_paired_batch`.
+pub const REFERENCE_INPUT: usize = 0;
+pub const COLUMN_INPUT: usize = 1;
+
+pub struct CrossSpectralPipeline {
+    id: PipelineId,
+    window_seconds: f64,
+    quantity: CrossQuantity,
+    output: Arc<Mutex<PlotData>>,
+    welch: Welch,
+    averager: FrameAverager,
+    frequencies: Vec<f64>,
+    /// Latest detrended window from each input, with its sample rate.
+    latest: [Option<(PlotData, f64)>; 2],
+}
+
+impl CrossSpectralPipeline {
+    pub fn new(config: &CrossSpectrumConfig) -> Self {
+        Self {
+            id: PipelineId(Uuid::new_v4()),
+            window_seconds: config.window_seconds,
+            quantity: config.quantity,
+            output: Arc::new(Mutex::new(PlotData::empty())),
+            welch: Welch::new(config.psd.clone()),
+            averager: FrameAverager::new(config.psd.frame_averaging.clone()),
+            frequencies: Vec::new(),
+            latest: [None, None],
+        }
+    }
+
+    /// Puts both windows on the reference's sample grid over the span they share.
+    fn aligned(&self) -> Option<(Vec<f64>, Vec<f64>, f64)> {
+        let (Some((x, fs)), Some((y, fy))) = (&self.latest[0], &self.latest[1]) else {
+            return None;
+        };
+        let (tx, vx) = (&x.timestamps, x.series_data.first()?);
+        let (ty, vy) = (&y.timestamps, y.series_data.first()?);
+        if *fs <= 0.0 || *fy <= 0.0 || tx.len() != vx.len() || ty.len() != vy.len() {
+            return None;
+        }
+        let start = tx.first()?.max(*ty.first()?);
+        let end = tx.last()?.min(*ty.last()?);
+        if end <= start {
+            return None;
+        }
+
+        let slots = (((end - start) * fs).floor() as usize + 1)
+            .min((fs * self.window_seconds).ceil() as usize);
+        let grid: Vec<f64> = (0..slots)
+            .map(|i| end - (slots - 1 - i) as f64 / fs)
+            .collect();
+        let xg = fill_gaps(&resample_linear(tx, vx, &grid, *fs));
+        let yg = fill_gaps(&resample_linear(ty, vy, &grid, *fy));
+        Some((xg, yg, *fs))
+    }
+
+    fn compute(&mut self) {
+        let Some((x, y, fs)) = self.aligned() else {
+            return;
+        };
+        let Some(spectra) = self.welch.cross(&x, &y, fs) else {
+            *self.output.lock().unwrap() = PlotData::empty();
+            return;
+        };
+        if spectra.frequencies != self.frequencies {
+            self.averager.clear();
+            self.frequencies = spectra.frequencies;
+        }
+
+        // Averaged as one frame, so coherence comes from averaged spectra as it should.
+        let bins = self.frequencies.len();
+        let mut frame = Vec::with_capacity(4 * bins);
+        frame.extend_from_slice(&spectra.pxx);
+        frame.extend_from_slice(&spectra.pyy);
+        frame.extend(spectra.pxy.iter().map(|c| c.re));
+        frame.extend(spectra.pxy.iter().map(|c| c.im));
+        let frame = self.averager.push(frame);
+        let (pxx, rest) = frame.split_at(bins);
+        let (pyy, rest) = rest.split_at(bins);
+        let (re, im) = rest.split_at(bins);
+
+        let values = (0..bins)
+            .map(|i| quantity(self.quantity, pxx[i], pyy[i], re[i], im[i]))
+            .collect();
+        *self.output.lock().unwrap() = PlotData {
+            timestamps: self.frequencies.clone(),
+            series_data: vec![values],
+        };
+    }
+}
+
+fn quantity(kind: CrossQuantity, pxx: f64, pyy: f64, re: f64, im: f64) -> f64 {
+    let cross_power = re * re + im * im;
+    let coherence = if pxx > 0.0 && pyy > 0.0 {
+        (cross_power / (pxx * pyy)).min(1.0)
+    } else {
+        0.0
+    };
+    match kind {
+        CrossQuantity::Coherence => coherence,
+        CrossQuantity::CrossPsd => cross_power.sqrt(),
+        CrossQuantity::TransferMagnitude if pxx > 0.0 => cross_power.sqrt() / pxx,
+        CrossQuantity::TransferMagnitude => f64::NAN,
+        CrossQuantity::TransferPhase => im.atan2(re).to_degrees(),
+        CrossQuantity::IncoherentAsd => ((1.0 - coherence) * pyy).sqrt(),
+    }
+}
+
+impl Pipeline for CrossSpectralPipeline {
+    fn id(&self) -> PipelineId {
+        self.id
+    }
+
+    fn get_output(&self) -> PlotData {
+        self.output.lock().unwrap().clone()
+    }
+
+    fn process_paired_batch(&mut self, input: usize, batch: (PlotData, f64)) {
+        let Some(slot) = self.latest.get_mut(input) else {
+            return;
+        };
+        *slot = Some(batch);
+        // Both detrend stages hop at the same pace; one spectrum per reference window is enough.
+        if input == REFERENCE_INPUT {
+            self.compute();
+        }
+    }
+
+    fn process_command(&mut self, cmd: PipelineCommand, _capture: &CaptureState) {
+        if let PipelineCommand::ResetSelf = cmd {
+            println!("[Cross {:?}] Received ResetSelf command", self.id);
+            self.latest = [None, None];
+            self.averager.clear();
+            *self.output.lock().unwrap() = PlotData::empty();
+        }
+    }
+}
@@ -100,6 +100,17 @@ impl StreamingFpcsPipeline {
         self.window_min_point = Some(min_p);
     }
 
+    /// Clears the output and the algorithm state; capacity is kept.
+    fn reset(&mut self) {
+        self.output.lock().unwrap().clear();
+        self.counter = 0;
+        self.potential_point = None;
+        self.last_retained_flag = FpcsLastRetained::None;
+        self.window_max_point = None;
+        self.window_min_point = None;
+        self.last_processed_time = 0.0;
+    }
+
     /// Retains a point in the output buffer, managing capacity.
     fn retain_point(&mut self, p: Point) {
         let mut output = self.output.lock().unwrap();
@@ -150,14 +161,7 @@ impl Pipeline for StreamingFpcsPipeline {
         match cmd {
             PipelineCommand::ResetSelf => {
                 println!("[FPCS Pipeline {:?}] Received ResetSelf command", self.id);
-                self.output.lock().unwrap().clear();
-                self.capacity = self.capacity;
-                self.counter = 0;
-                self.potential_point = None;
-                self.last_retained_flag = FpcsLastRetained::None;
-                self.window_max_point = None;
-                self.window_min_point = None;
-                self.last_processed_time = 0.0;
+                self.reset();
             }
             PipelineCommand::Hydrate => {
                 println!("[FPCS Pipeline {:?}] Received Hydrate command.", self.id);
@@ -185,23 +189,21 @@ impl Pipeline for StreamingFpcsPipeline {
                 };
 
                 let start_time = latest_time - self.window_seconds;
-                let raw_data_vecs = capture_state.get_data_across_sessions_for_keys(
-                    &[self.source_key.clone()],
+                // Each bucket's extremes, as FPCS would keep them, straight from the buffer's
+                // tiles; a long window costs the points it shows, not the samples it spans.
+                let envelope = capture_state.get_envelope_across_sessions_for_keys(
+                    std::slice::from_ref(&self.source_key),
                     start_time,
                     latest_time,
+                    self.ratio,
                 );
-
-                if let Some(points) = raw_data_vecs.get(0) {
+                if let Some(points) = envelope.into_iter().next() {
                     if points.is_empty() {
                         return;
                     }
-                    println!(
-                        "[FPCS Pipeline {:?}] Backfilling with {} points.",
-                        self.id,
-                        points.len()
-                    );
+                    self.reset();
                     for point in points {
-                        self.process_point(*point);
+                        self.retain_point(point);
                     }
                     self.last_processed_time = latest_time;
                 }
@@ -209,4 +211,19 @@ impl Pipeline for StreamingFpcsPipeline {
             _ => {}
         }
     }
+
+    fn backfill(&mut self, points: Vec<Point>) {
+        println!(
+            "[FPCS Pipeline {:?}] Backfilling with {} points.",
+            self.id,
+            points.len()
+        );
+        self.reset();
+        for point in &points {
+            self.process_point(*point);
+        }
+        if let Some(last) = points.last() {
+            self.last_processed_time = last.x;
+        }
+    }
 }
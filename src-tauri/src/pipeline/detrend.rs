@@ -11,7 +11,8 @@ pub struct DetrendPipeline {
     id: PipelineId,
     source_key: DataColumnId,
     window_seconds: f64,
-    method: DetrendMethod,
+    /// `None` passes the windows on as they are.
+    method: Option<DetrendMethod>,
     output: Arc<Mutex<PlotData>>,
     subscribers: Vec<Sender<(PlotData, f64)>>,
     buffer: VecDeque<Point>,
@@ -24,6 +25,19 @@ const HOP_TIME_SECONDS: f64 = 0.066;
 
 impl DetrendPipeline {
     pub fn new(source_key: DataColumnId, window_seconds: f64, method: DetrendMethod) -> Self {
+        Self::with_method(source_key, window_seconds, Some(method))
+    }
+
+    /// Emits the raw windows, for views that need the signal's own level (XY).
+    pub fn raw(source_key: DataColumnId, window_seconds: f64) -> Self {
+        Self::with_method(source_key, window_seconds, None)
+    }
+
+    fn with_method(
+        source_key: DataColumnId,
+        window_seconds: f64,
+        method: Option<DetrendMethod>,
+    ) -> Self {
         Self {
             id: PipelineId(Uuid::new_v4()),
             source_key,
@@ -44,17 +58,17 @@ impl DetrendPipeline {
             return;
         }
 
+        let timestamps: Vec<f64> = block.iter().map(|p| p.x).collect();
         let y_values: Vec<f64> = block.iter().map(|p| p.y).collect();
 
-        let detrended_y = match self.method {
-            DetrendMethod::None => remove_mean(&y_values),
-            DetrendMethod::Linear => remove_linear_trend(&y_values),
-            DetrendMethod::Quadratic => remove_quadratic_trend(&y_values),
+        let y_values = match &self.method {
+            Some(method) => detrend(&timestamps, &y_values, method),
+            None => y_values,
         };
 
         let result_plot_data = PlotData {
-            timestamps: block.iter().map(|p| p.x).collect(),
-            series_data: vec![detrended_y],
+            timestamps,
+            series_data: vec![y_values],
         };
 
         *self.output.lock().unwrap() = result_plot_data.clone();
@@ -174,64 +188,69 @@ impl Pipeline for DetrendPipeline {
                     latest_time,
                 );
 
-                if let Some(mut points) = raw_data_vecs.into_iter().next() {
-                    if self.window_size_samples > 0 && points.len() > self.window_size_samples {
-                        points.drain(..points.len() - self.window_size_samples);
-                    }
-                    self.calculate_and_distribute(&points);
-                    self.buffer.extend(points);
-                    self.since_last_emit = 0;
+                if let Some(points) = raw_data_vecs.into_iter().next() {
+                    self.backfill(points);
                 }
             }
             _ => {}
         }
     }
-}
 
-pub fn remove_mean(y: &[f64]) -> Vec<f64> {
-    let n = y.len();
-    if n == 0 {
-        return vec![];
+    fn backfill(&mut self, mut points: Vec<Point>) {
+        if self.window_size_samples > 0 && points.len() > self.window_size_samples {
+            points.drain(..points.len() - self.window_size_samples);
+        }
+        self.calculate_and_distribute(&points);
+        self.buffer.clear();
+        self.buffer.extend(points);
+        self.since_last_emit = 0;
     }
-    let mean = y.iter().sum::<f64>() / n as f64;
-    y.iter().map(|val| val - mean).collect()
 }
 
-pub fn remove_linear_trend(y: &[f64]) -> Vec<f64> {
-    let n = y.len();
-    if n == 0 {
-        return vec![];
-    }
-    let t: Vec<f64> = (0..n).map(|i| i as f64).collect();
-    let a = DMatrix::from_fn(n, 2, |r, c| if c == 0 { 1.0 } else { t[r] });
-    let b = DVector::from_vec(y.to_vec());
-    let coeffs = a.svd(true, true).solve(&b, 1e-10).unwrap();
-    let c = coeffs[0];
-    let m = coeffs[1];
-    y.iter()
-        .zip(t.iter())
-        .map(|(yi, ti)| yi - (m * ti + c))
-        .collect()
+/// Removes the mean, or a linear or quadratic trend in `t`, from `y`. The fit only uses finite
+/// samples, so NaNs (and the time gaps of dropped samples) don't spread to the rest of the window.
+pub fn detrend(t: &[f64], y: &[f64], method: &DetrendMethod) -> Vec<f64> {
+    let degree = match method {
+        DetrendMethod::None => 0,
+        DetrendMethod::Linear => 1,
+        DetrendMethod::Quadratic => 2,
+    };
+    remove_polynomial(t, y, degree)
 }
 
-pub fn remove_quadratic_trend(y: &[f64]) -> Vec<f64> {
-    let n = y.len();
-    if n < 3 {
-        return remove_linear_trend(y);
+pub fn remove_polynomial(t: &[f64], y: &[f64], degree: usize) -> Vec<f64> {
+    let finite: Vec<usize> = (0..y.len()).filter(|&i| y[i].is_finite()).collect();
+    if finite.is_empty() {
+        return y.to_vec();
     }
-    let t: Vec<f64> = (0..n).map(|i| i as f64).collect();
-    let a = DMatrix::from_fn(n, 3, |r, c| match c {
-        0 => 1.0,
-        1 => t[r],
-        _ => t[r].powi(2),
+    // Without matching timestamps, fall back to the sample index.
+    let t: Vec<f64> = if t.len() == y.len() {
+        t.to_vec()
+    } else {
+        (0..y.len()).map(|i| i as f64).collect()
+    };
+    // Scale time to [-1, 1] so unified (Unix) timestamps don't wreck the conditioning.
+    let (t0, t1) = (t[finite[0]], t[finite[finite.len() - 1]]);
+    let center = 0.5 * (t0 + t1);
+    let half_span = (0.5 * (t1 - t0)).max(f64::EPSILON);
+    let scaled = |i: usize| (t[i] - center) / half_span;
+
+    let degree = degree.min(finite.len() - 1);
+    let a = DMatrix::from_fn(finite.len(), degree + 1, |r, c| {
+        scaled(finite[r]).powi(c as i32)
     });
-    let b = DVector::from_vec(y.to_vec());
+    let b = DVector::from_iterator(finite.len(), finite.iter().map(|&i| y[i]));
     let coeffs = a.svd(true, true).solve(&b, 1e-10).unwrap();
-    let c = coeffs[0];
-    let b_coeff = coeffs[1];
-    let a_coeff = coeffs[2];
     y.iter()
-        .zip(t.iter())
-        .map(|(yi, ti)| yi - (a_coeff * ti.powi(2) + b_coeff * ti + c))
+        .enumerate()
+        .map(|(i, yi)| {
+            let x = scaled(i);
+            let trend: f64 = coeffs
+                .iter()
+                .enumerate()
+                .map(|(c, k)| k * x.powi(c as i32))
+                .sum();
+            yi - trend
+        })
         .collect()
 }
@@ -1,20 +1,36 @@
+use super::spectral::{convert_units, FrameAverager, Welch};
 use super::{Pipeline, PipelineCommand};
-use crate::shared::{PipelineId, PlotData};
+use crate::shared::{
+    DataColumnId, PipelineId, PlotData, PsdConfig, SpectralMethod, SpectrumStatus,
+};
 use crate::state::capture::CaptureState;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
-use welch_sde::{Build, SpectralDensity};
 
 pub struct FftPipeline {
     id: PipelineId,
+    source_key: DataColumnId,
+    window_seconds: f64,
     output: Arc<Mutex<PlotData>>,
+    config: PsdConfig,
+    welch: Welch,
+    frequencies: Vec<f64>,
+    averager: FrameAverager,
+    status: Option<SpectrumStatus>,
 }
 
 impl FftPipeline {
-    pub fn new() -> Self {
+    pub fn new(source_key: DataColumnId, window_seconds: f64, config: PsdConfig) -> Self {
         Self {
             id: PipelineId(Uuid::new_v4()),
+            source_key,
+            window_seconds,
             output: Arc::new(Mutex::new(PlotData::empty())),
+            welch: Welch::new(config.clone()),
+            averager: FrameAverager::new(config.frame_averaging.clone()),
+            config,
+            frequencies: Vec::new(),
+            status: None,
         }
     }
 }
@@ -37,28 +53,68 @@ impl Pipeline for FftPipeline {
             return;
         };
 
-        if y_values.len() < 16 || sample_rate <= 0.0 {
-            *self.output.lock().unwrap() = PlotData::empty();
-            return;
-        }
-
-        let welch: SpectralDensity<f64> = SpectralDensity::builder(y_values, sample_rate).build();
-        let psd = welch.periodogram();
+        let window_len = (sample_rate * self.window_seconds).ceil() as usize;
+        let result = self
+            .welch
+            .estimate(&plot_data.timestamps, y_values, sample_rate, window_len);
+        let estimate = match result {
+            Ok(Some(estimate)) => estimate,
+            Ok(None) => {
+                *self.output.lock().unwrap() = PlotData::empty();
+                self.status = None;
+                return;
+            }
+            Err(e) => {
+                if self.status.as_ref().and_then(|s| s.error.as_ref()) != Some(&e) {
+                    eprintln!("[FFT {:?}] {}", self.id, e);
+                }
+                *self.output.lock().unwrap() = PlotData::empty();
+                self.status = Some(SpectrumStatus {
+                    key: self.source_key.clone(),
+                    method: SpectralMethod::LombScargle,
+                    missing_samples: 0,
+                    gaps: 0,
+                    segments: 0,
+                    error: Some(e),
+                });
+                return;
+            }
+        };
+        self.status = Some(SpectrumStatus {
+            key: self.source_key.clone(),
+            method: estimate.method,
+            missing_samples: estimate.missing_samples as u32,
+            gaps: estimate.gaps as u32,
+            segments: estimate.segments as u32,
+            error: None,
+        });
+        let (frequencies, psd) = (estimate.frequencies, estimate.psd);
 
-        let asd: Vec<f64> = psd.to_vec().iter().map(|&p| p.sqrt()).collect();
-        let frequencies = psd.frequency().to_vec();
+        // A new frequency grid (rate or window change) invalidates the running average.
+        if frequencies != self.frequencies {
+            self.averager.clear();
+            self.frequencies = frequencies;
+        }
+        let mut spectrum = self.averager.push(psd);
+        convert_units(&mut spectrum, &self.config.units);
 
         let result = PlotData {
-            timestamps: frequencies,
-            series_data: vec![asd],
+            timestamps: self.frequencies.clone(),
+            series_data: vec![spectrum],
         };
 
         *self.output.lock().unwrap() = result;
     }
 
+    fn spectrum_status(&self) -> Option<SpectrumStatus> {
+        self.status.clone()
+    }
+
     fn process_command(&mut self, cmd: PipelineCommand, _capture: &CaptureState) {
         if let PipelineCommand::ResetSelf = cmd {
-            println!("[Detrend {:?}] Received ResetSelf command", self.id);
+            println!("[FFT {:?}] Received ResetSelf command", self.id);
+            self.averager.clear();
+            self.status = None;
             *self.output.lock().unwrap() = PlotData::empty();
         }
     }
@@ -0,0 +1,671 @@
+//! Digital filtering in front of a timeseries or FFT chain.
+//!
+//! `FilterPipeline` wraps the pipeline it feeds: every capture batch is filtered before the inner
+//! pipeline sees it, and on `Hydrate` the inner pipeline is backfilled with filtered history, so
+//! decimation, detrending and the FFT never see the raw signal.
+use super::{Pipeline, PipelineCommand};
+use crate::shared::{
+    DataColumnId, FilterConfig, FilterDesign, FilterKind, PipelineId, PlotData, Point,
+};
+use crate::state::capture::{BatchedData, CaptureState};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::slice;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// History pulled in front of the window on `Hydrate` so the filter has settled by the time the
+/// visible samples go through it, in periods of the lowest designed frequency.
+const SETTLE_PERIODS: f64 = 8.0;
+const MAX_SETTLE_SECONDS: f64 = 60.0;
+/// A jump of more than this many sample periods (a dropped packet, a new session) restarts the
+/// filter instead of ringing across the gap.
+const GAP_PERIODS: f64 = 10.0;
+const MAX_BUTTERWORTH_ORDER: u32 = 16;
+const MAX_FIR_TAPS: u32 = 4095;
+
+/// Second-order section, transposed direct form II.
+#[derive(Clone, Copy, Debug, Default)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    z1: f64,
+    z2: f64,
+}
+
+impl Biquad {
+    fn normalized(b: [f64; 3], a: [f64; 3]) -> Self {
+        Self {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            ..Default::default()
+        }
+    }
+
+    fn lowpass(cutoff_hz: f64, fs: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / fs;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        Self::normalized(
+            [(1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn highpass(cutoff_hz: f64, fs: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * cutoff_hz / fs;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        Self::normalized(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn notch(frequency_hz: f64, fs: f64, q: f64) -> Self {
+        let w0 = 2.0 * PI * frequency_hz / fs;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q);
+        Self::normalized(
+            [1.0, -2.0 * cos, 1.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    /// First-order section for the real pole of an odd-order Butterworth.
+    fn first_order(cutoff_hz: f64, fs: f64, highpass: bool) -> Self {
+        let k = (PI * cutoff_hz / fs).tan();
+        let b = if highpass {
+            [1.0, -1.0, 0.0]
+        } else {
+            [k, k, 0.0]
+        };
+        Self::normalized(b, [1.0 + k, k - 1.0, 0.0])
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.z1;
+        self.z1 = self.b1 * x - self.a1 * y + self.z2;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+
+    /// Sets the state to what a constant input `x` would have settled to and returns the
+    /// section's output for it, so a restart doesn't begin with a step from zero.
+    fn settle(&mut self, x: f64) -> f64 {
+        let den = 1.0 + self.a1 + self.a2;
+        let y = if den.abs() > f64::EPSILON {
+            (self.b0 + self.b1 + self.b2) / den * x
+        } else {
+            0.0
+        };
+        self.z1 = y - self.b0 * x;
+        self.z2 = self.b2 * x - self.a2 * y;
+        y
+    }
+}
+
+/// Butterworth low- or high-pass of `order` as a cascade of sections.
+fn butterworth(cutoff_hz: f64, fs: f64, order: u32, highpass: bool) -> Vec<Biquad> {
+    let n = order as f64;
+    let mut sections: Vec<Biquad> = (0..order / 2)
+        .map(|k| {
+            let q = 1.0 / (2.0 * ((2 * k + 1) as f64 * PI / (2.0 * n)).sin());
+            if highpass {
+                Biquad::highpass(cutoff_hz, fs, q)
+            } else {
+                Biquad::lowpass(cutoff_hz, fs, q)
+            }
+        })
+        .collect();
+    if order % 2 == 1 {
+        sections.push(Biquad::first_order(cutoff_hz, fs, highpass));
+    }
+    sections
+}
+
+/// Blackman-windowed sinc low-pass with unity DC gain.
+fn fir_lowpass(cutoff_hz: f64, fs: f64, taps: usize) -> Vec<f64> {
+    let fc = cutoff_hz / fs;
+    let m = (taps - 1) as f64;
+    let mut h: Vec<f64> = (0..taps)
+        .map(|i| {
+            let x = i as f64 - m / 2.0;
+            let sinc = if x == 0.0 {
+                2.0 * fc
+            } else {
+                (2.0 * PI * fc * x).sin() / (PI * x)
+            };
+            let phase = 2.0 * PI * i as f64 / m;
+            let window = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            sinc * window
+        })
+        .collect();
+    let sum: f64 = h.iter().sum();
+    h.iter_mut().for_each(|v| *v /= sum);
+    h
+}
+
+/// `delta - h`: turns a low-pass into a high-pass, or a band-pass into a band-stop.
+fn spectral_inversion(mut h: Vec<f64>) -> Vec<f64> {
+    h.iter_mut().for_each(|v| *v = -*v);
+    let center = h.len() / 2;
+    h[center] += 1.0;
+    h
+}
+
+fn fir_bandpass(low_hz: f64, high_hz: f64, fs: f64, taps: usize) -> Vec<f64> {
+    let high = fir_lowpass(high_hz, fs, taps);
+    let low = fir_lowpass(low_hz, fs, taps);
+    high.iter().zip(low.iter()).map(|(h, l)| h - l).collect()
+}
+
+/// Harmonics of a notch that fall below Nyquist.
+fn notch_frequencies(frequency_hz: f64, harmonics: u32, fs: f64) -> Vec<f64> {
+    (1..=harmonics.max(1))
+        .map(|k| k as f64 * frequency_hz)
+        .take_while(|f| *f < fs / 2.0)
+        .collect()
+}
+
+enum Stages {
+    /// Sections in cascade; `primed` is cleared on reset so the next finite sample settles them.
+    Iir { sections: Vec<Biquad>, primed: bool },
+    /// Taps and the input history; output is stamped with the centre sample's time, which
+    /// removes the FIR's group delay. NaNs wait in `gaps` until the output catches up with them.
+    Fir {
+        taps: Vec<f64>,
+        history: VecDeque<(Point, u32)>,
+        gaps: VecDeque<(Point, u32)>,
+    },
+}
+
+pub struct DigitalFilter {
+    stages: Stages,
+    sample_period: f64,
+    settle_seconds: f64,
+    last_time: Option<f64>,
+}
+
+impl DigitalFilter {
+    pub fn design(config: &FilterConfig, fs: f64) -> Result<Self, String> {
+        if !fs.is_finite() || fs <= 0.0 {
+            return Err(format!("invalid sampling rate {}", fs));
+        }
+        let nyquist = fs / 2.0;
+        let check = |f: f64| {
+            if f > 0.0 && f < nyquist {
+                Ok(f)
+            } else {
+                Err(format!("{} Hz is outside (0, {}) Hz", f, nyquist))
+            }
+        };
+
+        let lowest_hz = match &config.kind {
+            FilterKind::LowPass { cutoff_hz } | FilterKind::HighPass { cutoff_hz } => {
+                check(*cutoff_hz)?
+            }
+            FilterKind::BandPass { low_hz, high_hz } => {
+                check(*high_hz)?;
+                if low_hz >= high_hz {
+                    return Err(format!("band-pass edges {} >= {} Hz", low_hz, high_hz));
+                }
+                check(*low_hz)?
+            }
+            FilterKind::Notch {
+                frequency_hz, q, ..
+            } => {
+                if q.is_nan() || *q <= 0.0 {
+                    return Err(format!("notch Q must be positive, got {}", q));
+                }
+                check(*frequency_hz)? / q
+            }
+        };
+
+        let (stages, settle_seconds) = match config.design {
+            FilterDesign::Butterworth => {
+                let order = config.order.clamp(1, MAX_BUTTERWORTH_ORDER);
+                let sections = match &config.kind {
+                    FilterKind::LowPass { cutoff_hz } => butterworth(*cutoff_hz, fs, order, false),
+                    FilterKind::HighPass { cutoff_hz } => butterworth(*cutoff_hz, fs, order, true),
+                    FilterKind::BandPass { low_hz, high_hz } => {
+                        let mut sections = butterworth(*low_hz, fs, order, true);
+                        sections.extend(butterworth(*high_hz, fs, order, false));
+                        sections
+                    }
+                    FilterKind::Notch {
+                        frequency_hz,
+                        harmonics,
+                        q,
+                    } => notch_frequencies(*frequency_hz, *harmonics, fs)
+                        .into_iter()
+                        .map(|f| Biquad::notch(f, fs, *q))
+                        .collect(),
+                };
+                (
+                    Stages::Iir {
+                        sections,
+                        primed: false,
+                    },
+                    (SETTLE_PERIODS / lowest_hz).min(MAX_SETTLE_SECONDS),
+                )
+            }
+            FilterDesign::Fir => {
+                // Odd length keeps a centre tap, so the delay is a whole number of samples.
+                let taps = (config.order.clamp(3, MAX_FIR_TAPS) | 1) as usize;
+                let h = match &config.kind {
+                    FilterKind::LowPass { cutoff_hz } => fir_lowpass(*cutoff_hz, fs, taps),
+                    FilterKind::HighPass { cutoff_hz } => {
+                        spectral_inversion(fir_lowpass(*cutoff_hz, fs, taps))
+                    }
+                    FilterKind::BandPass { low_hz, high_hz } => {
+                        fir_bandpass(*low_hz, *high_hz, fs, taps)
+                    }
+                    FilterKind::Notch {
+                        frequency_hz,
+                        harmonics,
+                        q,
+                    } => {
+                        let mut stop = vec![0.0; taps];
+                        for f in notch_frequencies(*frequency_hz, *harmonics, fs) {
+                            let half_width = f / q / 2.0;
+                            let band = fir_bandpass(
+                                (f - half_width).max(f64::EPSILON),
+                                (f + half_width).min(nyquist * (1.0 - f64::EPSILON)),
+                                fs,
+                                taps,
+                            );
+                            stop.iter_mut().zip(band).for_each(|(s, b)| *s += b);
+                        }
+                        spectral_inversion(stop)
+                    }
+                };
+                (
+                    Stages::Fir {
+                        taps: h,
+                        history: VecDeque::with_capacity(taps),
+                        gaps: VecDeque::new(),
+                    },
+                    taps as f64 / fs,
+                )
+            }
+        };
+
+        Ok(Self {
+            stages,
+            sample_period: 1.0 / fs,
+            settle_seconds,
+            last_time: None,
+        })
+    }
+
+    /// How much history the filter needs to forget its initial state.
+    pub fn settle_seconds(&self) -> f64 {
+        self.settle_seconds
+    }
+
+    pub fn reset(&mut self) {
+        self.last_time = None;
+        match &mut self.stages {
+            Stages::Iir { primed, .. } => *primed = false,
+            Stages::Fir { history, gaps, .. } => {
+                history.clear();
+                gaps.clear();
+            }
+        }
+    }
+
+    /// Filters one sample into `out`. NaNs pass through as NaN without disturbing the filter
+    /// state; the FIR holds them back so they stay in time order with its delayed output.
+    pub fn process(&mut self, point: Point, sample_number: u32, out: &mut Vec<(Point, u32)>) {
+        if let Some(last) = self.last_time {
+            if point.x < last || point.x - last > GAP_PERIODS * self.sample_period {
+                if let Stages::Fir { gaps, .. } = &mut self.stages {
+                    out.extend(gaps.drain(..));
+                }
+                self.reset();
+            }
+        }
+        self.last_time = Some(point.x);
+
+        match &mut self.stages {
+            Stages::Iir { sections, primed } => {
+                if !point.y.is_finite() {
+                    out.push((point, sample_number));
+                    return;
+                }
+                if !*primed {
+                    sections.iter_mut().fold(point.y, |y, s| s.settle(y));
+                    *primed = true;
+                }
+                let y = sections.iter_mut().fold(point.y, |y, s| s.process(y));
+                out.push((Point { x: point.x, y }, sample_number));
+            }
+            Stages::Fir {
+                taps,
+                history,
+                gaps,
+            } => {
+                if !point.y.is_finite() {
+                    gaps.push_back((point, sample_number));
+                    return;
+                }
+                history.push_back((point, sample_number));
+                if history.len() > taps.len() {
+                    history.pop_front();
+                }
+                if history.len() < taps.len() {
+                    return;
+                }
+                let y = taps
+                    .iter()
+                    .zip(history.iter())
+                    .map(|(h, (p, _))| h * p.y)
+                    .sum();
+                let (center, n) = history[taps.len() / 2];
+                while gaps.front().is_some_and(|(gap, _)| gap.x < center.x) {
+                    out.extend(gaps.pop_front());
+                }
+                out.push((Point { x: center.x, y }, n));
+            }
+        }
+    }
+
+    pub fn process_all(&mut self, points: &[Point]) -> Vec<Point> {
+        let mut out = Vec::with_capacity(points.len());
+        for p in points {
+            self.process(*p, 0, &mut out);
+        }
+        out.into_iter().map(|(p, _)| p).collect()
+    }
+}
+
+pub struct FilterPipeline {
+    id: PipelineId,
+    source_key: DataColumnId,
+    config: FilterConfig,
+    window_seconds: f64,
+    filter: Option<DigitalFilter>,
+    /// Rate `filter` (or `design_error`) is for.
+    sample_rate: Option<f64>,
+    design_error: Option<String>,
+    inner: Box<dyn Pipeline>,
+    capture: CaptureState,
+}
+
+impl FilterPipeline {
+    pub fn new(
+        source_key: DataColumnId,
+        config: FilterConfig,
+        window_seconds: f64,
+        inner: Box<dyn Pipeline>,
+        capture: CaptureState,
+    ) -> Self {
+        Self {
+            id: PipelineId(Uuid::new_v4()),
+            source_key,
+            config,
+            window_seconds,
+            filter: None,
+            sample_rate: None,
+            design_error: None,
+            inner,
+            capture,
+        }
+    }
+
+    /// Redesigns the filter if the stream's rate has changed since it was last designed.
+    fn update_design(&mut self) {
+        let Some(rate) = self
+            .capture
+            .get_effective_sampling_rate(&self.source_key)
+            .filter(|r| *r > 0.0)
+        else {
+            return;
+        };
+        if self.sample_rate.is_none_or(|sr| (sr - rate).abs() > 1e-9) {
+            self.design(rate);
+        }
+    }
+
+    fn design(&mut self, fs: f64) {
+        if self.sample_rate.is_none_or(|sr| (sr - fs).abs() > 1e-9) {
+            self.design_error = None;
+        }
+        self.sample_rate = Some(fs);
+        match DigitalFilter::design(&self.config, fs) {
+            Ok(filter) => {
+                println!(
+                    "[Filter {:?}] Designed {:?} {:?} at {:.1} Hz.",
+                    self.id, self.config.design, self.config.kind, fs
+                );
+                self.filter = Some(filter);
+                self.design_error = None;
+            }
+            Err(e) => {
+                if self.design_error.as_ref() != Some(&e) {
+                    eprintln!(
+                        "[Filter {:?}] Passing data through unfiltered: {}",
+                        self.id, e
+                    );
+                }
+                self.filter = None;
+                self.design_error = Some(e);
+            }
+        }
+    }
+}
+
+impl Pipeline for FilterPipeline {
+    fn id(&self) -> PipelineId {
+        self.id
+    }
+
+    fn get_output(&self) -> PlotData {
+        self.inner.get_output()
+    }
+
+    fn process_batch(&mut self, batch: Arc<BatchedData>) {
+        if batch.key != self.source_key || batch.points.is_empty() {
+            return;
+        }
+
+        // The rate may not have been known at hydration, or may have changed since.
+        self.update_design();
+        let Some(filter) = self.filter.as_mut() else {
+            self.inner.process_batch(batch);
+            return;
+        };
+
+        let mut filtered = Vec::with_capacity(batch.points.len());
+        for (i, p) in batch.points.iter().enumerate() {
+            let n = batch.sample_numbers.get(i).copied().unwrap_or_default();
+            filter.process(*p, n, &mut filtered);
+        }
+        let (points, sample_numbers): (Vec<Point>, Vec<u32>) = filtered.into_iter().unzip();
+        let (Some(first), Some(last)) = (points.first(), points.last()) else {
+            return;
+        };
+
+        self.inner.process_batch(Arc::new(BatchedData {
+            key: batch.key.clone(),
+            session_id: batch.session_id,
+            t_min: first.x,
+            t_max: last.x,
+            points: Arc::new(points),
+            offset: batch.offset,
+            sample_numbers: Arc::new(sample_numbers),
+        }));
+    }
+
+    fn process_command(&mut self, cmd: PipelineCommand, capture_state: &CaptureState) {
+        match cmd {
+            PipelineCommand::Hydrate => {
+                // Sizes the inner pipeline; its raw backfill is replaced below.
+                self.inner
+                    .process_command(PipelineCommand::Hydrate, capture_state);
+
+                if let Some(sr) = capture_state.get_effective_sampling_rate(&self.source_key) {
+                    self.design(sr);
+                }
+                let Some(filter) = self.filter.as_mut() else {
+                    return;
+                };
+                filter.reset();
+
+                let Some(latest_time) =
+                    capture_state.get_latest_unified_timestamp(slice::from_ref(&self.source_key))
+                else {
+                    return;
+                };
+                let start_time = latest_time - self.window_seconds - filter.settle_seconds();
+                let raw_data_vecs = capture_state.get_data_across_sessions_for_keys(
+                    slice::from_ref(&self.source_key),
+                    start_time,
+                    latest_time,
+                );
+                if let Some(points) = raw_data_vecs.into_iter().next() {
+                    let window_start = latest_time - self.window_seconds;
+                    let filtered: Vec<Point> = filter
+                        .process_all(&points)
+                        .into_iter()
+                        .filter(|p| p.x >= window_start)
+                        .collect();
+                    self.inner.backfill(filtered);
+                }
+            }
+            PipelineCommand::ResetSelf => {
+                println!("[Filter {:?}] Received ResetSelf command", self.id);
+                if let Some(filter) = self.filter.as_mut() {
+                    filter.reset();
+                }
+                self.inner
+                    .process_command(PipelineCommand::ResetSelf, capture_state);
+            }
+            cmd => self.inner.process_command(cmd, capture_state),
+        }
+    }
+
+    fn backfill(&mut self, points: Vec<Point>) {
+        let filtered = match self.filter.as_mut() {
+            Some(filter) => {
+                filter.reset();
+                filter.process_all(&points)
+            }
+            None => points,
+        };
+        self.inner.backfill(filtered);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FS: f64 = 1000.0;
+
+    fn config(kind: FilterKind, design: FilterDesign, order: u32) -> FilterConfig {
+        FilterConfig {
+            kind,
+            design,
+            order,
+        }
+    }
+
+    /// Steady-state gain in dB for a sine at `f`, from the RMS of the second half of the output.
+    fn gain_db(config: &FilterConfig, f: f64) -> f64 {
+        let mut filter = DigitalFilter::design(config, FS).unwrap();
+        let input: Vec<Point> = (0..20_000)
+            .map(|i| {
+                let x = i as f64 / FS;
+                Point {
+                    x,
+                    y: (2.0 * PI * f * x).sin(),
+                }
+            })
+            .collect();
+        let output = filter.process_all(&input);
+        let tail = &output[output.len() / 2..];
+        let rms = (tail.iter().map(|p| p.y * p.y).sum::<f64>() / tail.len() as f64).sqrt();
+        20.0 * (rms * 2f64.sqrt()).log10()
+    }
+
+    #[test]
+    fn butterworth_is_3_db_down_at_cutoff() {
+        for order in [1, 2, 4, 5] {
+            let low = config(
+                FilterKind::LowPass { cutoff_hz: 50.0 },
+                FilterDesign::Butterworth,
+                order,
+            );
+            let high = config(
+                FilterKind::HighPass { cutoff_hz: 50.0 },
+                FilterDesign::Butterworth,
+                order,
+            );
+            let expected = -10.0 * 2f64.log10();
+            assert!(
+                (gain_db(&low, 50.0) - expected).abs() < 0.05,
+                "order {}",
+                order
+            );
+            assert!(
+                (gain_db(&high, 50.0) - expected).abs() < 0.05,
+                "order {}",
+                order
+            );
+            assert!(gain_db(&low, 5.0).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn fir_is_6_db_down_at_cutoff() {
+        let low = config(
+            FilterKind::LowPass { cutoff_hz: 50.0 },
+            FilterDesign::Fir,
+            201,
+        );
+        let high = config(
+            FilterKind::HighPass { cutoff_hz: 50.0 },
+            FilterDesign::Fir,
+            201,
+        );
+        let expected = -20.0 * 2f64.log10();
+        assert!((gain_db(&low, 50.0) - expected).abs() < 0.1);
+        assert!((gain_db(&high, 50.0) - expected).abs() < 0.1);
+        assert!(gain_db(&low, 5.0).abs() < 0.05);
+        assert!(gain_db(&low, 150.0) < -60.0);
+    }
+
+    #[test]
+    fn notch_rejects_its_frequency() {
+        let notch = config(
+            FilterKind::Notch {
+                frequency_hz: 60.0,
+                harmonics: 2,
+                q: 30.0,
+            },
+            FilterDesign::Butterworth,
+            0,
+        );
+        assert!(gain_db(&notch, 60.0) < -60.0);
+        assert!(gain_db(&notch, 120.0) < -60.0);
+        assert!(gain_db(&notch, 90.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn design_rejects_cutoffs_past_nyquist() {
+        let low = config(
+            FilterKind::LowPass { cutoff_hz: 600.0 },
+            FilterDesign::Butterworth,
+            4,
+        );
+        assert!(DigitalFilter::design(&low, FS).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! Distribution of a column's values over a window, for the histogram view.
+//!
+//! The counts go out as a step outline, two points per bin (its left and right edge) closed to
+//! zero at both ends, so the plot's merge, which interpolates each series at the others' x
+//! values, stays flat inside every bin. The optional Gaussian fit takes its mean and standard
+//! deviation from the same `ValueAccumulator` as the statistics panel, and is scaled to counts
+//! per bin.
+use super::statistics::ValueAccumulator;
+use super::{Pipeline, PipelineCommand};
+use crate::shared::{
+    DataColumnId, HistogramBins, HistogramConfig, HistogramStatus, PipelineId, PlotData, Point,
+    StatisticSet,
+};
+use crate::state::capture::{BatchedData, CaptureState};
+use std::collections::VecDeque;
+use std::f64::consts::TAU;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Bins are capped here, whatever the width rule or setting asks for.
+const MAX_BINS: usize = 1000;
+/// Points of the fitted Gaussian across the histogram's range.
+const FIT_POINTS: usize = 256;
+/// The histogram is rebuilt at most this often; binning sorts the whole window.
+const RECOMPUTE_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Equal-width bins starting at `lo`.
+struct Binning {
+    lo: f64,
+    width: f64,
+    count: usize,
+}
+
+impl Binning {
+    /// Bins for `values` (finite, at least one) spanning `min..=max`. Integer-valued columns
+    /// (ADC codes) get bins centred on whole codes, so a missing code shows as an empty bin.
+    fn new(values: &[f64], min: f64, max: f64, bins: HistogramBins) -> Self {
+        let integer = values.iter().all(|v| v.fract() == 0.0);
+        let (lo, span) = if integer {
+            (min - 0.5, max - min + 1.0)
+        } else {
+            (min, max - min)
+        };
+        if span <= 0.0 {
+            let width = if min == 0.0 { 1.0 } else { min.abs() * 1e-3 };
+            return Self {
+                lo: min - 0.5 * width,
+                width,
+                count: 1,
+            };
+        }
+
+        let mut count = match bins {
+            HistogramBins::Fixed { bins } => bins as usize,
+            HistogramBins::Auto => {
+                let mut width = freedman_diaconis_width(values);
+                if width <= 0.0 {
+                    // Over half the values are identical; fall back to Sturges' rule.
+                    width = span / ((values.len() as f64).log2() + 1.0).ceil();
+                }
+                if integer {
+                    width = width.round().max(1.0);
+                }
+                (span / width).ceil() as usize
+            }
+        }
+        .clamp(1, MAX_BINS);
+        let mut width = span / count as f64;
+        if integer && matches!(bins, HistogramBins::Auto) {
+            // Whole codes per bin, even when MAX_BINS forced fewer bins.
+            width = width.ceil();
+            count = (span / width).ceil() as usize;
+        }
+        Self { lo, width, count }
+    }
+
+    fn index(&self, v: f64) -> usize {
+        (((v - self.lo) / self.width).floor().max(0.0) as usize).min(self.count - 1)
+    }
+
+    fn edge(&self, i: usize) -> f64 {
+        self.lo + i as f64 * self.width
+    }
+}
+
+/// 2·IQR·n^(-1/3).
+fn freedman_diaconis_width(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_unstable_by(|a, b| a.total_cmp(b));
+    let quantile = |q: f64| {
+        let pos = q * (sorted.len() - 1) as f64;
+        let (i, frac) = (pos.floor() as usize, pos.fract());
+        let next = sorted[(i + 1).min(sorted.len() - 1)];
+        sorted[i] + frac * (next - sorted[i])
+    };
+    2.0 * (quantile(0.75) - quantile(0.25)) / (sorted.len() as f64).cbrt()
+}
+
+pub struct HistogramPipeline {
+    id: PipelineId,
+    key: DataColumnId,
+    config: HistogramConfig,
+    window: VecDeque<Point>,
+    /// Counts, then the fit when enabled.
+    outputs: Vec<PlotData>,
+    status: Option<HistogramStatus>,
+    next_compute: Instant,
+}
+
+impl HistogramPipeline {
+    pub fn new(key: DataColumnId, config: HistogramConfig) -> Self {
+        Self {
+            id: PipelineId(Uuid::new_v4()),
+            key,
+            config,
+            window: VecDeque::new(),
+            outputs: Vec::new(),
+            status: None,
+            next_compute: Instant::now(),
+        }
+    }
+
+    fn trim(&mut self) {
+        let Some(last) = self.window.back() else {
+            return;
+        };
+        let t_min = last.x - self.config.window_seconds;
+        while self.window.front().is_some_and(|p| p.x < t_min) {
+            self.window.pop_front();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.window.clear();
+        self.outputs.clear();
+        self.status = None;
+    }
+
+    fn compute(&mut self) {
+        self.next_compute = Instant::now() + RECOMPUTE_INTERVAL;
+        let values: Vec<f64> = self
+            .window
+            .iter()
+            .map(|p| p.y)
+            .filter(|y| y.is_finite())
+            .collect();
+        if values.is_empty() {
+            self.outputs.clear();
+            self.status = None;
+            return;
+        }
+        let mut accumulator = ValueAccumulator::default();
+        for &v in &values {
+            accumulator.update(v);
+        }
+        let stats = accumulator.to_stat();
+        let binning = Binning::new(&values, stats.min, stats.max, self.config.bins);
+
+        let mut counts = vec![0u64; binning.count];
+        for &v in &values {
+            counts[binning.index(v)] += 1;
+        }
+        let mut outline = PlotData::with_series_capacity(1);
+        let mut push = |x: f64, y: f64| {
+            outline.timestamps.push(x);
+            outline.series_data[0].push(y);
+        };
+        push(binning.edge(0), 0.0);
+        for (i, &c) in counts.iter().enumerate() {
+            push(binning.edge(i), c as f64);
+            push(binning.edge(i + 1), c as f64);
+        }
+        push(binning.edge(binning.count), 0.0);
+
+        self.outputs = vec![outline];
+        if self.config.fit_gaussian {
+            self.outputs.push(gaussian(&stats, &binning));
+        }
+        self.status = Some(HistogramStatus {
+            key: self.key.clone(),
+            bins: binning.count as u32,
+            bin_width: binning.width,
+            window: stats,
+        });
+    }
+}
+
+/// The normal density of `stats`, in counts per bin, across the bins' range. `NaN` throughout
+/// for a constant column, so the series is still there to keep the plot's order.
+fn gaussian(stats: &StatisticSet, binning: &Binning) -> PlotData {
+    let mut fit = PlotData::with_series_capacity(1);
+    let (lo, hi) = (binning.edge(0), binning.edge(binning.count));
+    let scale = stats.count as f64 * binning.width / (stats.stdev * TAU.sqrt());
+    for i in 0..FIT_POINTS {
+        let x = lo + (hi - lo) * i as f64 / (FIT_POINTS - 1) as f64;
+        let z = (x - stats.mean) / stats.stdev;
+        fit.timestamps.push(x);
+        fit.series_data[0].push(if stats.stdev > 0.0 {
+            scale * (-0.5 * z * z).exp()
+        } else {
+            f64::NAN
+        });
+    }
+    fit
+}
+
+impl Pipeline for HistogramPipeline {
+    fn id(&self) -> PipelineId {
+        self.id
+    }
+
+    fn get_output(&self) -> PlotData {
+        self.outputs
+            .first()
+            .cloned()
+            .unwrap_or_else(PlotData::empty)
+    }
+
+    fn get_outputs(&self) -> Vec<PlotData> {
+        self.outputs.clone()
+    }
+
+    fn histogram_status(&self) -> Option<HistogramStatus> {
+        self.status.clone()
+    }
+
+    fn process_batch(&mut self, batch: Arc<BatchedData>) {
+        if batch.key != self.key {
+            return;
+        }
+        self.window.extend(batch.points.iter().copied());
+        self.trim();
+        if Instant::now() >= self.next_compute {
+            self.compute();
+        }
+    }
+
+    fn process_command(&mut self, cmd: PipelineCommand, capture_state: &CaptureState) {
+        match cmd {
+            PipelineCommand::ResetSelf => {
+                println!(
+                    "[Histogram Pipeline {:?}] Received ResetSelf command",
+                    self.id
+                );
+                self.clear();
+            }
+            PipelineCommand::Hydrate => {
+                println!(
+                    "[Histogram Pipeline {:?}] Received Hydrate command.",
+                    self.id
+                );
+                let Some(latest_time) =
+                    capture_state.get_latest_unified_timestamp(std::slice::from_ref(&self.key))
+                else {
+                    return;
+                };
+                let start_time = latest_time - self.config.window_seconds;
+                let raw_data_vecs = capture_state.get_data_across_sessions_for_keys(
+                    std::slice::from_ref(&self.key),
+                    start_time,
+                    latest_time,
+                );
+                if let Some(points) = raw_data_vecs.into_iter().next() {
+                    self.backfill(points);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn backfill(&mut self, points: Vec<Point>) {
+        self.window.clear();
+        self.window.extend(points);
+        self.trim();
+        self.compute();
+    }
+}
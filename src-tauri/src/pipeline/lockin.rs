@@ -0,0 +1,164 @@
+//! Digital lock-in amplifier, for lock-in channels.
+//!
+//! `LockInPipeline` multiplies each signal sample by the sine and cosine of the reference phase
+//! (times the harmonic, plus the phase offset) and low-passes both products through a cascade of
+//! first-order stages. A signal `A sin(θ + φ)` comes out as `X = A cos φ`, `Y = A sin φ`, i.e. in
+//! peak units of the signal. The reference is either an internal oscillator or the zero crossings
+//! of another column, tracked by `ReferenceTracker`.
+use crate::shared::{LockInConfig, LockInOutput, LockInReference, Point};
+use std::f64::consts::{PI, TAU};
+
+/// Hysteresis of the zero-crossing detector, as a fraction of the reference's mean |deviation|.
+const HYSTERESIS: f64 = 0.25;
+/// Weight of each new period in the smoothed reference period.
+const PERIOD_SMOOTHING: f64 = 0.5;
+/// Time constant of the reference's DC tracking, in reference periods.
+const DC_PERIODS: f64 = 10.0;
+/// The reference counts as lost after this many periods without a crossing.
+const LOST_PERIODS: f64 = 2.0;
+
+/// Recovers the phase of a periodic reference column from its rising zero crossings.
+#[derive(Default)]
+struct ReferenceTracker {
+    prev: Option<Point>,
+    dc: f64,
+    /// Mean |v - dc|, scales the hysteresis.
+    deviation: f64,
+    high: bool,
+    /// Interpolated time of the last upward zero crossing since the reference went low.
+    candidate: Option<f64>,
+    last_crossing: Option<f64>,
+    period: Option<f64>,
+}
+
+impl ReferenceTracker {
+    fn push(&mut self, p: Point) {
+        if !p.y.is_finite() {
+            return;
+        }
+        let Some(prev) = self.prev.replace(p) else {
+            self.dc = p.y;
+            return;
+        };
+        let dt = p.x - prev.x;
+        if dt <= 0.0 {
+            return;
+        }
+        let tau = self.period.map_or(1.0, |t| t * DC_PERIODS);
+        let alpha = 1.0 - (-dt / tau).exp();
+        let d0 = prev.y - self.dc;
+        self.dc += alpha * (p.y - self.dc);
+        let d = p.y - self.dc;
+        self.deviation += alpha * (d.abs() - self.deviation);
+
+        if d0 <= 0.0 && d > 0.0 && !self.high {
+            self.candidate = Some(prev.x + dt * -d0 / (d - d0));
+        }
+        let hysteresis = HYSTERESIS * self.deviation;
+        if self.high && d < -hysteresis {
+            self.high = false;
+            self.candidate = None;
+        } else if !self.high && d > hysteresis {
+            self.high = true;
+            if let Some(crossing) = self.candidate.take() {
+                self.crossing(crossing);
+            }
+        }
+    }
+
+    fn crossing(&mut self, t: f64) {
+        if let Some(last) = self.last_crossing {
+            let period = t - last;
+            self.period = Some(match self.period {
+                Some(p) if period > 0.5 * p && period < 2.0 * p => {
+                    p + PERIOD_SMOOTHING * (period - p)
+                }
+                _ => period,
+            });
+        }
+        self.last_crossing = Some(t);
+    }
+
+    /// Phase at `t` (0 at a rising zero crossing), while the reference is locked.
+    fn phase(&self, t: f64) -> Option<f64> {
+        let (last, period) = (self.last_crossing?, self.period?);
+        let elapsed = t - last;
+        (elapsed < LOST_PERIODS * period).then(|| TAU * elapsed / period)
+    }
+}
+
+pub struct LockInPipeline {
+    /// Internal oscillator frequency; `None` when locked to a reference column.
+    frequency: Option<f64>,
+    tracker: ReferenceTracker,
+    harmonic: f64,
+    phase_offset: f64,
+    time_constant: f64,
+    output: LockInOutput,
+    /// Time of the oscillator's zero phase, the first signal sample.
+    origin: Option<f64>,
+    /// (X, Y) after each low-pass stage; they start from zero, like a lock-in being switched on.
+    stages: Vec<(f64, f64)>,
+    last_time: Option<f64>,
+}
+
+impl LockInPipeline {
+    pub fn new(config: &LockInConfig) -> Self {
+        let frequency = match config.reference {
+            LockInReference::Frequency { hz } => Some(hz),
+            LockInReference::Column { .. } => None,
+        };
+        Self {
+            frequency,
+            tracker: ReferenceTracker::default(),
+            harmonic: config.harmonic.max(1) as f64,
+            phase_offset: config.phase_deg.to_radians(),
+            time_constant: config.time_constant,
+            output: config.output,
+            origin: None,
+            stages: vec![(0.0, 0.0); config.filter_order.max(1) as usize],
+            last_time: None,
+        }
+    }
+
+    /// Feeds the reference column; points must come before the signal samples they precede.
+    pub fn push_reference(&mut self, p: Point) {
+        self.tracker.push(p);
+    }
+
+    /// Demodulates one signal sample into (X, Y) or (R, θ°). `None` for non-finite or
+    /// out-of-order samples and while the reference column is not locked, which also restarts
+    /// the low-pass.
+    pub fn push_signal(&mut self, p: Point) -> Option<(f64, f64)> {
+        if !p.y.is_finite() || self.last_time.is_some_and(|t| p.x <= t) {
+            return None;
+        }
+        let dt = self.last_time.replace(p.x).map_or(0.0, |t| p.x - t);
+
+        let phase = match self.frequency {
+            Some(hz) => TAU * hz * (p.x - *self.origin.get_or_insert(p.x)),
+            None => match self.tracker.phase(p.x) {
+                Some(phase) => phase,
+                None => {
+                    self.stages.fill((0.0, 0.0));
+                    return None;
+                }
+            },
+        };
+        let (sin, cos) = (self.harmonic * phase + self.phase_offset).sin_cos();
+        let mut x = 2.0 * p.y * sin;
+        let mut y = 2.0 * p.y * cos;
+
+        let alpha = 1.0 - (-dt / self.time_constant).exp();
+        for stage in &mut self.stages {
+            stage.0 += alpha * (x - stage.0);
+            stage.1 += alpha * (y - stage.1);
+            (x, y) = *stage;
+        }
+
+        Some(match self.output {
+            LockInOutput::Cartesian => (x, y),
+            LockInOutput::Polar => (x.hypot(y), y.atan2(x) * 180.0 / PI),
+        })
+    }
+}
@@ -1,19 +1,26 @@
+use super::allan::AllanPipeline;
+use super::cross::{CrossSpectralPipeline, COLUMN_INPUT, REFERENCE_INPUT};
 use super::decimation::StreamingFpcsPipeline;
 use super::detrend::DetrendPipeline;
 use super::fft::FftPipeline;
+use super::filter::FilterPipeline;
+use super::histogram::HistogramPipeline;
 use super::passthrough::PassthroughPipeline;
-use super::{Pipeline, PipelineCommand};
+use super::spectrogram::SpectrogramPipeline;
+use super::trigger::TriggerPipeline;
+use super::xy::{XyPipeline, X_INPUT, Y_INPUT};
+use super::{FrameCursor, Pipeline, PipelineCommand};
 use crate::pipeline::statistics::StreamingStatisticsProvider;
 use crate::pipeline::StatisticsProvider;
 use crate::shared::{
-    DataColumnId, DecimationMethod, FftConfig, PipelineId, PlotData, SharedPlotConfig,
-    ColumnStatistics, TimeseriesConfig, ViewConfig,
+    AllanConfig, AllanStatus, CrossSpectrumConfig, DataColumnId, DecimationMethod, FftConfig, FilterConfig, HistogramConfig, HistogramStatus, PipelineId, PlotData, SharedPlotConfig,
+    ColumnStatistics, SpectrogramConfig, SpectrogramData, SpectrumStatus, TimeseriesConfig,
+    TriggerConfig, TriggerStatus, ViewConfig, XyConfig,
 };
 use crate::state::capture::{CaptureCommand, CaptureState};
 use crate::util::k_way_merge_plot_data;
 use crossbeam::channel::{bounded, select, Sender};
 use std::collections::HashMap;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
@@ -37,6 +44,10 @@ enum ThreadType {
     Derived{
         source_key: DataColumnId,
     },
+    /// Fed by two derived channels: a reference column's chain and another column's.
+    Paired {
+        source_keys: [DataColumnId; 2],
+    },
 }
 
 struct PipelineThreadHandle {
@@ -47,14 +58,18 @@ struct PipelineThreadHandle {
 
 pub struct ProcessingManager {
     pub managed_plots: HashMap<String, ManagedPlotPipeline>,
+    /// Plot ids in the order they were first configured.
+    plot_order: Vec<String>,
     pub pipelines: HashMap<PipelineId, Arc<Mutex<dyn Pipeline>>>,
     pub stat_providers: HashMap<PipelineId, Arc<Mutex<dyn StatisticsProvider>>>,
     pub plot_channels: HashMap<String, Channel<PlotData>>,
+    pub spectrogram_channels: HashMap<String, Channel<SpectrogramData>>,
+    /// What each spectrogram pipeline has already sent on its plot's channel.
+    frame_cursors: HashMap<PipelineId, FrameCursor>,
     pub statistics_channels: HashMap<PipelineId, Channel<ColumnStatistics>>,
     pipeline_threads: HashMap<PipelineId, PipelineThreadHandle>,
     capture_state: CaptureState,
     capture_cmd_tx: Sender<CaptureCommand>,
-    next_sub_id: AtomicUsize,
 }
 
 impl ProcessingManager {
@@ -62,14 +77,16 @@ impl ProcessingManager {
         let capture_cmd_tx = capture_state.inner.command_tx.clone();
         let manager = Arc::new(Mutex::new(Self {
             managed_plots: HashMap::new(),
+            plot_order: Vec::new(),
             pipelines: HashMap::new(),
             stat_providers: HashMap::new(),
             plot_channels: HashMap::new(),
+            spectrogram_channels: HashMap::new(),
+            frame_cursors: HashMap::new(),
             statistics_channels: HashMap::new(),
             pipeline_threads: HashMap::new(),
             capture_state,
             capture_cmd_tx,
-            next_sub_id: AtomicUsize::new(1),
         }));
 
         {
@@ -86,7 +103,7 @@ impl ProcessingManager {
                     loop {
                         let now = Instant::now();
 
-                        if let Ok(mg) = manager_clone.lock() {
+                        if let Ok(mut mg) = manager_clone.lock() {
                             if now >= next_plot {
                                 for (plot_id, channel) in &mg.plot_channels {
                                     if let Some(data) = mg.get_merged_data_for_plot(plot_id) {
@@ -95,6 +112,7 @@ impl ProcessingManager {
                                         }
                                     }
                                 }
+                                mg.emit_spectrogram_frames();
                                 next_plot = now + plot_period;
                             }
 
@@ -133,7 +151,7 @@ impl ProcessingManager {
         let id = pipeline.id();
         let pipeline_arc = Arc::new(Mutex::new(pipeline));
         self.pipelines.insert(id, pipeline_arc.clone());
-        let sub_id = self.next_sub_id.fetch_add(1, Ordering::Relaxed);
+        let sub_id = self.capture_state.next_subscriber_id();
         let (data_tx, data_rx) = bounded(128);
         let (cmd_tx, cmd_rx) = bounded(16);
         self.capture_cmd_tx
@@ -219,15 +237,74 @@ impl ProcessingManager {
         (id, data_tx)
     }
 
+    /// Like `spawn_derived_pipeline`, with one input channel per source; batches arrive through
+    /// `Pipeline::process_paired_batch` tagged with the index of their channel.
+    fn spawn_paired_pipeline<P>(
+        &mut self,
+        pipeline: P,
+        source_keys: [DataColumnId; 2],
+    ) -> (PipelineId, [Sender<(PlotData, f64)>; 2])
+    where
+        P: Pipeline + 'static,
+    {
+        let id = pipeline.id();
+        let pipeline_arc = Arc::new(Mutex::new(pipeline));
+        self.pipelines.insert(id, pipeline_arc.clone());
+
+        let (a_tx, a_rx) = bounded(128);
+        let (b_tx, b_rx) = bounded(128);
+        let (cmd_tx, cmd_rx) = bounded(16);
+        let capture_clone = self.capture_state.clone();
+        let handle = thread::Builder::new()
+            .name(format!("pipeline-paired-{:?}", id))
+            .spawn(move || loop {
+                select! {
+                    recv(a_rx) -> msg => match msg {
+                        Ok(batch) => pipeline_arc.lock().unwrap().process_paired_batch(0, batch),
+                        Err(_) => break,
+                    },
+                    recv(b_rx) -> msg => match msg {
+                        Ok(batch) => pipeline_arc.lock().unwrap().process_paired_batch(1, batch),
+                        Err(_) => break,
+                    },
+                    recv(cmd_rx) -> msg => match msg {
+                        Ok(cmd) => {
+                            if matches!(cmd, PipelineCommand::Shutdown) { break; }
+                            pipeline_arc.lock().unwrap().process_command(cmd, &capture_clone);
+                        },
+                        Err(_) => break,
+                    }
+                }
+            })
+            .unwrap();
+        self.pipeline_threads.insert(
+            id,
+            PipelineThreadHandle {
+                cmd_tx,
+                handle,
+                thread_type: ThreadType::Paired { source_keys },
+            },
+        );
+        (id, [a_tx, b_tx])
+    }
+
     pub fn apply_plot_config(
         &mut self,
         config: SharedPlotConfig,
     ) -> Result<Vec<PipelineId>, String> {
         if self.managed_plots.contains_key(&config.plot_id) {
             self._destroy_plot_components(&config.plot_id);
+        } else if !self.plot_order.contains(&config.plot_id) {
+            self.plot_order.push(config.plot_id.clone());
         }
         let mut output_ids = Vec::new();
         let mut all_ids = Vec::new();
+        // A trigger captures every column of the plot at once, so frames stay aligned.
+        if let ViewConfig::Trigger(trigger_config) = &config.view_config {
+            let id = self._create_trigger_for_plot(&config.data_keys, trigger_config);
+            output_ids.push(id);
+            all_ids.push(id);
+        }
         for key in &config.data_keys {
             match &config.view_config {
                 ViewConfig::Timeseries(ts_config) => {
@@ -242,6 +319,37 @@ impl ProcessingManager {
                     all_ids.push(src_id);
                     all_ids.push(fft_id);
                 }
+                ViewConfig::Spectrogram(spec_config) => {
+                    let (spec_id, src_id) =
+                        self._create_spectrogram_chain_for_plot(key, spec_config)?;
+                    output_ids.push(spec_id);
+                    all_ids.push(src_id);
+                    all_ids.push(spec_id);
+                }
+                ViewConfig::CrossSpectrum(cross_config) => {
+                    let (cross_id, src_ids) =
+                        self._create_cross_chain_for_plot(key, cross_config)?;
+                    output_ids.push(cross_id);
+                    all_ids.extend(src_ids);
+                    all_ids.push(cross_id);
+                }
+                ViewConfig::Trigger(_) => {}
+                ViewConfig::AllanDeviation(allan_config) => {
+                    let id = self._create_allan_for_plot(key, allan_config);
+                    output_ids.push(id);
+                    all_ids.push(id);
+                }
+                ViewConfig::Histogram(histogram_config) => {
+                    let id = self._create_histogram_for_plot(key, histogram_config);
+                    output_ids.push(id);
+                    all_ids.push(id);
+                }
+                ViewConfig::Xy(xy_config) => {
+                    let (xy_id, src_ids) = self._create_xy_chain_for_plot(key, xy_config)?;
+                    output_ids.push(xy_id);
+                    all_ids.extend(src_ids);
+                    all_ids.push(xy_id);
+                }
             }
         }
         self.managed_plots.insert(
@@ -257,7 +365,9 @@ impl ProcessingManager {
 
     pub fn destroy_plot_pipelines(&mut self, plot_id: &str) {
         self._destroy_plot_components(plot_id);
+        self.plot_order.retain(|id| id != plot_id);
         self.plot_channels.remove(plot_id);
+        self.spectrogram_channels.remove(plot_id);
     }
 
     fn _create_timeseries_for_plot(
@@ -273,15 +383,51 @@ impl ProcessingManager {
                     .round() as usize;
                 let pipeline =
                     StreamingFpcsPipeline::new(key.clone(), ratio.max(1), config.window_seconds);
-                self.spawn_root_pipeline(pipeline, key.clone())
+                self.spawn_filtered_root_pipeline(
+                    pipeline,
+                    key,
+                    config.filter.as_ref(),
+                    config.window_seconds,
+                )
             }
             DecimationMethod::None => {
                 let pipeline = PassthroughPipeline::new(key.clone(), config.window_seconds);
-                self.spawn_root_pipeline(pipeline, key.clone())
+                self.spawn_filtered_root_pipeline(
+                    pipeline,
+                    key,
+                    config.filter.as_ref(),
+                    config.window_seconds,
+                )
             }
         }
     }
 
+    /// Spawns `pipeline` as a root, behind a `FilterPipeline` if the plot has a filter.
+    fn spawn_filtered_root_pipeline<P>(
+        &mut self,
+        pipeline: P,
+        key: &DataColumnId,
+        filter: Option<&FilterConfig>,
+        window_seconds: f64,
+    ) -> PipelineId
+    where
+        P: Pipeline + 'static,
+    {
+        match filter {
+            Some(filter) => {
+                let filtered = FilterPipeline::new(
+                    key.clone(),
+                    filter.clone(),
+                    window_seconds,
+                    Box::new(pipeline),
+                    self.capture_state.clone(),
+                );
+                self.spawn_root_pipeline(filtered, key.clone())
+            }
+            None => self.spawn_root_pipeline(pipeline, key.clone()),
+        }
+    }
+
     fn _create_fft_chain_for_plot(
         &mut self,
         key: &DataColumnId,
@@ -292,9 +438,14 @@ impl ProcessingManager {
             config.window_seconds,
             config.detrend_method.clone(),
         );
-        let detrend_id = self.spawn_root_pipeline(detrend_pipeline, key.clone());
+        let detrend_id = self.spawn_filtered_root_pipeline(
+            detrend_pipeline,
+            key,
+            config.filter.as_ref(),
+            config.window_seconds,
+        );
 
-        let fft_pipeline = FftPipeline::new();
+        let fft_pipeline = FftPipeline::new(key.clone(), config.window_seconds, config.psd.clone());
         let (fft_id, fft_input_tx) = self.spawn_derived_pipeline(fft_pipeline, key.clone());
 
         let handle = self
@@ -310,6 +461,150 @@ impl ProcessingManager {
         Ok((fft_id, detrend_id))
     }
 
+    fn _create_spectrogram_chain_for_plot(
+        &mut self,
+        key: &DataColumnId,
+        config: &SpectrogramConfig,
+    ) -> Result<(PipelineId, PipelineId), String> {
+        let detrend_pipeline = DetrendPipeline::new(
+            key.clone(),
+            config.window_seconds,
+            config.detrend_method.clone(),
+        );
+        let detrend_id = self.spawn_filtered_root_pipeline(
+            detrend_pipeline,
+            key,
+            config.filter.as_ref(),
+            config.window_seconds,
+        );
+
+        let spectrogram_pipeline = SpectrogramPipeline::new(
+            key.clone(),
+            config.window_seconds,
+            config.history_seconds,
+            config.detrend_method.clone(),
+            config.psd.clone(),
+            config.filter.clone(),
+        );
+        let (spec_id, spec_input_tx) =
+            self.spawn_derived_pipeline(spectrogram_pipeline, key.clone());
+
+        let spec_handle = self
+            .pipeline_threads
+            .get(&spec_id)
+            .ok_or("Spectrogram handle not found")?;
+        spec_handle
+            .cmd_tx
+            .send(PipelineCommand::Hydrate)
+            .map_err(|e| e.to_string())?;
+
+        let handle = self
+            .pipeline_threads
+            .get(&detrend_id)
+            .ok_or("Detrend handle not found")?;
+        handle
+            .cmd_tx
+            .send(PipelineCommand::AddSubscriber(spec_input_tx))
+            .map_err(|e| e.to_string())?;
+
+        Ok((spec_id, detrend_id))
+    }
+
+    /// A detrend chain on the reference and on `key`, both feeding one cross-spectral pipeline.
+    fn _create_cross_chain_for_plot(
+        &mut self,
+        key: &DataColumnId,
+        config: &CrossSpectrumConfig,
+    ) -> Result<(PipelineId, [PipelineId; 2]), String> {
+        let pipeline = CrossSpectralPipeline::new(config);
+        let source_keys = [config.reference.clone(), key.clone()];
+        let (cross_id, input_txs) = self.spawn_paired_pipeline(pipeline, source_keys.clone());
+
+        let mut detrend_ids = Vec::with_capacity(2);
+        for (input, source_key) in [REFERENCE_INPUT, COLUMN_INPUT].into_iter().zip(source_keys) {
+            let detrend_pipeline = DetrendPipeline::new(
+                source_key.clone(),
+                config.window_seconds,
+                config.detrend_method.clone(),
+            );
+            let detrend_id = self.spawn_filtered_root_pipeline(
+                detrend_pipeline,
+                &source_key,
+                config.filter.as_ref(),
+                config.window_seconds,
+            );
+            let handle = self
+                .pipeline_threads
+                .get(&detrend_id)
+                .ok_or("Detrend handle not found")?;
+            handle
+                .cmd_tx
+                .send(PipelineCommand::AddSubscriber(input_txs[input].clone()))
+                .map_err(|e| e.to_string())?;
+            detrend_ids.push(detrend_id);
+        }
+
+        Ok((cross_id, [detrend_ids[0], detrend_ids[1]]))
+    }
+
+    /// A window of the x column and of `key`, both feeding one pairing pipeline.
+    fn _create_xy_chain_for_plot(
+        &mut self,
+        key: &DataColumnId,
+        config: &XyConfig,
+    ) -> Result<(PipelineId, [PipelineId; 2]), String> {
+        let pipeline = XyPipeline::new();
+        let source_keys = [config.x.clone(), key.clone()];
+        let (xy_id, input_txs) = self.spawn_paired_pipeline(pipeline, source_keys.clone());
+
+        let mut window_ids = Vec::with_capacity(2);
+        for (input, source_key) in [X_INPUT, Y_INPUT].into_iter().zip(source_keys) {
+            let window_pipeline =
+                DetrendPipeline::raw(source_key.clone(), config.persistence_seconds);
+            let window_id = self.spawn_filtered_root_pipeline(
+                window_pipeline,
+                &source_key,
+                config.filter.as_ref(),
+                config.persistence_seconds,
+            );
+            let handle = self
+                .pipeline_threads
+                .get(&window_id)
+                .ok_or("Window handle not found")?;
+            handle
+                .cmd_tx
+                .send(PipelineCommand::AddSubscriber(input_txs[input].clone()))
+                .map_err(|e| e.to_string())?;
+            window_ids.push(window_id);
+        }
+
+        Ok((xy_id, [window_ids[0], window_ids[1]]))
+    }
+
+    fn _create_trigger_for_plot(
+        &mut self,
+        keys: &[DataColumnId],
+        config: &TriggerConfig,
+    ) -> PipelineId {
+        let pipeline =
+            TriggerPipeline::new(config.clone(), keys.to_vec(), self.capture_state.clone());
+        self.spawn_root_pipeline(pipeline, config.source.clone())
+    }
+
+    fn _create_allan_for_plot(&mut self, key: &DataColumnId, config: &AllanConfig) -> PipelineId {
+        let pipeline = AllanPipeline::new(key.clone(), config.clone(), self.capture_state.clone());
+        self.spawn_root_pipeline(pipeline, key.clone())
+    }
+
+    fn _create_histogram_for_plot(
+        &mut self,
+        key: &DataColumnId,
+        config: &HistogramConfig,
+    ) -> PipelineId {
+        let pipeline = HistogramPipeline::new(key.clone(), config.clone());
+        self.spawn_root_pipeline(pipeline, key.clone())
+    }
+
     fn _destroy_plot_components(&mut self, plot_id: &str) {
         if let Some(plot) = self.managed_plots.remove(plot_id) {
             for id in plot.all_component_ids.iter().rev() {
@@ -318,16 +613,35 @@ impl ProcessingManager {
         }
     }
 
+    /// Configs of every managed plot, in creation order.
+    pub fn plot_configs(&self) -> Vec<SharedPlotConfig> {
+        self.plot_order
+            .iter()
+            .filter_map(|id| self.managed_plots.get(id))
+            .map(|p| p.config.clone())
+            .collect()
+    }
+
     pub fn get_merged_data_for_plot(&self, plot_id: &str) -> Option<PlotData> {
         let managed_plot = self.managed_plots.get(plot_id)?;
-        let mut data_to_merge = Vec::with_capacity(managed_plot.output_pipeline_ids.len());
+        let mut outputs = Vec::with_capacity(managed_plot.output_pipeline_ids.len());
         for id in &managed_plot.output_pipeline_ids {
             if let Some(p) = self.pipelines.get(id) {
                 if let Ok(pipeline) = p.try_lock() {
-                    data_to_merge.push(pipeline.get_output());
+                    outputs.push(pipeline.get_outputs().into_iter());
                 }
             }
         }
+        // Every pipeline's first output, then every second one, so extra series (histogram
+        // fits) follow all of the plot's columns, as a trigger's σ series do.
+        let mut data_to_merge = Vec::with_capacity(outputs.len());
+        loop {
+            let before = data_to_merge.len();
+            data_to_merge.extend(outputs.iter_mut().filter_map(Iterator::next));
+            if data_to_merge.len() == before {
+                break;
+            }
+        }
         if data_to_merge.is_empty() {
             None
         } else {
@@ -335,6 +649,95 @@ impl ProcessingManager {
         }
     }
 
+    /// How each FFT output of a plot estimated its latest spectrum.
+    pub fn get_spectrum_status(&self, plot_id: &str) -> Vec<SpectrumStatus> {
+        let Some(managed_plot) = self.managed_plots.get(plot_id) else {
+            return Vec::new();
+        };
+        managed_plot
+            .output_pipeline_ids
+            .iter()
+            .filter_map(|id| self.pipelines.get(id))
+            .filter_map(|p| p.try_lock().ok()?.spectrum_status())
+            .collect()
+    }
+
+    /// What each Allan deviation output of a plot was computed over.
+    pub fn get_allan_status(&self, plot_id: &str) -> Vec<AllanStatus> {
+        let Some(managed_plot) = self.managed_plots.get(plot_id) else {
+            return Vec::new();
+        };
+        managed_plot
+            .output_pipeline_ids
+            .iter()
+            .filter_map(|id| self.pipelines.get(id))
+            .filter_map(|p| p.try_lock().ok()?.allan_status())
+            .collect()
+    }
+
+    /// Bins and window statistics of each histogram output of a plot.
+    pub fn get_histogram_status(&self, plot_id: &str) -> Vec<HistogramStatus> {
+        let Some(managed_plot) = self.managed_plots.get(plot_id) else {
+            return Vec::new();
+        };
+        managed_plot
+            .output_pipeline_ids
+            .iter()
+            .filter_map(|id| self.pipelines.get(id))
+            .filter_map(|p| p.try_lock().ok()?.histogram_status())
+            .collect()
+    }
+
+    /// Trigger state of a triggered plot, or `None` for other views.
+    pub fn get_trigger_status(&self, plot_id: &str) -> Option<TriggerStatus> {
+        let managed_plot = self.managed_plots.get(plot_id)?;
+        managed_plot
+            .output_pipeline_ids
+            .iter()
+            .filter_map(|id| self.pipelines.get(id))
+            .find_map(|p| p.try_lock().ok()?.trigger_status())
+    }
+
+    /// Re-arms a triggered plot after a single capture.
+    pub fn rearm_trigger(&self, plot_id: &str) -> Result<(), String> {
+        let managed_plot = self
+            .managed_plots
+            .get(plot_id)
+            .ok_or_else(|| format!("No plot {}", plot_id))?;
+        for id in &managed_plot.output_pipeline_ids {
+            if let Some(handle) = self.pipeline_threads.get(id) {
+                handle
+                    .cmd_tx
+                    .send(PipelineCommand::Rearm)
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Sends each spectrogram plot the frames its channel hasn't seen yet.
+    fn emit_spectrogram_frames(&mut self) {
+        for (plot_id, channel) in &self.spectrogram_channels {
+            let Some(managed_plot) = self.managed_plots.get(plot_id) else {
+                continue;
+            };
+            for id in &managed_plot.output_pipeline_ids {
+                let Some(p) = self.pipelines.get(id) else {
+                    continue;
+                };
+                let Ok(pipeline) = p.try_lock() else {
+                    continue;
+                };
+                let cursor = self.frame_cursors.get(id).copied();
+                if let Some((data, next)) = pipeline.get_frames(cursor) {
+                    if channel.send(data).is_ok() {
+                        self.frame_cursors.insert(*id, next);
+                    }
+                }
+            }
+        }
+    }
+
     pub fn create_statistics_provider(
         &mut self,
         source_key: DataColumnId,
@@ -348,7 +751,7 @@ impl ProcessingManager {
         let (cmd_tx, cmd_rx) = bounded(16);
         let (data_tx, data_rx) = bounded(128);
 
-        let sub_id = self.next_sub_id.fetch_add(1, Ordering::Relaxed);
+        let sub_id = self.capture_state.next_subscriber_id();
         println!(
             "[Stats] Creating provider {:?} for stream (url={}, route={}, stream={}, col={}, window={:.2}s); sub_id={}",
             id, source_key.port_url, source_key.device_route, source_key.stream_id, source_key.column_index, window_seconds, sub_id
@@ -421,7 +824,7 @@ impl ProcessingManager {
                         id: *sub_id,
                     });
                 }
-                ThreadType::Derived {..}=> {
+                ThreadType::Derived {..} | ThreadType::Paired {..} => {
                     println!("[Pipeline] Destroying derived pipeline {:?}.", id);
                 }
             }
@@ -434,6 +837,7 @@ impl ProcessingManager {
             }
         }
 
+        self.frame_cursors.remove(&id);
         let removed_p = self.pipelines.remove(&id).is_some();
         let removed_s = self.stat_providers.remove(&id).is_some();
         let removed_c = self.statistics_channels.remove(&id).is_some();
@@ -461,6 +865,11 @@ impl ProcessingManager {
                         let _ = handle.cmd_tx.send(PipelineCommand::ResetSelf);
                     }
                 }
+                ThreadType::Paired { source_keys } => {
+                    if source_keys.contains(column_key) {
+                        let _ = handle.cmd_tx.send(PipelineCommand::ResetSelf);
+                    }
+                }
             }
         }
         for (plot_id, managed) in &self.managed_plots {
@@ -487,6 +896,8 @@ impl ProcessingManager {
                 ThreadType::Root { source_key, .. }
                 | ThreadType::Statistics { source_key, .. }
                 | ThreadType::Derived { source_key } => source_key,
+                // Its inputs' root pipelines are covered on their own.
+                ThreadType::Paired { .. } => continue,
             };
             if source_key.stream_key() == stream_key && !column_keys.contains(source_key) {
                 column_keys.push(source_key.clone());
@@ -514,6 +925,8 @@ impl ProcessingManager {
                 ThreadType::Root { source_key, .. }
                 | ThreadType::Statistics { source_key, .. }
                 | ThreadType::Derived { source_key } => source_key,
+                // Its inputs' root pipelines are covered on their own.
+                ThreadType::Paired { .. } => continue,
             };
             if source_key.device_key() == device_key {
                 let sk = source_key.stream_key();
@@ -550,7 +963,7 @@ impl ProcessingManager {
             let key_opt = match &h.thread_type {
                 ThreadType::Root { source_key, .. } |
                 ThreadType::Statistics { source_key, .. } => Some(source_key.clone()),
-                ThreadType::Derived { .. } => None,
+                ThreadType::Derived { .. } | ThreadType::Paired { .. } => None,
             };
             (h.cmd_tx.clone(), key_opt)
         };
@@ -570,6 +983,20 @@ impl ProcessingManager {
         self.plot_channels.insert(plot_id, channel);
     }
 
+    /// A new listener starts from a full snapshot of every spectrogram in the plot.
+    pub fn register_spectrogram_channel(
+        &mut self,
+        plot_id: String,
+        channel: Channel<SpectrogramData>,
+    ) {
+        if let Some(managed_plot) = self.managed_plots.get(&plot_id) {
+            for id in &managed_plot.output_pipeline_ids {
+                self.frame_cursors.remove(id);
+            }
+        }
+        self.spectrogram_channels.insert(plot_id, channel);
+    }
+
     pub fn register_statistics_channel(
         &mut self,
         provider_id: PipelineId,
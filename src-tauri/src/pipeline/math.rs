@@ -0,0 +1,448 @@
+//! Arithmetic expressions over named variables, for math channels.
+//!
+//! Supports `+ - * / ^` (`**` is accepted for `^`), unary minus, parentheses, the constants `pi`
+//! and `e`, and the functions listed in `Function::parse`. Variables shadow the constants.
+
+#[derive(Clone, Copy, Debug)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Function {
+    Sqrt,
+    Abs,
+    Sin,
+    Cos,
+    Tan,
+    Asin,
+    Acos,
+    Atan,
+    Exp,
+    Ln,
+    Log10,
+    Atan2,
+    Min,
+    Max,
+    Pow,
+    Hypot,
+}
+
+impl Function {
+    /// The function called `name` and the number of arguments it takes.
+    fn parse(name: &str) -> Option<(Self, usize)> {
+        let f = match name {
+            "sqrt" => (Self::Sqrt, 1),
+            "abs" => (Self::Abs, 1),
+            "sin" => (Self::Sin, 1),
+            "cos" => (Self::Cos, 1),
+            "tan" => (Self::Tan, 1),
+            "asin" => (Self::Asin, 1),
+            "acos" => (Self::Acos, 1),
+            "atan" => (Self::Atan, 1),
+            "exp" => (Self::Exp, 1),
+            "ln" | "log" => (Self::Ln, 1),
+            "log10" => (Self::Log10, 1),
+            "atan2" => (Self::Atan2, 2),
+            "min" => (Self::Min, 2),
+            "max" => (Self::Max, 2),
+            "pow" => (Self::Pow, 2),
+            "hypot" => (Self::Hypot, 2),
+            _ => return None,
+        };
+        Some(f)
+    }
+
+    fn apply(self, args: &[f64]) -> f64 {
+        match self {
+            Self::Sqrt => args[0].sqrt(),
+            Self::Abs => args[0].abs(),
+            Self::Sin => args[0].sin(),
+            Self::Cos => args[0].cos(),
+            Self::Tan => args[0].tan(),
+            Self::Asin => args[0].asin(),
+            Self::Acos => args[0].acos(),
+            Self::Atan => args[0].atan(),
+            Self::Exp => args[0].exp(),
+            Self::Ln => args[0].ln(),
+            Self::Log10 => args[0].log10(),
+            Self::Atan2 => args[0].atan2(args[1]),
+            Self::Min => args[0].min(args[1]),
+            Self::Max => args[0].max(args[1]),
+            Self::Pow => args[0].powf(args[1]),
+            Self::Hypot => args[0].hypot(args[1]),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    Const(f64),
+    Var(usize),
+    Neg(Box<Node>),
+    Binary(BinaryOp, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, values: &[f64]) -> f64 {
+        match self {
+            Node::Const(c) => *c,
+            Node::Var(i) => values[*i],
+            Node::Neg(a) => -a.eval(values),
+            Node::Binary(op, a, b) => {
+                let (a, b) = (a.eval(values), b.eval(values));
+                match op {
+                    BinaryOp::Add => a + b,
+                    BinaryOp::Sub => a - b,
+                    BinaryOp::Mul => a * b,
+                    BinaryOp::Div => a / b,
+                    BinaryOp::Pow => a.powf(b),
+                }
+            }
+            Node::Call(f, args) => {
+                let args: Vec<f64> = args.iter().map(|a| a.eval(values)).collect();
+                f.apply(&args)
+            }
+        }
+    }
+
+    fn collect_vars(&self, used: &mut [bool]) {
+        match self {
+            Node::Const(_) => {}
+            Node::Var(i) => used[*i] = true,
+            Node::Neg(a) => a.collect_vars(used),
+            Node::Binary(_, a, b) => {
+                a.collect_vars(used);
+                b.collect_vars(used);
+            }
+            Node::Call(_, args) => args.iter().for_each(|a| a.collect_vars(used)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(v) => write!(f, "number {}", v),
+            Token::Ident(name) => write!(f, "'{}'", name),
+            Token::Op(c) => write!(f, "'{}'", c),
+            Token::LParen => write!(f, "'('"),
+            Token::RParen => write!(f, "')'"),
+            Token::Comma => write!(f, "','"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || c == '.' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent, only if digits follow; otherwise the `e` starts the next token.
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                let mut j = i + 1;
+                if j < chars.len() && (chars[j] == '+' || chars[j] == '-') {
+                    j += 1;
+                }
+                if j < chars.len() && chars[j].is_ascii_digit() {
+                    i = j;
+                    while i < chars.len() && chars[i].is_ascii_digit() {
+                        i += 1;
+                    }
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let value = text
+                .parse()
+                .map_err(|_| format!("Invalid number '{}'", text))?;
+            tokens.push(Token::Number(value));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            i += 1;
+            match c {
+                '*' if chars.get(i) == Some(&'*') => {
+                    i += 1;
+                    tokens.push(Token::Op('^'));
+                }
+                '+' | '-' | '*' | '/' | '^' => tokens.push(Token::Op(c)),
+                '(' => tokens.push(Token::LParen),
+                ')' => tokens.push(Token::RParen),
+                ',' => tokens.push(Token::Comma),
+                _ => return Err(format!("Unexpected character '{}'", c)),
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    variables: &'a [String],
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), String> {
+        match self.next() {
+            Some(t) if t == expected => Ok(()),
+            _ => Err(format!("Expected {}", what)),
+        }
+    }
+
+    fn expression(&mut self) -> Result<Node, String> {
+        let mut node = self.term()?;
+        while let Some(Token::Op(c @ ('+' | '-'))) = self.peek() {
+            let op = if *c == '+' {
+                BinaryOp::Add
+            } else {
+                BinaryOp::Sub
+            };
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.term()?));
+        }
+        Ok(node)
+    }
+
+    fn term(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while let Some(Token::Op(c @ ('*' | '/'))) = self.peek() {
+            let op = if *c == '*' {
+                BinaryOp::Mul
+            } else {
+                BinaryOp::Div
+            };
+            self.pos += 1;
+            node = Node::Binary(op, Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    /// Unary minus binds looser than `^`, so `-x^2` is `-(x^2)`.
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.peek() {
+            Some(Token::Op('-')) => {
+                self.pos += 1;
+                Ok(Node::Neg(Box::new(self.unary()?)))
+            }
+            Some(Token::Op('+')) => {
+                self.pos += 1;
+                self.unary()
+            }
+            _ => self.power(),
+        }
+    }
+
+    /// Right-associative: `2^3^2` is `2^(3^2)`.
+    fn power(&mut self) -> Result<Node, String> {
+        let base = self.primary()?;
+        if let Some(Token::Op('^')) = self.peek() {
+            self.pos += 1;
+            let exponent = self.unary()?;
+            return Ok(Node::Binary(
+                BinaryOp::Pow,
+                Box::new(base),
+                Box::new(exponent),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn primary(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some(Token::Number(v)) => Ok(Node::Const(v)),
+            Some(Token::LParen) => {
+                let node = self.expression()?;
+                self.expect(Token::RParen, "')'")?;
+                Ok(node)
+            }
+            Some(Token::Ident(name)) => {
+                if self.peek() == Some(&Token::LParen) {
+                    self.pos += 1;
+                    return self.call(&name);
+                }
+                if let Some(i) = self.variables.iter().position(|v| *v == name) {
+                    return Ok(Node::Var(i));
+                }
+                match name.as_str() {
+                    "pi" => Ok(Node::Const(std::f64::consts::PI)),
+                    "e" => Ok(Node::Const(std::f64::consts::E)),
+                    _ => Err(format!("Unknown variable '{}'", name)),
+                }
+            }
+            Some(t) => Err(format!("Unexpected {}", t)),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Node, String> {
+        let (function, arity) =
+            Function::parse(name).ok_or_else(|| format!("Unknown function '{}'", name))?;
+        let mut args = vec![self.expression()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.expression()?);
+        }
+        self.expect(Token::RParen, "')'")?;
+        if args.len() != arity {
+            return Err(format!(
+                "{}() takes {} argument(s), got {}",
+                name,
+                arity,
+                args.len()
+            ));
+        }
+        Ok(Node::Call(function, args))
+    }
+}
+
+/// A parsed expression. Variables are referred to by their index in the list it was parsed with.
+#[derive(Clone, Debug)]
+pub struct Expression {
+    root: Node,
+    used: Vec<bool>,
+}
+
+impl Expression {
+    pub fn parse(source: &str, variables: &[String]) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            pos: 0,
+            variables,
+        };
+        if parser.tokens.is_empty() {
+            return Err("Expression is empty".to_string());
+        }
+        let root = parser.expression()?;
+        if let Some(t) = parser.peek() {
+            return Err(format!("Unexpected {}", t));
+        }
+        let mut used = vec![false; variables.len()];
+        root.collect_vars(&mut used);
+        Ok(Self { root, used })
+    }
+
+    /// Whether the variable at `index` appears in the expression.
+    pub fn uses(&self, index: usize) -> bool {
+        self.used.get(index).copied().unwrap_or(false)
+    }
+
+    /// `values` is indexed like the variables the expression was parsed with.
+    pub fn eval(&self, values: &[f64]) -> f64 {
+        self.root.eval(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(source: &str, vars: &[(&str, f64)]) -> f64 {
+        let names: Vec<String> = vars.iter().map(|(n, _)| n.to_string()).collect();
+        let values: Vec<f64> = vars.iter().map(|(_, v)| *v).collect();
+        Expression::parse(source, &names).unwrap().eval(&values)
+    }
+
+    fn parse_error(source: &str) -> String {
+        Expression::parse(source, &["x".to_string()]).unwrap_err()
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3", &[]), 7.0);
+        assert_eq!(eval("(1 + 2) * 3", &[]), 9.0);
+        assert_eq!(eval("8 / 4 / 2", &[]), 1.0);
+        assert_eq!(eval("10 - 4 - 3", &[]), 3.0);
+        assert_eq!(eval("2 * 3 ^ 2", &[]), 18.0);
+        assert_eq!(eval("2 ** 3", &[]), 8.0);
+    }
+
+    #[test]
+    fn unary_minus_binds_looser_than_power() {
+        assert_eq!(eval("-x^2", &[("x", 3.0)]), -9.0);
+        assert_eq!(eval("(-x)^2", &[("x", 3.0)]), 9.0);
+        assert_eq!(eval("2^-1", &[]), 0.5);
+        assert_eq!(eval("--x", &[("x", 3.0)]), 3.0);
+    }
+
+    #[test]
+    fn power_is_right_associative() {
+        assert_eq!(eval("2^3^2", &[]), 512.0);
+        assert_eq!(eval("(2^3)^2", &[]), 64.0);
+    }
+
+    #[test]
+    fn variables_shadow_constants() {
+        assert_eq!(eval("e", &[]), std::f64::consts::E);
+        assert_eq!(eval("e", &[("e", 2.0)]), 2.0);
+        assert_eq!(eval("2e3 + 1e-3", &[]), 2000.001);
+        assert_eq!(eval("hypot(a, b)", &[("a", 3.0), ("b", 4.0)]), 5.0);
+    }
+
+    #[test]
+    fn arity_is_checked() {
+        assert_eq!(
+            parse_error("sqrt(x, 2)"),
+            "sqrt() takes 1 argument(s), got 2"
+        );
+        assert_eq!(
+            parse_error("atan2(x)"),
+            "atan2() takes 2 argument(s), got 1"
+        );
+        assert_eq!(parse_error("foo(x)"), "Unknown function 'foo'");
+    }
+
+    #[test]
+    fn malformed_expressions_are_rejected() {
+        assert_eq!(parse_error(""), "Expression is empty");
+        assert_eq!(parse_error("y + 1"), "Unknown variable 'y'");
+        assert!(parse_error("(x + 1").starts_with("Expected"));
+        assert!(parse_error("x x").starts_with("Unexpected"));
+        assert!(parse_error("x $ 1").starts_with("Unexpected character"));
+    }
+
+    #[test]
+    fn used_variables_are_tracked() {
+        let names = ["x".to_string(), "y".to_string()];
+        let expression = Expression::parse("sin(x) * 2", &names).unwrap();
+        assert!(expression.uses(0));
+        assert!(!expression.uses(1));
+    }
+}
@@ -1,4 +1,7 @@
-use crate::shared::{PipelineId, PlotData, ColumnStatistics};
+use crate::shared::{
+    AllanStatus, ColumnStatistics, HistogramStatus, PipelineId, PlotData, Point, SpectrogramData,
+    SpectrumStatus, TriggerStatus,
+};
 use crate::state::capture::{BatchedData, CaptureState};
 use crossbeam::channel::Sender;
 use std::sync::Arc;
@@ -9,14 +12,54 @@ pub enum PipelineCommand {
     AddSubscriber(Sender<(PlotData, f64)>),
     Shutdown,
     ResetSelf,
+    /// Re-arms a trigger stopped after its single capture.
+    Rearm,
+}
+
+/// How far a plot's spectrogram channel has been sent: frames up to `last_time` of the pipeline's
+/// current `generation` (bumped whenever its frames are cleared or re-binned).
+#[derive(Clone, Copy, Debug)]
+pub struct FrameCursor {
+    pub generation: u64,
+    pub last_time: f64,
 }
 
 /// The core trait for a processing stage.
 pub trait Pipeline: Send + Sync {
     fn id(&self) -> PipelineId;
     fn get_output(&self) -> PlotData;
+    /// One `PlotData` per plotted column, for pipelines that serve a whole plot.
+    fn get_outputs(&self) -> Vec<PlotData> {
+        vec![self.get_output()]
+    }
     fn process_batch(&mut self, _batch: Arc<BatchedData>) {}
     fn process_derived_batch(&mut self, _batch: (PlotData, f64)) {}
+    /// Output of the `input`th upstream pipeline, for pipelines with two inputs.
+    fn process_paired_batch(&mut self, _input: usize, _batch: (PlotData, f64)) {}
+    /// Replaces the pipeline's history with `points`, as `Hydrate` does with the window it reads
+    /// from `CaptureState`. Lets a stage in front of the pipeline hydrate it with its own output.
+    fn backfill(&mut self, _points: Vec<Point>) {}
+    /// Frames after `cursor` for pipelines with a 2D (waterfall) output, or everything if
+    /// `cursor` is `None` or stale. `None` when there is nothing new.
+    fn get_frames(&self, _cursor: Option<FrameCursor>) -> Option<(SpectrogramData, FrameCursor)> {
+        None
+    }
+    /// How the latest spectrum was estimated, for pipelines that produce one.
+    fn spectrum_status(&self) -> Option<SpectrumStatus> {
+        None
+    }
+    /// State of the trigger, for pipelines that capture triggered frames.
+    fn trigger_status(&self) -> Option<TriggerStatus> {
+        None
+    }
+    /// What an Allan deviation was computed over.
+    fn allan_status(&self) -> Option<AllanStatus> {
+        None
+    }
+    /// Bins and statistics of the latest histogram.
+    fn histogram_status(&self) -> Option<HistogramStatus> {
+        None
+    }
     fn process_command(&mut self, cmd: PipelineCommand, capture: &CaptureState);
 }
 
@@ -26,9 +69,19 @@ pub trait StatisticsProvider: Send + Sync {
     fn process_batch(&mut self, batch: Arc<BatchedData>);
     fn reset(&mut self);
 }
+pub mod allan;
+pub mod cross;
 pub mod decimation;
 pub mod detrend;
 pub mod fft;
+pub mod filter;
+pub mod histogram;
+pub mod lockin;
 pub mod manager;
+pub mod math;
 pub mod passthrough;
+pub mod spectral;
+pub mod spectrogram;
 pub mod statistics;
+pub mod trigger;
+pub mod xy;
@@ -86,13 +86,17 @@ impl Pipeline for PassthroughPipeline {
                     start_time,
                     latest_time,
                 );
-                if let Some(points) = raw_data_vecs.get(0) {
-                    let mut buffer = self.buffer.lock().unwrap();
-                    buffer.clear();
-                    buffer.extend(points);
+                if let Some(points) = raw_data_vecs.into_iter().next() {
+                    self.backfill(points);
                 }
             }
             _ => {}
         }
     }
+
+    fn backfill(&mut self, points: Vec<Point>) {
+        let mut buffer = self.buffer.lock().unwrap();
+        buffer.clear();
+        buffer.extend(points);
+    }
 }
@@ -0,0 +1,739 @@
+//! Welch spectral density estimation: windowed, overlapping segments averaged in power.
+//!
+//! Densities are one-sided and scaled by `1 / (fs · Σw²)`, i.e. normalised by the window's
+//! equivalent noise bandwidth, so a white noise floor reads the same for every window.
+use crate::shared::{
+    FrameAveraging, GapHandling, PsdConfig, SpectralMethod, SpectrumUnits, WindowFunction,
+};
+use rustfft::{num_complex::Complex, Fft, FftPlanner};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+/// Segments used when neither a segment length nor a segment count is configured.
+const DEFAULT_SEGMENTS: usize = 8;
+/// Shortest segment worth transforming.
+pub const MIN_SEGMENT: usize = 16;
+/// Most grid samples × bins a Lomb-Scargle estimate may take; its cost is O(n·len/2) for every
+/// window, against O(n·log len) for Welch.
+const MAX_LOMB_SCARGLE_WORK: usize = 50_000_000;
+
+/// Cosine-sum coefficients `a0, a1, ...` for `w[i] = Σ (-1)^k a_k cos(2πki/L)`.
+fn coefficients(window: &WindowFunction) -> &'static [f64] {
+    match window {
+        WindowFunction::Hann => &[0.5, 0.5],
+        WindowFunction::BlackmanHarris => &[0.35875, 0.48829, 0.14128, 0.01168],
+        WindowFunction::FlatTop => &[
+            0.21557895,
+            0.41663158,
+            0.277263158,
+            0.083578947,
+            0.006947368,
+        ],
+    }
+}
+
+/// Periodic (DFT-even) window of length `len`.
+pub fn window(kind: &WindowFunction, len: usize) -> Vec<f64> {
+    let a = coefficients(kind);
+    (0..len)
+        .map(|i| {
+            let phase = 2.0 * PI * i as f64 / len as f64;
+            a.iter()
+                .enumerate()
+                .map(|(k, ak)| {
+                    let sign = if k.is_multiple_of(2) { 1.0 } else { -1.0 };
+                    sign * ak * (k as f64 * phase).cos()
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// A spectrum and how it was estimated.
+pub struct Estimate {
+    pub frequencies: Vec<f64>,
+    /// One-sided PSD, units²/Hz.
+    pub psd: Vec<f64>,
+    pub method: SpectralMethod,
+    pub missing_samples: usize,
+    pub gaps: usize,
+    pub segments: usize,
+}
+
+/// Reusable estimator; caches the FFT plan and window between calls of the same length.
+pub struct Welch {
+    config: PsdConfig,
+    planner: FftPlanner<f64>,
+    plan: Option<Arc<dyn Fft<f64>>>,
+    window: Vec<f64>,
+}
+
+impl Welch {
+    pub fn new(config: PsdConfig) -> Self {
+        Self {
+            config,
+            planner: FftPlanner::new(),
+            plan: None,
+            window: Vec::new(),
+        }
+    }
+
+    fn overlap(&self) -> f64 {
+        self.config.overlap.clamp(0.0, 0.95)
+    }
+
+    /// Segment length and hop for a window of `n` samples, or `None` if segments would be too
+    /// short.
+    fn segment_shape(&self, n: usize) -> Option<(usize, usize)> {
+        let len = if self.config.segment_length > 0 {
+            (self.config.segment_length as usize).min(n)
+        } else {
+            let count = match self.config.averages {
+                0 => DEFAULT_SEGMENTS,
+                k => k as usize,
+            };
+            (n as f64 / (1.0 + (count - 1) as f64 * (1.0 - self.overlap()))).floor() as usize
+        };
+        if len < MIN_SEGMENT {
+            return None;
+        }
+        let step = ((len as f64 * (1.0 - self.overlap())).round() as usize).max(1);
+        Some((len, step))
+    }
+
+    fn max_segments(&self) -> usize {
+        match self.config.averages {
+            0 => usize::MAX,
+            k => k as usize,
+        }
+    }
+
+    /// One-sided PSD of `y` (units²/Hz): bin frequencies from DC to Nyquist, and densities.
+    /// Uses the most recent segments, so the last sample always contributes. `y` must be
+    /// evenly sampled and free of NaNs; see `estimate` for anything else.
+    pub fn psd(&mut self, y: &[f64], sample_rate: f64) -> Option<(Vec<f64>, Vec<f64>)> {
+        if sample_rate <= 0.0 {
+            return None;
+        }
+        let (len, step) = self.segment_shape(y.len())?;
+        let fit = (y.len() - len) / step + 1;
+        let starts: Vec<usize> = (0..fit.min(self.max_segments()))
+            .map(|k| y.len() - len - k * step)
+            .collect();
+        Some(self.average_segments(y, &starts, len, sample_rate))
+    }
+
+    /// Gap-aware estimate from a detrended window with timestamps `t` (unified seconds), of at
+    /// most `window_len` samples.
+    ///
+    /// Samples are placed on a grid at `sample_rate` counted back from the last one; device
+    /// timestamps are `sample_number / rate`, so empty grid slots are exactly the dropped
+    /// samples. Those and NaN values are missing, and `GapHandling` decides what happens.
+    /// The grid spans the full window whatever was dropped, so the bins stay put.
+    ///
+    /// `None` if there is too little data; an error if the window is too long for the
+    /// Lomb-Scargle estimate its gaps call for.
+    pub fn estimate(
+        &mut self,
+        t: &[f64],
+        y: &[f64],
+        sample_rate: f64,
+        window_len: usize,
+    ) -> Result<Option<Estimate>, String> {
+        if sample_rate <= 0.0 || y.is_empty() {
+            return Ok(None);
+        }
+        let grid = regularize(t, y, sample_rate, window_len);
+        let n = grid.len();
+        let Some((len, step)) = self.segment_shape(n) else {
+            return Ok(None);
+        };
+        let runs = finite_runs(&grid);
+        let present: usize = runs.iter().map(|r| r.1).sum();
+        let missing_samples = n - present;
+        let gaps = count_gaps(&runs, n);
+
+        let estimate = |frequencies, psd, method, segments| Estimate {
+            frequencies,
+            psd,
+            method,
+            missing_samples,
+            gaps,
+            segments,
+        };
+
+        if missing_samples == 0 {
+            let fit = (n - len) / step + 1;
+            let count = fit.min(self.max_segments());
+            return Ok(self
+                .psd(&grid, sample_rate)
+                .map(|(f, p)| estimate(f, p, SpectralMethod::Contiguous, count)));
+        }
+        if present < MIN_SEGMENT {
+            return Ok(None);
+        }
+
+        let longest_gap = longest_gap(&runs, n);
+        let longest_run = runs.iter().map(|r| r.1).max().unwrap_or(0);
+        let method = match self.config.gap_handling {
+            GapHandling::Auto => {
+                if missing_samples * 100 <= n && longest_gap <= (len / 32).max(2) {
+                    SpectralMethod::Interpolated
+                } else if longest_run >= len {
+                    SpectralMethod::Segmented
+                } else {
+                    SpectralMethod::LombScargle
+                }
+            }
+            GapHandling::Interpolate => SpectralMethod::Interpolated,
+            GapHandling::Segments if longest_run >= MIN_SEGMENT => SpectralMethod::Segmented,
+            GapHandling::Segments | GapHandling::LombScargle => SpectralMethod::LombScargle,
+        };
+
+        match method {
+            SpectralMethod::Interpolated => {
+                let filled = interpolate_gaps(&grid, &runs);
+                let count = ((n - len) / step + 1).min(self.max_segments());
+                Ok(self
+                    .psd(&filled, sample_rate)
+                    .map(|(f, p)| estimate(f, p, method, count)))
+            }
+            SpectralMethod::Segmented => {
+                // Only an explicit `Segments` choice gets here with runs shorter than `len`.
+                let len = len.min(longest_run);
+                let step = ((len as f64 * (1.0 - self.overlap())).round() as usize).max(1);
+                let mut starts = Vec::new();
+                'runs: for &(start, run) in runs.iter().rev() {
+                    if run < len {
+                        continue;
+                    }
+                    let mut end = start + run;
+                    while end >= start + len {
+                        if starts.len() == self.max_segments() {
+                            break 'runs;
+                        }
+                        starts.push(end - len);
+                        end -= step;
+                    }
+                }
+                let (f, p) = self.average_segments(&grid, &starts, len, sample_rate);
+                Ok(Some(estimate(f, p, method, starts.len())))
+            }
+            _ => {
+                let bins = len / 2 + 1;
+                if n * bins > MAX_LOMB_SCARGLE_WORK {
+                    return Err(format!(
+                        "The window has gaps that need Lomb-Scargle, which is limited to {} samples \
+                         at {}-sample segments; shorten the window or the segments.",
+                        MAX_LOMB_SCARGLE_WORK / bins,
+                        len
+                    ));
+                }
+                let (f, p) = lomb_scargle(&grid, len, sample_rate);
+                Ok(Some(estimate(f, p, SpectralMethod::LombScargle, 0)))
+            }
+        }
+    }
+
+    /// Averages the windowed periodograms of `y[start..start + len]` for each start.
+    fn average_segments(
+        &mut self,
+        y: &[f64],
+        starts: &[usize],
+        len: usize,
+        sample_rate: f64,
+    ) -> (Vec<f64>, Vec<f64>) {
+        let plan = self.prepare(len);
+        let mut power = vec![0.0; len / 2 + 1];
+        let mut buffer = vec![Complex::new(0.0, 0.0); len];
+        for &start in starts {
+            self.transform(&plan, &y[start..start + len], &mut buffer);
+            for (p, x) in power.iter_mut().zip(&buffer) {
+                *p += x.norm_sqr();
+            }
+        }
+        for (p, scale) in power
+            .iter_mut()
+            .zip(self.density_scale(len, starts.len(), sample_rate))
+        {
+            *p *= scale;
+        }
+        (frequencies(len, sample_rate), power)
+    }
+
+    /// Welch auto- and cross-spectra of two evenly sampled, NaN-free signals of equal length,
+    /// from the most recent segments.
+    pub fn cross(&mut self, x: &[f64], y: &[f64], sample_rate: f64) -> Option<CrossSpectra> {
+        let n = x.len().min(y.len());
+        if sample_rate <= 0.0 {
+            return None;
+        }
+        let (x, y) = (&x[x.len() - n..], &y[y.len() - n..]);
+        let (len, step) = self.segment_shape(n)?;
+        let count = ((n - len) / step + 1).min(self.max_segments());
+
+        let plan = self.prepare(len);
+        let bins = len / 2 + 1;
+        let (mut pxx, mut pyy) = (vec![0.0; bins], vec![0.0; bins]);
+        let mut pxy = vec![Complex::new(0.0, 0.0); bins];
+        let mut bx = vec![Complex::new(0.0, 0.0); len];
+        let mut by = vec![Complex::new(0.0, 0.0); len];
+        for k in 0..count {
+            let start = n - len - k * step;
+            self.transform(&plan, &x[start..start + len], &mut bx);
+            self.transform(&plan, &y[start..start + len], &mut by);
+            for i in 0..bins {
+                pxx[i] += bx[i].norm_sqr();
+                pyy[i] += by[i].norm_sqr();
+                pxy[i] += bx[i].conj() * by[i];
+            }
+        }
+        for (i, scale) in self
+            .density_scale(len, count, sample_rate)
+            .into_iter()
+            .enumerate()
+        {
+            pxx[i] *= scale;
+            pyy[i] *= scale;
+            pxy[i] *= scale;
+        }
+        Some(CrossSpectra {
+            frequencies: frequencies(len, sample_rate),
+            pxx,
+            pyy,
+            pxy,
+            segments: count,
+        })
+    }
+
+    /// The window and FFT plan for `len`-sample segments.
+    fn prepare(&mut self, len: usize) -> Arc<dyn Fft<f64>> {
+        if self.window.len() != len {
+            self.window = window(&self.config.window, len);
+            self.plan = Some(self.planner.plan_fft_forward(len));
+        }
+        self.plan.clone().expect("plan is set with the window")
+    }
+
+    fn transform(&self, plan: &Arc<dyn Fft<f64>>, segment: &[f64], buffer: &mut [Complex<f64>]) {
+        for (out, (&v, &w)) in buffer.iter_mut().zip(segment.iter().zip(&self.window)) {
+            *out = Complex::new(v * w, 0.0);
+        }
+        plan.process(buffer);
+    }
+
+    /// Per-bin factor turning summed |X|² of `count` segments into a one-sided density.
+    fn density_scale(&self, len: usize, count: usize, sample_rate: f64) -> Vec<f64> {
+        let bins = len / 2 + 1;
+        let window_power: f64 = self.window.iter().map(|w| w * w).sum();
+        let scale = 1.0 / (count.max(1) as f64 * sample_rate * window_power);
+        let nyquist = if len.is_multiple_of(2) {
+            bins - 1
+        } else {
+            bins
+        };
+        // Fold negative frequencies in; DC and an even-length Nyquist bin have no mirror.
+        (0..bins)
+            .map(|i| {
+                if i == 0 || i == nyquist {
+                    scale
+                } else {
+                    2.0 * scale
+                }
+            })
+            .collect()
+    }
+}
+
+/// One-sided Welch auto-spectra of x and y and their cross-spectrum conj(X)·Y.
+pub struct CrossSpectra {
+    pub frequencies: Vec<f64>,
+    pub pxx: Vec<f64>,
+    pub pyy: Vec<f64>,
+    pub pxy: Vec<Complex<f64>>,
+    pub segments: usize,
+}
+
+/// Averages successive spectra, of any fixed length, as `FrameAveraging` asks.
+pub struct FrameAverager {
+    mode: FrameAveraging,
+    // The last N spectra for linear averaging, or just the running mean for exponential.
+    history: VecDeque<Vec<f64>>,
+}
+
+impl FrameAverager {
+    pub fn new(mode: FrameAveraging) -> Self {
+        Self {
+            mode,
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+
+    /// Folds `frame` into the average and returns the current average.
+    pub fn push(&mut self, frame: Vec<f64>) -> Vec<f64> {
+        if self.history.front().is_some_and(|f| f.len() != frame.len()) {
+            self.history.clear();
+        }
+        match self.mode {
+            FrameAveraging::None => frame,
+            FrameAveraging::Linear { frames } => {
+                self.history.push_back(frame);
+                while self.history.len() > (frames as usize).max(1) {
+                    self.history.pop_front();
+                }
+                let n = self.history.len() as f64;
+                let mut mean = vec![0.0; self.history[0].len()];
+                for frame in &self.history {
+                    for (m, p) in mean.iter_mut().zip(frame) {
+                        *m += p / n;
+                    }
+                }
+                mean
+            }
+            FrameAveraging::Exponential { frames } => {
+                let alpha = 1.0 / frames.max(1) as f64;
+                match self.history.front_mut() {
+                    Some(mean) => {
+                        for (m, p) in mean.iter_mut().zip(&frame) {
+                            *m += alpha * (p - *m);
+                        }
+                    }
+                    None => self.history.push_back(frame),
+                }
+                self.history[0].clone()
+            }
+        }
+    }
+}
+
+fn frequencies(len: usize, sample_rate: f64) -> Vec<f64> {
+    (0..len / 2 + 1)
+        .map(|i| i as f64 * sample_rate / len as f64)
+        .collect()
+}
+
+/// Places `y` on an even grid ending at the last sample; empty slots are NaN. The grid covers
+/// the samples' time span, up to `max_len` slots; older samples are dropped.
+fn regularize(t: &[f64], y: &[f64], sample_rate: f64, max_len: usize) -> Vec<f64> {
+    let (Some(&first), Some(&last)) = (t.first(), t.last()) else {
+        return y.to_vec();
+    };
+    if t.len() != y.len() {
+        return y.to_vec();
+    }
+    let span = ((last - first) * sample_rate).round() as usize + 1;
+    let n = span.min(max_len.max(1));
+    let mut grid = vec![f64::NAN; n];
+    for (&ti, &yi) in t.iter().zip(y).rev() {
+        let back = ((last - ti) * sample_rate).round();
+        if back < 0.0 || back >= n as f64 {
+            break;
+        }
+        grid[n - 1 - back as usize] = yi;
+    }
+    grid
+}
+
+/// `(start, len)` of every run of finite values.
+fn finite_runs(grid: &[f64]) -> Vec<(usize, usize)> {
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (i, v) in grid.iter().enumerate() {
+        match (v.is_finite(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push((s, i - s));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        runs.push((s, grid.len() - s));
+    }
+    runs
+}
+
+/// Lengths of the missing stretches between, before and after `runs`.
+fn gap_lengths(runs: &[(usize, usize)], n: usize) -> impl Iterator<Item = usize> + '_ {
+    let mut previous_end = 0;
+    runs.iter()
+        .map(move |&(start, len)| {
+            let gap = start - previous_end;
+            previous_end = start + len;
+            gap
+        })
+        .chain(std::iter::once(
+            n - runs.last().map_or(0, |&(start, len)| start + len),
+        ))
+}
+
+fn count_gaps(runs: &[(usize, usize)], n: usize) -> usize {
+    gap_lengths(runs, n).filter(|&g| g > 0).count()
+}
+
+fn longest_gap(runs: &[(usize, usize)], n: usize) -> usize {
+    gap_lengths(runs, n).max().unwrap_or(0)
+}
+
+/// `y` with NaN stretches filled in, as `GapHandling::Interpolate` does.
+pub fn fill_gaps(y: &[f64]) -> Vec<f64> {
+    interpolate_gaps(y, &finite_runs(y))
+}
+
+/// Linear interpolation across interior gaps; edge gaps hold the nearest value.
+fn interpolate_gaps(grid: &[f64], runs: &[(usize, usize)]) -> Vec<f64> {
+    let mut out = grid.to_vec();
+    let (Some(&(first, _)), Some(&(last_start, last_len))) = (runs.first(), runs.last()) else {
+        return out;
+    };
+    out[..first].fill(grid[first]);
+    let end = last_start + last_len;
+    out[end..].fill(grid[end - 1]);
+    for pair in runs.windows(2) {
+        let (a_start, a_len) = pair[0];
+        let b = pair[1].0;
+        let a = a_start + a_len - 1;
+        let slope = (grid[b] - grid[a]) / (b - a) as f64;
+        for (step, v) in out[a + 1..b].iter_mut().enumerate() {
+            *v = grid[a] + slope * (step + 1) as f64;
+        }
+    }
+    out
+}
+
+/// Lomb-Scargle periodogram of the finite samples in `grid`, on the bins of a `len`-sample
+/// Welch segment and scaled to a one-sided PSD. Unlike Welch it uses the whole window at
+/// once, so nothing is averaged and the estimate is noisier.
+fn lomb_scargle(grid: &[f64], len: usize, sample_rate: f64) -> (Vec<f64>, Vec<f64>) {
+    let bins = len / 2 + 1;
+    // Every ω·t is 2π·k·i/len for bin k and grid index i, so one table covers all phases.
+    let cos: Vec<f64> = (0..len)
+        .map(|m| (2.0 * PI * m as f64 / len as f64).cos())
+        .collect();
+    let sin: Vec<f64> = (0..len)
+        .map(|m| (2.0 * PI * m as f64 / len as f64).sin())
+        .collect();
+
+    let (mut yc, mut ys) = (vec![0.0; bins], vec![0.0; bins]);
+    let (mut c2, mut s2) = (vec![0.0; bins], vec![0.0; bins]);
+    let mut count = 0usize;
+    for (i, &v) in grid.iter().enumerate() {
+        if !v.is_finite() {
+            continue;
+        }
+        count += 1;
+        let di = i % len;
+        let mut m = 0;
+        for k in 0..bins {
+            yc[k] += v * cos[m];
+            ys[k] += v * sin[m];
+            let m2 = (2 * m) % len;
+            c2[k] += cos[m2];
+            s2[k] += sin[m2];
+            m = (m + di) % len;
+        }
+    }
+
+    let n = count as f64;
+    let psd = (0..bins)
+        .map(|k| {
+            // τ makes the sine and cosine terms orthogonal over the actual sample times.
+            let h = c2[k].hypot(s2[k]);
+            let (cos_2wt, sin_2wt) = if h > 0.0 {
+                (c2[k] / h, s2[k] / h)
+            } else {
+                (1.0, 0.0)
+            };
+            let wt = 0.5 * sin_2wt.atan2(cos_2wt);
+            let (c, s) = (wt.cos(), wt.sin());
+            let yc_tau = yc[k] * c + ys[k] * s;
+            let ys_tau = ys[k] * c - yc[k] * s;
+            let cc = 0.5 * (n + h);
+            let ss = 0.5 * (n - h);
+            let mut power = 0.0;
+            if cc > 1e-9 * n {
+                power += yc_tau * yc_tau / cc;
+            }
+            if ss > 1e-9 * n {
+                power += ys_tau * ys_tau / ss;
+            }
+            // 0.5·power is the classic normalisation; it equals |X|²/N for even sampling.
+            power / sample_rate
+        })
+        .collect();
+    (frequencies(len, sample_rate), psd)
+}
+
+/// Converts a PSD (units²/Hz) into the requested output units, in place.
+pub fn convert_units(psd: &mut [f64], units: &SpectrumUnits) {
+    match units {
+        SpectrumUnits::Psd => {}
+        SpectrumUnits::Asd => psd.iter_mut().for_each(|p| *p = p.sqrt()),
+        SpectrumUnits::Db { reference } => {
+            // 10·log10(PSD / ref²) == 20·log10(ASD / ref)
+            let reference_power = reference * reference;
+            psd.iter_mut()
+                .for_each(|p| *p = 10.0 * (*p / reference_power).log10());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    const FS: f64 = 1000.0;
+
+    /// Uniform noise on ±0.5: variance 1/12, so a one-sided density of 2 / (12 · fs).
+    fn white_noise(n: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(7);
+        (0..n).map(|_| rng.random_range(-0.5..0.5)).collect()
+    }
+
+    fn sine(n: usize, f: f64, amplitude: f64) -> Vec<f64> {
+        (0..n)
+            .map(|i| amplitude * (2.0 * PI * f * i as f64 / FS).sin())
+            .collect()
+    }
+
+    fn welch(window: WindowFunction, segment_length: u32) -> Welch {
+        Welch::new(PsdConfig {
+            window,
+            segment_length,
+            ..PsdConfig::default()
+        })
+    }
+
+    /// Mean density over the bins between DC and Nyquist.
+    fn mean_level(psd: &[f64]) -> f64 {
+        let inner = &psd[1..psd.len() - 1];
+        inner.iter().sum::<f64>() / inner.len() as f64
+    }
+
+    #[test]
+    fn white_noise_level_is_the_same_for_every_window() {
+        let y = white_noise(1 << 16);
+        let expected = 2.0 / (12.0 * FS);
+        for kind in [
+            WindowFunction::Hann,
+            WindowFunction::BlackmanHarris,
+            WindowFunction::FlatTop,
+        ] {
+            let (_, psd) = welch(kind.clone(), 1024).psd(&y, FS).unwrap();
+            let level = mean_level(&psd);
+            assert!(
+                (level / expected - 1.0).abs() < 0.03,
+                "{:?}: {} vs {}",
+                kind,
+                level,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn sine_power_integrates_to_its_mean_square() {
+        let y = sine(1 << 14, 100.0, 2.0);
+        for kind in [
+            WindowFunction::Hann,
+            WindowFunction::BlackmanHarris,
+            WindowFunction::FlatTop,
+        ] {
+            let (f, psd) = welch(kind.clone(), 1000).psd(&y, FS).unwrap();
+            let df = f[1] - f[0];
+            let peak = (100.0 / df).round() as usize;
+            let power: f64 = psd[peak - 10..=peak + 10].iter().sum::<f64>() * df;
+            assert!((power - 2.0).abs() < 0.02, "{:?}: {}", kind, power);
+        }
+    }
+
+    #[test]
+    fn segments_follow_length_and_overlap() {
+        let mut welch = Welch::new(PsdConfig {
+            segment_length: 256,
+            overlap: 0.5,
+            ..PsdConfig::default()
+        });
+        let (f, psd) = welch.psd(&white_noise(4096), FS).unwrap();
+        assert_eq!(psd.len(), 129);
+        assert_eq!(f.len(), 129);
+        assert!((f[128] - FS / 2.0).abs() < 1e-9);
+        assert_eq!(welch.segment_shape(4096), Some((256, 128)));
+        assert_eq!(welch.segment_shape(MIN_SEGMENT - 1), None);
+    }
+
+    fn times(n: usize) -> Vec<f64> {
+        (0..n).map(|i| i as f64 / FS).collect()
+    }
+
+    #[test]
+    fn lomb_scargle_matches_welch_without_gaps() {
+        let y = white_noise(1 << 14);
+        let (f, welch_psd) = welch(WindowFunction::Hann, 1024).psd(&y, FS).unwrap();
+        let (lomb_f, lomb_psd) = lomb_scargle(&y, 1024, FS);
+        assert_eq!(f, lomb_f);
+        // A single periodogram is noisy bin by bin, but its mean level matches.
+        let ratio = mean_level(&lomb_psd) / mean_level(&welch_psd);
+        assert!((ratio - 1.0).abs() < 0.05, "{}", ratio);
+
+        let y = sine(1 << 14, 125.0, 1.0);
+        let (_, welch_psd) = welch(WindowFunction::Hann, 1024).psd(&y, FS).unwrap();
+        let (_, lomb_psd) = lomb_scargle(&y, 1024, FS);
+        let peak = |psd: &[f64]| {
+            (0..psd.len())
+                .max_by(|&a, &b| psd[a].total_cmp(&psd[b]))
+                .unwrap()
+        };
+        assert_eq!(peak(&lomb_psd), 128);
+        assert_eq!(peak(&welch_psd), 128);
+    }
+
+    #[test]
+    fn estimate_picks_a_method_for_the_gaps() {
+        let n = 1 << 13;
+        let (t, y) = (times(n), white_noise(n));
+        let mut welch = Welch::new(PsdConfig::default());
+        let contiguous = welch.estimate(&t, &y, FS, n).unwrap().unwrap();
+        assert_eq!(contiguous.method, SpectralMethod::Contiguous);
+        assert_eq!(contiguous.missing_samples, 0);
+
+        // One dropped sample is interpolated over.
+        let (mut t1, mut y1) = (t.clone(), y.clone());
+        t1.remove(n / 2);
+        y1.remove(n / 2);
+        let interpolated = welch.estimate(&t1, &y1, FS, n).unwrap().unwrap();
+        assert_eq!(interpolated.method, SpectralMethod::Interpolated);
+        assert_eq!((interpolated.missing_samples, interpolated.gaps), (1, 1));
+        assert_eq!(interpolated.frequencies, contiguous.frequencies);
+
+        // Long NaN runs every few hundred samples leave no run a segment long.
+        let mut y2 = y.clone();
+        for start in (0..n).step_by(300) {
+            y2[start..(start + 40).min(n)].fill(f64::NAN);
+        }
+        let lomb = welch.estimate(&t, &y2, FS, n).unwrap().unwrap();
+        assert_eq!(lomb.method, SpectralMethod::LombScargle);
+        assert_eq!(lomb.frequencies, contiguous.frequencies);
+    }
+
+    #[test]
+    fn lomb_scargle_refuses_windows_it_cannot_afford() {
+        let n = 1 << 16;
+        let mut y = white_noise(n);
+        y[n / 2] = f64::NAN;
+        let mut welch = Welch::new(PsdConfig {
+            segment_length: 4096,
+            gap_handling: GapHandling::LombScargle,
+            ..PsdConfig::default()
+        });
+        assert!(welch.estimate(&times(n), &y, FS, n).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+//! Rolling time × frequency waterfall, fed by a `DetrendPipeline`'s windows.
+use super::detrend::detrend;
+use super::filter::DigitalFilter;
+use super::spectral::{convert_units, Welch};
+use super::{FrameCursor, Pipeline, PipelineCommand};
+use crate::shared::{
+    DataColumnId, DetrendMethod, FilterConfig, PipelineId, PlotData, PsdConfig, SpectrogramData,
+    SpectrumUnits,
+};
+use crate::state::capture::CaptureState;
+use std::collections::VecDeque;
+use std::slice;
+use uuid::Uuid;
+
+/// Frequency bins per frame; wider spectra are averaged (in power) down to this.
+const MAX_BINS: usize = 512;
+
+pub struct SpectrogramPipeline {
+    id: PipelineId,
+    source_key: DataColumnId,
+    window_seconds: f64,
+    history_seconds: f64,
+    method: DetrendMethod,
+    filter: Option<FilterConfig>,
+    welch: Welch,
+    frequencies: Vec<f64>,
+    frames: VecDeque<(f64, Vec<f64>)>,
+    generation: u64,
+    last_frame_time: f64,
+    /// Last estimate error, so it's logged once rather than every frame.
+    error: Option<String>,
+}
+
+impl SpectrogramPipeline {
+    pub fn new(
+        source_key: DataColumnId,
+        window_seconds: f64,
+        history_seconds: f64,
+        method: DetrendMethod,
+        psd: PsdConfig,
+        filter: Option<FilterConfig>,
+    ) -> Self {
+        Self {
+            id: PipelineId(Uuid::new_v4()),
+            source_key,
+            window_seconds,
+            history_seconds,
+            method,
+            filter,
+            welch: Welch::new(psd),
+            frequencies: Vec::new(),
+            frames: VecDeque::new(),
+            generation: 0,
+            last_frame_time: f64::NEG_INFINITY,
+            error: None,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.frames.clear();
+        self.generation += 1;
+        self.last_frame_time = f64::NEG_INFINITY;
+    }
+
+    /// Frames are spaced half a window apart, whatever rate the detrend stage emits at.
+    fn frame_due(&self, time: f64) -> bool {
+        time - self.last_frame_time >= self.window_seconds / 2.0
+    }
+
+    /// Computes one frame from an already detrended window with timestamps `t`.
+    fn push_frame(&mut self, t: &[f64], y: &[f64], sample_rate: f64) {
+        let Some(&time) = t.last() else {
+            return;
+        };
+        // Gaps are handled as in the FFT view; every method keeps the bin layout the same.
+        let window_len = self.window_samples(sample_rate);
+        let estimate = match self.welch.estimate(t, y, sample_rate, window_len) {
+            Ok(Some(estimate)) => estimate,
+            Ok(None) => return,
+            Err(e) => {
+                if self.error.as_ref() != Some(&e) {
+                    eprintln!("[Spectrogram {:?}] {}", self.id, e);
+                    self.error = Some(e);
+                }
+                return;
+            }
+        };
+        self.error = None;
+        let mut asd = estimate.psd;
+        convert_units(&mut asd, &SpectrumUnits::Asd);
+        let frequencies = estimate.frequencies;
+        let (frequencies, asd) = rebin(&frequencies, &asd);
+        if frequencies.is_empty() {
+            return;
+        }
+        if frequencies != self.frequencies {
+            self.clear();
+            self.frequencies = frequencies;
+        }
+
+        self.frames.push_back((time, asd));
+        self.last_frame_time = time;
+        let oldest = time - self.history_seconds;
+        while self.frames.front().is_some_and(|(t, _)| *t < oldest) {
+            self.frames.pop_front();
+        }
+    }
+
+    fn window_samples(&self, sample_rate: f64) -> usize {
+        (sample_rate * self.window_seconds).ceil() as usize
+    }
+}
+
+/// Drops the DC bin and averages groups of bins down to at most `MAX_BINS`.
+fn rebin(frequencies: &[f64], asd: &[f64]) -> (Vec<f64>, Vec<f64>) {
+    let len = frequencies.len().min(asd.len());
+    if len < 2 {
+        return (Vec::new(), Vec::new());
+    }
+    let frequencies = &frequencies[1..len];
+    let asd = &asd[1..len];
+    let group = frequencies.len().div_ceil(MAX_BINS);
+
+    frequencies
+        .chunks(group)
+        .zip(asd.chunks(group))
+        .map(|(f, a)| {
+            let n = f.len() as f64;
+            let power = a.iter().map(|v| v * v).sum::<f64>() / n;
+            (f.iter().sum::<f64>() / n, power.sqrt())
+        })
+        .unzip()
+}
+
+impl Pipeline for SpectrogramPipeline {
+    fn id(&self) -> PipelineId {
+        self.id
+    }
+
+    /// The latest frame, as an FFT view would show it.
+    fn get_output(&self) -> PlotData {
+        match self.frames.back() {
+            Some((_, asd)) => PlotData {
+                timestamps: self.frequencies.clone(),
+                series_data: vec![asd.clone()],
+            },
+            None => PlotData::empty(),
+        }
+    }
+
+    fn process_derived_batch(&mut self, batch: (PlotData, f64)) {
+        let (plot_data, sample_rate) = batch;
+        let (Some(y), Some(&time)) = (plot_data.series_data.first(), plot_data.timestamps.last())
+        else {
+            return;
+        };
+        if sample_rate <= 0.0 || !self.frame_due(time) {
+            return;
+        }
+        // Partial windows (right after a reset) would give frames with a different bin layout.
+        let n = self.window_samples(sample_rate);
+        if n < 16 || y.len() < n || plot_data.timestamps.len() != y.len() {
+            return;
+        }
+        let start = y.len() - n;
+        self.push_frame(&plot_data.timestamps[start..], &y[start..], sample_rate);
+    }
+
+    fn process_command(&mut self, cmd: PipelineCommand, capture_state: &CaptureState) {
+        match cmd {
+            PipelineCommand::ResetSelf => {
+                println!("[Spectrogram {:?}] Received ResetSelf command", self.id);
+                self.clear();
+            }
+            PipelineCommand::Hydrate => {
+                // The detrend stage only backfills its latest window; rebuild the history here.
+                let Some(sr) = capture_state.get_effective_sampling_rate(&self.source_key) else {
+                    return;
+                };
+                let Some(latest_time) =
+                    capture_state.get_latest_unified_timestamp(slice::from_ref(&self.source_key))
+                else {
+                    return;
+                };
+
+                let mut filter = self
+                    .filter
+                    .as_ref()
+                    .and_then(|config| DigitalFilter::design(config, sr).ok());
+                let settle = filter.as_ref().map_or(0.0, |f| f.settle_seconds());
+                let first_window_start = latest_time - self.history_seconds - self.window_seconds;
+                let Some(mut points) = capture_state
+                    .get_data_across_sessions_for_keys(
+                        slice::from_ref(&self.source_key),
+                        first_window_start - settle,
+                        latest_time,
+                    )
+                    .into_iter()
+                    .next()
+                else {
+                    return;
+                };
+                if let Some(filter) = filter.as_mut() {
+                    points = filter.process_all(&points);
+                }
+                points.retain(|p| p.x >= first_window_start);
+
+                let n = self.window_samples(sr);
+                if n < 16 || points.len() < n {
+                    return;
+                }
+                self.clear();
+                let hop = (n / 2).max(1);
+                let mut start = (points.len() - n) % hop;
+                while start + n <= points.len() {
+                    let window = &points[start..start + n];
+                    let t: Vec<f64> = window.iter().map(|p| p.x).collect();
+                    let y: Vec<f64> = window.iter().map(|p| p.y).collect();
+                    self.push_frame(&t, &detrend(&t, &y, &self.method), sr);
+                    start += hop;
+                }
+                println!(
+                    "[Spectrogram {:?}] Hydrated {} frames of {} bins.",
+                    self.id,
+                    self.frames.len(),
+                    self.frequencies.len()
+                );
+            }
+            _ => {}
+        }
+    }
+
+    fn get_frames(&self, cursor: Option<FrameCursor>) -> Option<(SpectrogramData, FrameCursor)> {
+        let reset = cursor.is_none_or(|c| c.generation != self.generation);
+        let since = match cursor {
+            Some(c) if !reset => c.last_time,
+            _ => f64::NEG_INFINITY,
+        };
+
+        let new_frames: Vec<&(f64, Vec<f64>)> =
+            self.frames.iter().filter(|(t, _)| *t > since).collect();
+        if new_frames.is_empty() && !reset {
+            return None;
+        }
+
+        let mut timestamps = Vec::with_capacity(new_frames.len());
+        let mut values = Vec::with_capacity(new_frames.len() * self.frequencies.len());
+        for (t, row) in new_frames {
+            timestamps.push(*t);
+            values.extend_from_slice(row);
+        }
+        let next = FrameCursor {
+            generation: self.generation,
+            last_time: timestamps.last().copied().unwrap_or(since),
+        };
+
+        Some((
+            SpectrogramData {
+                key: self.source_key.clone(),
+                reset,
+                timestamps,
+                frequencies: self.frequencies.clone(),
+                values,
+            },
+            next,
+        ))
+    }
+}
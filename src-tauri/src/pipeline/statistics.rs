@@ -6,8 +6,9 @@ use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+/// Running count, mean, variance (Welford), RMS and extremes of the finite values pushed.
 #[derive(Clone, Debug, Default)]
-struct ValueAccumulator {
+pub(crate) struct ValueAccumulator {
     count: u64,
     mean: f64,
     m2: f64,
@@ -17,7 +18,7 @@ struct ValueAccumulator {
 }
 
 impl ValueAccumulator {
-    fn update(&mut self, v: f64) {
+    pub(crate) fn update(&mut self, v: f64) {
         if !v.is_finite() { return; }
         if self.count == 0 {
             self.count = 1;
@@ -37,7 +38,7 @@ impl ValueAccumulator {
         if v < self.min { self.min = v; }
         if v > self.max { self.max = v; }
     }
-    fn to_stat(&self) -> StatisticSet {
+    pub(crate) fn to_stat(&self) -> StatisticSet {
         if self.count == 0 {
             return StatisticSet::default();
         }
@@ -0,0 +1,425 @@
+//! Oscilloscope-style capture. A `TriggerPipeline` watches one column for a `TriggerCondition`
+//! and, once the post-trigger time has been captured, reads the frame around the trigger for
+//! every plotted column back from `CaptureState`, timed relative to the trigger. With
+//! `averages` set, segments are resampled onto a fixed grid around the trigger and averaged
+//! (synchronous averaging), so a repeatable response stands out of uncorrelated noise.
+use super::{Pipeline, PipelineCommand};
+use crate::shared::{
+    DataColumnId, PipelineId, PlotData, Point, TriggerCondition, TriggerConfig, TriggerMode,
+    TriggerState, TriggerStatus,
+};
+use crate::state::capture::{BatchedData, CaptureState};
+use crate::util::resample_linear;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// How long past the end of a frame to wait for a lagging column before capturing without it.
+const MAX_CAPTURE_WAIT_SECONDS: f64 = 1.0;
+
+/// Evaluates a trigger condition sample by sample.
+struct Detector {
+    condition: TriggerCondition,
+    level: f64,
+    previous: Option<Point>,
+    /// Whether the slope condition held between the previous two samples.
+    slope_met: bool,
+    pulse_start: Option<f64>,
+}
+
+impl Detector {
+    fn new(condition: TriggerCondition, level: f64) -> Self {
+        Self {
+            condition,
+            level,
+            previous: None,
+            slope_met: false,
+            pulse_start: None,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.previous = None;
+        self.slope_met = false;
+        self.pulse_start = None;
+    }
+
+    /// Time at which the condition fired between the previous sample and `p`, if it did.
+    /// Crossings are interpolated between the two samples.
+    fn step(&mut self, p: Point) -> Option<f64> {
+        if !p.y.is_finite() {
+            self.reset();
+            return None;
+        }
+        let prev = self.previous.replace(p)?;
+        if p.x <= prev.x {
+            return None;
+        }
+        let crossing = |value: f64| {
+            if p.y == prev.y {
+                p.x
+            } else {
+                prev.x + (value - prev.y) / (p.y - prev.y) * (p.x - prev.x)
+            }
+        };
+        let level = self.level;
+        let rising = prev.y < level && p.y >= level;
+        let falling = prev.y > level && p.y <= level;
+
+        match self.condition {
+            TriggerCondition::RisingEdge => rising.then(|| crossing(level)),
+            TriggerCondition::FallingEdge => falling.then(|| crossing(level)),
+            TriggerCondition::Level => (rising || falling).then(|| crossing(level)),
+            TriggerCondition::Window { upper } => {
+                let (lo, hi) = (level.min(upper), level.max(upper));
+                let inside = |y: f64| (lo..=hi).contains(&y);
+                if !inside(prev.y) || inside(p.y) {
+                    return None;
+                }
+                Some(crossing(if p.y > hi { hi } else { lo }))
+            }
+            TriggerCondition::Slope { rate } => {
+                let slope = (p.y - prev.y) / (p.x - prev.x);
+                let met = if rate >= 0.0 {
+                    slope >= rate
+                } else {
+                    slope <= rate
+                };
+                let fired = met && !self.slope_met;
+                self.slope_met = met;
+                fired.then_some(p.x)
+            }
+            TriggerCondition::PulseWidth {
+                positive,
+                min_seconds,
+                max_seconds,
+            } => {
+                let (starts, ends) = if positive {
+                    (rising, falling)
+                } else {
+                    (falling, rising)
+                };
+                if starts {
+                    self.pulse_start = Some(crossing(level));
+                    return None;
+                }
+                if !ends {
+                    return None;
+                }
+                let start = self.pulse_start.take()?;
+                let end = crossing(level);
+                let width = end - start;
+                (width >= min_seconds && max_seconds.is_none_or(|max| width <= max)).then_some(end)
+            }
+        }
+    }
+}
+
+pub struct TriggerPipeline {
+    id: PipelineId,
+    config: TriggerConfig,
+    /// Plotted columns; one frame each, in this order.
+    keys: Vec<DataColumnId>,
+    capture: CaptureState,
+    detector: Detector,
+    /// Cleared once a `Single` frame is captured, until `Rearm`.
+    armed: bool,
+    /// Trigger waiting for its post-trigger data.
+    pending: Option<f64>,
+    /// End of the frame on screen; triggers before it are held off.
+    frame_end: f64,
+    /// Latest sample seen on the trigger column.
+    latest: f64,
+    triggers: u32,
+    last_trigger_time: Option<f64>,
+    /// Relative sample times of each column's averaging grid.
+    grids: Vec<Vec<f64>>,
+    /// Resampled segments in the running average, oldest first; one vector per column each.
+    segments: VecDeque<Vec<Vec<f64>>>,
+    frames: Vec<PlotData>,
+}
+
+impl TriggerPipeline {
+    pub fn new(config: TriggerConfig, keys: Vec<DataColumnId>, capture: CaptureState) -> Self {
+        Self {
+            id: PipelineId(Uuid::new_v4()),
+            detector: Detector::new(config.condition.clone(), config.level),
+            config,
+            keys,
+            capture,
+            armed: true,
+            pending: None,
+            frame_end: f64::NEG_INFINITY,
+            latest: f64::NEG_INFINITY,
+            triggers: 0,
+            last_trigger_time: None,
+            grids: Vec::new(),
+            segments: VecDeque::new(),
+            frames: Vec::new(),
+        }
+    }
+
+    fn averaging(&self) -> bool {
+        self.config.averages > 1
+    }
+
+    fn frame_seconds(&self) -> f64 {
+        self.config.pre_trigger_seconds + self.config.post_trigger_seconds
+    }
+
+    /// Captures a pending trigger whose post-trigger data is in, or free-runs in `Auto` mode.
+    fn poll(&mut self) {
+        if let Some(t0) = self.pending {
+            let end = t0 + self.config.post_trigger_seconds;
+            let captured = self.keys.iter().all(|key| {
+                self.capture
+                    .get_latest_unified_timestamp(std::slice::from_ref(key))
+                    .is_none_or(|t| t >= end)
+            });
+            if captured || self.latest >= end + MAX_CAPTURE_WAIT_SECONDS {
+                self.pending = None;
+                if self.averaging() {
+                    self.accumulate(t0);
+                } else {
+                    self.capture_frame(t0);
+                }
+                self.triggers += 1;
+                self.last_trigger_time = Some(t0);
+                // A single shot of an average is complete once it has all its segments.
+                let complete =
+                    !self.averaging() || self.segments.len() >= self.config.averages as usize;
+                if self.config.mode == TriggerMode::Single && complete {
+                    self.armed = false;
+                }
+            }
+        } else if self.config.mode == TriggerMode::Auto
+            && self.segments.is_empty()
+            && self.latest - self.frame_end >= self.frame_seconds()
+        {
+            self.capture_frame(self.latest - self.config.post_trigger_seconds);
+            self.last_trigger_time = None;
+        }
+    }
+
+    /// Adds the segment around `t0` to the running average and shows the average.
+    fn accumulate(&mut self, t0: f64) {
+        let pre = self.config.pre_trigger_seconds;
+        let post = self.config.post_trigger_seconds;
+        let grids: Vec<Vec<f64>> = self
+            .keys
+            .iter()
+            .map(|key| {
+                let rate = self.capture.get_effective_sampling_rate(key).unwrap_or(0.0);
+                averaging_grid(rate, pre, post)
+            })
+            .collect();
+        // A rate change moves the grid; segments on the old one can't be averaged with it.
+        if grids != self.grids {
+            self.segments.clear();
+            self.grids = grids;
+        }
+
+        let data = self
+            .capture
+            .get_data_across_sessions_for_keys(&self.keys, t0 - pre, t0 + post);
+        let segment = data
+            .iter()
+            .zip(&self.keys)
+            .zip(&self.grids)
+            .map(|((points, key), grid)| {
+                let rate = self.capture.get_effective_sampling_rate(key).unwrap_or(0.0);
+                let t: Vec<f64> = points.iter().map(|p| p.x - t0).collect();
+                let v: Vec<f64> = points.iter().map(|p| p.y).collect();
+                resample_linear(&t, &v, grid, rate)
+            })
+            .collect();
+        self.segments.push_back(segment);
+        while self.segments.len() > self.config.averages as usize {
+            self.segments.pop_front();
+        }
+
+        let (means, deviations): (Vec<PlotData>, Vec<PlotData>) = self
+            .grids
+            .iter()
+            .enumerate()
+            .map(|(i, grid)| {
+                let (mean, deviation) = mean_and_deviation(self.segments.iter().map(|s| &s[i]));
+                (
+                    PlotData {
+                        timestamps: grid.clone(),
+                        series_data: vec![mean],
+                    },
+                    PlotData {
+                        timestamps: grid.clone(),
+                        series_data: vec![deviation],
+                    },
+                )
+            })
+            .unzip();
+        self.frames = means;
+        self.frames.extend(deviations);
+        self.frame_end = t0 + post;
+    }
+
+    /// Reads the frame around `t0` for every plotted column.
+    fn capture_frame(&mut self, t0: f64) {
+        let start = t0 - self.config.pre_trigger_seconds;
+        let end = t0 + self.config.post_trigger_seconds;
+        let data = self
+            .capture
+            .get_data_across_sessions_for_keys(&self.keys, start, end);
+        self.frames = data
+            .into_iter()
+            .map(|points| PlotData {
+                timestamps: points.iter().map(|p| p.x - t0).collect(),
+                series_data: vec![points.iter().map(|p| p.y).collect()],
+            })
+            .collect();
+        if self.averaging() {
+            // Keeps the series layout of an averaged frame, with no deviation to show yet.
+            let deviations: Vec<PlotData> = self
+                .frames
+                .iter()
+                .map(|f| PlotData {
+                    timestamps: f.timestamps.clone(),
+                    series_data: vec![vec![f64::NAN; f.timestamps.len()]],
+                })
+                .collect();
+            self.frames.extend(deviations);
+        }
+        self.frame_end = end;
+    }
+
+    fn reset(&mut self) {
+        self.detector.reset();
+        self.armed = true;
+        self.pending = None;
+        self.frame_end = f64::NEG_INFINITY;
+        self.triggers = 0;
+        self.last_trigger_time = None;
+        self.segments.clear();
+        self.frames.clear();
+    }
+}
+
+/// Sample times from `-pre` to `post` seconds on the `rate` grid through the trigger.
+fn averaging_grid(rate: f64, pre: f64, post: f64) -> Vec<f64> {
+    if rate <= 0.0 {
+        return Vec::new();
+    }
+    let first = (-pre * rate).ceil() as i64;
+    let last = (post * rate).floor() as i64;
+    (first..=last).map(|k| k as f64 / rate).collect()
+}
+
+/// Mean and sample standard deviation across segments at each grid point, skipping NaNs.
+/// The deviation is NaN where fewer than two segments have a value.
+fn mean_and_deviation<'a>(
+    segments: impl Iterator<Item = &'a Vec<f64>> + Clone,
+) -> (Vec<f64>, Vec<f64>) {
+    let len = segments.clone().map(|s| s.len()).max().unwrap_or(0);
+    let mut mean = vec![0.0; len];
+    let mut count = vec![0u32; len];
+    for segment in segments.clone() {
+        for (j, &v) in segment.iter().enumerate() {
+            if v.is_finite() {
+                mean[j] += v;
+                count[j] += 1;
+            }
+        }
+    }
+    for (m, &n) in mean.iter_mut().zip(&count) {
+        *m = if n > 0 { *m / n as f64 } else { f64::NAN };
+    }
+    let mut deviation = vec![0.0; len];
+    for segment in segments {
+        for (j, &v) in segment.iter().enumerate() {
+            if v.is_finite() {
+                deviation[j] += (v - mean[j]).powi(2);
+            }
+        }
+    }
+    for (d, &n) in deviation.iter_mut().zip(&count) {
+        *d = if n > 1 {
+            (*d / (n - 1) as f64).sqrt()
+        } else {
+            f64::NAN
+        };
+    }
+    (mean, deviation)
+}
+
+impl Pipeline for TriggerPipeline {
+    fn id(&self) -> PipelineId {
+        self.id
+    }
+
+    fn get_output(&self) -> PlotData {
+        self.frames.first().cloned().unwrap_or_else(PlotData::empty)
+    }
+
+    fn get_outputs(&self) -> Vec<PlotData> {
+        self.frames.clone()
+    }
+
+    fn process_batch(&mut self, batch: Arc<BatchedData>) {
+        if batch.key != self.config.source {
+            return;
+        }
+        for p in batch.points.iter() {
+            self.latest = self.latest.max(p.x);
+            let Some(t) = self.detector.step(*p) else {
+                continue;
+            };
+            if self.armed && self.pending.is_none() && t >= self.frame_end {
+                self.pending = Some(t);
+            }
+        }
+        self.poll();
+    }
+
+    fn trigger_status(&self) -> Option<TriggerStatus> {
+        let state = if self.pending.is_some() {
+            TriggerState::Capturing
+        } else if self.armed {
+            TriggerState::Armed
+        } else {
+            TriggerState::Stopped
+        };
+        Some(TriggerStatus {
+            state,
+            triggers: self.triggers,
+            last_trigger_time: self.last_trigger_time,
+            averaged: self.segments.len() as u32,
+        })
+    }
+
+    fn process_command(&mut self, cmd: PipelineCommand, capture_state: &CaptureState) {
+        match cmd {
+            PipelineCommand::ResetSelf => {
+                println!("[Trigger {:?}] Received ResetSelf command", self.id);
+                self.reset();
+            }
+            PipelineCommand::Rearm => {
+                println!("[Trigger {:?}] Re-armed.", self.id);
+                self.armed = true;
+                self.pending = None;
+                self.triggers = 0;
+                self.segments.clear();
+            }
+            PipelineCommand::Hydrate => {
+                let Some(latest) = capture_state
+                    .get_latest_unified_timestamp(std::slice::from_ref(&self.config.source))
+                else {
+                    return;
+                };
+                // Starts from the data already captured; only `Auto` has something to show yet.
+                self.latest = latest;
+                if self.config.mode == TriggerMode::Auto {
+                    self.capture_frame(latest - self.config.post_trigger_seconds);
+                }
+            }
+            _ => {}
+        }
+    }
+}
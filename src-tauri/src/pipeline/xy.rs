@@ -0,0 +1,93 @@
+//! One column against another (Lissajous figures, hodograms), fed by a windowed
+//! `DetrendPipeline` without detrending on each.
+//!
+//! The two windows are paired by timestamp with `k_way_merge_plot_data`, which interpolates each
+//! column at the other's sample times, and only instants where both have a value are kept. The
+//! output is x(t) and y(t) as two series on the shared time axis, so the front-end keeps each
+//! point's age for persistence.
+use super::{Pipeline, PipelineCommand};
+use crate::shared::{PipelineId, PlotData};
+use crate::state::capture::CaptureState;
+use crate::util::k_way_merge_plot_data;
+use uuid::Uuid;
+
+/// Inputs of an `XyPipeline`, as passed to `Pipeline::process_paired_batch`.
+pub const X_INPUT: usize = 0;
+pub const Y_INPUT: usize = 1;
+
+pub struct XyPipeline {
+    id: PipelineId,
+    /// Latest window from each input.
+    latest: [Option<PlotData>; 2],
+    /// x(t), then y(t).
+    outputs: [PlotData; 2],
+}
+
+impl XyPipeline {
+    pub fn new() -> Self {
+        Self {
+            id: PipelineId(Uuid::new_v4()),
+            latest: [None, None],
+            outputs: [PlotData::empty(), PlotData::empty()],
+        }
+    }
+
+    fn pair(&mut self) {
+        let (Some(x), Some(y)) = (&self.latest[X_INPUT], &self.latest[Y_INPUT]) else {
+            return;
+        };
+        let merged = k_way_merge_plot_data(vec![x.clone(), y.clone()]);
+        let [mut x_out, mut y_out] = [
+            PlotData::with_series_capacity(1),
+            PlotData::with_series_capacity(1),
+        ];
+        if let [xs, ys] = merged.series_data.as_slice() {
+            for ((&t, &x), &y) in merged.timestamps.iter().zip(xs).zip(ys) {
+                // Outside the span both windows cover the merge leaves NaN.
+                if x.is_finite() && y.is_finite() {
+                    x_out.timestamps.push(t);
+                    x_out.series_data[0].push(x);
+                    y_out.timestamps.push(t);
+                    y_out.series_data[0].push(y);
+                }
+            }
+        }
+        self.outputs = [x_out, y_out];
+    }
+}
+
+impl Default for XyPipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Pipeline for XyPipeline {
+    fn id(&self) -> PipelineId {
+        self.id
+    }
+
+    fn get_output(&self) -> PlotData {
+        self.outputs[Y_INPUT].clone()
+    }
+
+    fn get_outputs(&self) -> Vec<PlotData> {
+        self.outputs.to_vec()
+    }
+
+    fn process_paired_batch(&mut self, input: usize, batch: (PlotData, f64)) {
+        let Some(slot) = self.latest.get_mut(input) else {
+            return;
+        };
+        *slot = Some(batch.0);
+        self.pair();
+    }
+
+    fn process_command(&mut self, cmd: PipelineCommand, _capture: &CaptureState) {
+        if let PipelineCommand::ResetSelf = cmd {
+            println!("[XY {:?}] Received ResetSelf command", self.id);
+            self.latest = [None, None];
+            self.outputs = [PlotData::empty(), PlotData::empty()];
+        }
+    }
+}
@@ -1,22 +1,59 @@
 // src/proxy/discovery.rs
 use crate::state::proxy_register::ProxyRegister;
-use crate::util::get_valid_twinleaf_serial_urls;
+use crate::util::{enum_lan_devices, get_valid_twinleaf_serial_urls};
 
-use std::{collections::HashSet, sync::Arc, thread, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
 
-/// Spawn a background thread that keeps `ProxyRegister` in sync with whatever Twinleaf-compatible serial devices are visible on the host.
+/// LAN probes are slower than enumerating serial ports, so they run every few passes.
+const LAN_PROBE_EVERY: u32 = 3;
+const LAN_PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+/// UDP replies can get lost; a LAN device is only pruned after missing probes for this long.
+const LAN_GRACE: Duration = Duration::from_secs(20);
+
+/// Spawn a background thread that keeps `ProxyRegister` in sync with whatever Twinleaf-compatible serial devices and LAN endpoints are visible from the host.
 pub fn spawn(registry: Arc<ProxyRegister>) {
     thread::Builder::new()
         .name("twinleaf-discovery".into())
-        .spawn(move || loop {
-            let urls: HashSet<String> = get_valid_twinleaf_serial_urls().into_iter().collect();
+        .spawn(move || {
+            let mut lan_last_seen: HashMap<String, Instant> = HashMap::new();
+            let mut pass: u32 = 0;
 
-            for url in &urls {
-                registry.ensure(url.clone());
-            }
-            registry.prune(|url| urls.contains(url));
+            loop {
+                let mut urls: HashSet<String> =
+                    get_valid_twinleaf_serial_urls().into_iter().collect();
+
+                for url in &urls {
+                    registry.ensure(url.clone());
+                }
 
-            thread::sleep(Duration::from_secs(2));
+                if pass.is_multiple_of(LAN_PROBE_EVERY) {
+                    let now = Instant::now();
+                    for device in enum_lan_devices(LAN_PROBE_TIMEOUT) {
+                        if !lan_last_seen.contains_key(&device.url) {
+                            println!(
+                                "[Discovery] Found LAN device '{}' at {}",
+                                device.name, device.url
+                            );
+                        }
+                        lan_last_seen.insert(device.url, now);
+                    }
+                    lan_last_seen.retain(|_, seen| now.duration_since(*seen) < LAN_GRACE);
+                }
+                for url in lan_last_seen.keys() {
+                    registry.ensure_discovered(url.clone());
+                    urls.insert(url.clone());
+                }
+
+                registry.prune(|url| urls.contains(url));
+
+                pass = pass.wrapping_add(1);
+                thread::sleep(Duration::from_secs(2));
+            }
         })
         .expect("Failed to spawn Discovery thread.");
 }
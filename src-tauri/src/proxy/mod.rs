@@ -1,2 +1,4 @@
 pub mod discovery;
 pub mod port_manager;
+pub mod tcp_proxy;
+pub mod tio_server;
@@ -1,12 +1,16 @@
 use crate::shared::{
-    ColumnMeta, DataColumnId, DeviceMeta, Point, PortState, RpcError, RpcMeta, UiDevice, UiStream,
+    ColumnMeta, DataColumnId, DeviceMeta, Point, PortState, RpcError, RpcMeta, TcpProxyStatus,
+    UiDevice, UiStream,
 };
+use crate::proxy::tcp_proxy::TcpProxy;
+use crate::recording::recorder::Recorder;
 use crate::state::capture::{CaptureCommand, CaptureState, SessionId};
 use crate::state::proxy_register::ProxyRegister;
 use crate::util::{self, parse_arg_type_and_size, parse_permissions_string};
 use crossbeam::channel::{Sender, TrySendError};
 use crossbeam::select;
 use serde_json::{json, Value};
+use std::net::SocketAddr;
 use std::panic::AssertUnwindSafe;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{
@@ -50,6 +54,10 @@ pub struct PortManager {
     pub app: tauri::AppHandle,
     pub capture_tx: Sender<CaptureCommand>,
     pub counters: DebugCounters,
+    /// Where the TCP proxy should listen, if the user turned it on.
+    tcp_proxy_bind: Mutex<Option<SocketAddr>>,
+    tcp_proxy: Mutex<Option<TcpProxy>>,
+    tcp_proxy_error: Mutex<Option<String>>,
 }
 
 impl PortManager {
@@ -75,6 +83,9 @@ impl PortManager {
                 points_inserted: AtomicUsize::new(0),
                 dropped_batches: AtomicUsize::new(0),
             },
+            tcp_proxy_bind: Mutex::new(None),
+            tcp_proxy: Mutex::new(None),
+            tcp_proxy_error: Mutex::new(None),
         });
 
         Self::spawn_thread(pm.clone(), command_rx);
@@ -93,6 +104,88 @@ impl PortManager {
         let _ = self.command_tx.send(PortCommand::RescanDevices);
     }
 
+    /// Turns the built-in TCP proxy on (`Some`) or off. It follows the port's `proxy::Interface`,
+    /// pausing while the port has none and restarting when a new one is created.
+    pub fn set_tcp_proxy(&self, bind: Option<SocketAddr>) -> Result<TcpProxyStatus, String> {
+        *self.tcp_proxy_bind.lock().unwrap() = bind;
+        *self.tcp_proxy_error.lock().unwrap() = None;
+        self.sync_tcp_proxy();
+        let status = self.tcp_proxy_status();
+        match &status.error {
+            Some(e) => Err(e.clone()),
+            None => Ok(status),
+        }
+    }
+
+    pub fn tcp_proxy_status(&self) -> TcpProxyStatus {
+        let proxy = self.tcp_proxy.lock().unwrap();
+        TcpProxyStatus {
+            port_url: self.url.clone(),
+            address: proxy.as_ref().map(|p| p.local_addr().to_string()),
+            clients: proxy.as_ref().map(|p| p.client_count()).unwrap_or(0),
+            error: self.tcp_proxy_error.lock().unwrap().clone(),
+        }
+    }
+
+    /// Starts, restarts or stops the TCP proxy to match the requested bind address and the
+    /// current interface. Cheap enough to run on every tick.
+    fn sync_tcp_proxy(&self) {
+        let bind = *self.tcp_proxy_bind.lock().unwrap();
+        let proxy_if = self.proxy.lock().unwrap().clone();
+        let mut running = self.tcp_proxy.lock().unwrap();
+
+        let keep = match (&*running, bind, &proxy_if) {
+            (Some(p), Some(bind), Some(proxy_if)) => {
+                p.serves(proxy_if) && (bind.port() == 0 || p.local_addr() == bind)
+            }
+            _ => false,
+        };
+        let mut changed = false;
+        if !keep && running.is_some() {
+            *running = None;
+            changed = true;
+        }
+        if let (None, Some(bind), Some(proxy_if)) = (&*running, bind, proxy_if) {
+            match TcpProxy::start(self.url.clone(), proxy_if, bind) {
+                Ok(p) => {
+                    println!("[{}] TCP proxy listening on {}", self.url, p.local_addr());
+                    *running = Some(p);
+                }
+                Err(e) => {
+                    let err_msg = format!("Failed to start TCP proxy on {}: {}", bind, e);
+                    eprintln!("[{}] {}", self.url, err_msg);
+                    *self.tcp_proxy_bind.lock().unwrap() = None;
+                    *self.tcp_proxy_error.lock().unwrap() = Some(err_msg);
+                }
+            }
+            changed = true;
+        }
+        drop(running);
+
+        if changed {
+            self.update_tcp_proxy_menu();
+            let _ = self.app.emit("tcp-proxy-changed", self.tcp_proxy_status());
+        }
+    }
+
+    fn update_tcp_proxy_menu(&self) {
+        let is_running = self.tcp_proxy.lock().unwrap().is_some();
+        if let Some(window) = self.app.get_webview_window("main") {
+            if let Some(menu) = window.menu() {
+                if let Some(device_menu) = util::find_submenu_by_text(&menu, "Device") {
+                    if let Some(MenuItemKind::MenuItem(item)) = device_menu.get("toggle_tcp_proxy") {
+                        let text = if is_running {
+                            "Stop TCP Proxy"
+                        } else {
+                            "Start TCP Proxy"
+                        };
+                        let _ = item.set_text(text);
+                    }
+                }
+            }
+        }
+    }
+
     fn spawn_thread(self_: Arc<Self>, command_rx: crossbeam::channel::Receiver<PortCommand>) {
         thread::Builder::new()
             .name(format!("port-{}", self_.url))
@@ -244,6 +337,7 @@ impl PortManager {
                             if current_state == PortState::Streaming {
                                 self_.poll_device_data();
                             }
+                            self_.sync_tcp_proxy();
                             if last_debug_print.elapsed() > Duration::from_secs(30) {
                                 let polls  = self_.counters.polls.swap(0, Ordering::Relaxed);
                                 let points = self_.counters.points_inserted.swap(0, Ordering::Relaxed);
@@ -265,6 +359,8 @@ impl PortManager {
                 }
 
                 println!("[{}] Port manager thread cleaning up and shutting down.", self_.url);
+                *self_.tcp_proxy_bind.lock().unwrap() = None;
+                self_.sync_tcp_proxy();
                 if let Some(proxy) = self_.proxy.lock().unwrap().take() {
                     drop(proxy);
                 }
@@ -327,6 +423,7 @@ impl PortManager {
             let data_device = Device::open(proxy_if, route.clone());
 
             self.update_capture_state_with_stream_metadata(&route, &ui_dev.streams);
+            self.record_device_metadata(&ui_dev);
 
             discovered_ui_devices_for_event.push(ui_dev.clone());
             devices.insert(route, Arc::new(Mutex::new((data_device, ui_dev))));
@@ -518,6 +615,9 @@ impl PortManager {
                     if let Some(MenuItemKind::MenuItem(item)) = device_menu.get("toggle_logging") {
                         item.set_enabled(is_connected).unwrap();
                     }
+                    if let Some(MenuItemKind::MenuItem(item)) = device_menu.get("toggle_tcp_proxy") {
+                        item.set_enabled(is_connected).unwrap();
+                    }
                     if let Some(MenuItemKind::MenuItem(item)) = device_menu.get("rpc_settings") {
                         item.set_enabled(is_connected).unwrap();
                     }
@@ -615,6 +715,7 @@ impl PortManager {
                 sample_numbers: entry.sample_numbers,
                 session_id: sid,
                 instant: poll_instant,
+                host_time: None,
             }) {
                 Ok(()) => {
                     self.counters
@@ -673,6 +774,7 @@ impl PortManager {
 
                     ui_device.meta = new_meta;
                     ui_device.streams = new_streams;
+                    self_clone.record_device_metadata(ui_device);
 
                     if let Err(e) = self_clone
                         .app
@@ -784,6 +886,12 @@ impl PortManager {
         Ok(rpc_result)
     }
 
+    fn record_device_metadata(&self, ui_device: &UiDevice) {
+        if let Some(recorder) = self.app.try_state::<Arc<Recorder>>() {
+            recorder.record_device(ui_device);
+        }
+    }
+
     fn update_capture_state_with_stream_metadata(&self, route: &DeviceRoute, streams: &[UiStream]) {
         for stream in streams {
             let stream_key = DataColumnId {
@@ -793,6 +901,19 @@ impl PortManager {
                 column_index: 0,
             };
 
+            if let Some(segment) = &stream.segment {
+                let command = CaptureCommand::UpdateTimeBase {
+                    key: stream_key.clone(),
+                    epoch: segment.time_ref_epoch.clone(),
+                };
+                if let Err(e) = self.capture_tx.send(command) {
+                    eprintln!(
+                        "[{}] Failed to send time base for '{}' to capture thread: {}",
+                        self.url, route, e
+                    );
+                }
+            }
+
             let command = CaptureCommand::UpdateSampleRate {
                 key: stream_key,
                 rate: stream.effective_sampling_rate,
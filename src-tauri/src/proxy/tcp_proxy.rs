@@ -0,0 +1,216 @@
+//! Built-in replacement for `tio-proxy`: shares a connected port with TCP clients.
+//!
+//! Every client gets its own `tree_full()` port on the `PortManager`'s `proxy::Interface`, so it
+//! sees the raw packet stream of the whole device tree and its RPCs are routed back through the
+//! same interface Trendline is streaming from.
+use crossbeam::channel::RecvTimeoutError;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use twinleaf::tio::proto::{self, Packet};
+use twinleaf::tio::proxy;
+
+/// Same port `tio-proxy` listens on.
+pub const DEFAULT_BIND: &str = "127.0.0.1:7855";
+
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Reads TIO packets off `stream` until it closes, `stop` is set, or `on_packet` returns `false`.
+pub(crate) fn read_packets(
+    stream: &mut TcpStream,
+    stop: &AtomicBool,
+    mut on_packet: impl FnMut(Packet) -> bool,
+) -> io::Result<()> {
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+    let mut buf: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 4096];
+
+    while !stop.load(Ordering::Relaxed) {
+        match stream.read(&mut chunk) {
+            Ok(0) => return Ok(()),
+            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => continue,
+            Err(e) => return Err(e),
+        }
+
+        loop {
+            match Packet::deserialize(&buf) {
+                Ok((packet, used)) => {
+                    buf.drain(..used);
+                    if !on_packet(packet) {
+                        return Ok(());
+                    }
+                }
+                Err(proto::Error::NeedMore) => break,
+                Err(e) => {
+                    return Err(io::Error::new(
+                        ErrorKind::InvalidData,
+                        format!("bad packet ({:?})", e),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+pub struct TcpProxy {
+    interface: Arc<proxy::Interface>,
+    local_addr: SocketAddr,
+    stop: Arc<AtomicBool>,
+    clients: Arc<AtomicUsize>,
+    acceptor: Option<JoinHandle<()>>,
+}
+
+impl TcpProxy {
+    /// `label` is only used for logging, usually the port URL.
+    pub fn start(
+        label: String,
+        interface: Arc<proxy::Interface>,
+        bind: SocketAddr,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind(bind)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let clients = Arc::new(AtomicUsize::new(0));
+
+        let acceptor = {
+            let interface = interface.clone();
+            let stop = stop.clone();
+            let clients = clients.clone();
+            thread::Builder::new()
+                .name(format!("tcp-proxy-{}", local_addr))
+                .spawn(move || accept_loop(label, listener, interface, stop, clients))?
+        };
+
+        Ok(Self {
+            interface,
+            local_addr,
+            stop,
+            clients,
+            acceptor: Some(acceptor),
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+
+    /// Whether this proxy forwards `interface`; a new interface after a reconnect needs a new
+    /// proxy.
+    pub fn serves(&self, interface: &Arc<proxy::Interface>) -> bool {
+        Arc::ptr_eq(&self.interface, interface)
+    }
+}
+
+impl Drop for TcpProxy {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.acceptor.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn accept_loop(
+    label: String,
+    listener: TcpListener,
+    interface: Arc<proxy::Interface>,
+    stop: Arc<AtomicBool>,
+    clients: Arc<AtomicUsize>,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, addr)) => {
+                println!("[{}] TCP proxy client connected: {}", label, addr);
+                let result = interface
+                    .tree_full()
+                    .map_err(|e| io::Error::other(format!("{:?}", e)))
+                    .and_then(|port| {
+                        spawn_client(stream, Arc::new(port), stop.clone(), clients.clone())
+                    });
+                if let Err(e) = result {
+                    eprintln!(
+                        "[{}] Failed to attach TCP proxy client {}: {}",
+                        label, addr, e
+                    );
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(50)),
+            Err(e) => {
+                eprintln!("[{}] TCP proxy accept failed: {}", label, e);
+                thread::sleep(Duration::from_millis(500));
+            }
+        }
+    }
+    println!(
+        "[{}] TCP proxy on {:?} stopped.",
+        label,
+        listener.local_addr()
+    );
+}
+
+fn spawn_client(
+    stream: TcpStream,
+    port: Arc<proxy::Port>,
+    stop: Arc<AtomicBool>,
+    clients: Arc<AtomicUsize>,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    let mut writer = stream.try_clone()?;
+    let mut reader = stream;
+    let closed = Arc::new(AtomicBool::new(false));
+
+    // Device -> client
+    let writer_port = port.clone();
+    let writer_stop = stop.clone();
+    let writer_closed = closed.clone();
+    thread::Builder::new()
+        .name("tcp-proxy-writer".into())
+        .spawn(move || {
+            while !writer_stop.load(Ordering::Relaxed) && !writer_closed.load(Ordering::Relaxed) {
+                match writer_port.receiver().recv_timeout(POLL_INTERVAL) {
+                    Ok(packet) => {
+                        let Ok(bytes) = packet.serialize() else {
+                            continue;
+                        };
+                        if writer.write_all(&bytes).is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            // Unblocks the reader if the device side went away first.
+            let _ = writer.shutdown(Shutdown::Both);
+        })?;
+
+    // Client -> device
+    clients.fetch_add(1, Ordering::Relaxed);
+    thread::Builder::new()
+        .name("tcp-proxy-reader".into())
+        .spawn(move || {
+            let peer = reader
+                .peer_addr()
+                .map(|a| a.to_string())
+                .unwrap_or_default();
+            if let Err(e) = read_packets(&mut reader, &stop, |packet| port.send(packet).is_ok()) {
+                eprintln!("[TcpProxy] Dropping client {}: {}", peer, e);
+            }
+            let _ = reader.shutdown(Shutdown::Both);
+            closed.store(true, Ordering::Relaxed);
+            clients.fetch_sub(1, Ordering::Relaxed);
+            println!("[TcpProxy] Client disconnected: {}", peer);
+        })?;
+    Ok(())
+}
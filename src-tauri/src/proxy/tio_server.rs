@@ -0,0 +1,779 @@
+//! Headless TIO device backed by a `.tlrec` log.
+//!
+//! Listens on a TCP endpoint the same way `tio-proxy` does, so `tio-proxy` clients and other
+//! Trendline instances (`tcp://host:port`) can connect to a recording as if it were a live sensor.
+//! Samples are re-packed into `StreamData` packets at the recorded pace; RPCs are answered from the
+//! recorded metadata and are read-only.
+use super::tcp_proxy::{read_packets, DEFAULT_BIND};
+use crate::recording::reader::{LogBatch, LogEvent, LogReader};
+use crate::shared::{ColumnMeta, RpcMeta, SegmentMeta, UiDevice, UiStream};
+use crate::util;
+use crossbeam::channel::{bounded, select, tick, Receiver, Sender, TrySendError};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, ErrorKind, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use twinleaf::tio::proto::meta::{
+    ColumnMetadata, DeviceMetadata, MetadataContent, MetadataType, SegmentMetadata, StreamMetadata,
+};
+use twinleaf::tio::proto::{
+    self, rpc::RpcErrorCode, rpc::RpcErrorPayload, DataType, DeviceRoute, HeartbeatPayload, Packet,
+    Payload, RpcMethod, RpcReplyPayload, RpcRequestPayload, StreamDataPayload,
+};
+
+/// Rows waiting for their remaining columns before a stream gives up and sends them incomplete.
+const MAX_PENDING_ROWS: usize = 4096;
+/// Outgoing packets buffered per client before a slow client starts losing data.
+const CLIENT_QUEUE: usize = 8192;
+
+#[derive(Clone, Debug)]
+pub struct ServeOptions {
+    pub bind: SocketAddr,
+    /// Playback speed relative to the recorded pace.
+    pub speed: f64,
+    /// Start over from the top of the log when it ends.
+    pub looped: bool,
+}
+
+impl Default for ServeOptions {
+    fn default() -> Self {
+        Self {
+            bind: DEFAULT_BIND.parse().unwrap(),
+            speed: 1.0,
+            looped: false,
+        }
+    }
+}
+
+struct ServedDevice {
+    ui: UiDevice,
+    session_id: u32,
+}
+
+#[derive(Default)]
+struct Shared {
+    devices: RwLock<HashMap<DeviceRoute, ServedDevice>>,
+    clients: Mutex<Vec<Sender<Arc<Vec<u8>>>>>,
+    stop: AtomicBool,
+}
+
+impl Shared {
+    fn broadcast(&self, bytes: Vec<u8>) {
+        let bytes = Arc::new(bytes);
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|tx| match tx.try_send(bytes.clone()) {
+                Ok(()) | Err(TrySendError::Full(_)) => true,
+                Err(TrySendError::Disconnected(_)) => false,
+            });
+    }
+
+    fn update_device(&self, device: UiDevice) {
+        let Ok(route) = DeviceRoute::from_str(&device.route) else {
+            return;
+        };
+        let session_id = device.meta.session_id;
+        self.devices.write().unwrap().insert(
+            route,
+            ServedDevice {
+                ui: device,
+                session_id,
+            },
+        );
+    }
+}
+
+pub struct TioServer {
+    pub path: PathBuf,
+    local_addr: SocketAddr,
+    shared: Arc<Shared>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+impl TioServer {
+    pub fn start(path: PathBuf, options: ServeOptions) -> io::Result<Self> {
+        if !options.speed.is_finite() || options.speed <= 0.0 {
+            return Err(io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid playback speed: {}", options.speed),
+            ));
+        }
+        // Fail early on a bad log instead of inside the feeder thread.
+        LogReader::open(&path)?;
+
+        let listener = TcpListener::bind(options.bind)?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        let shared = Arc::new(Shared::default());
+
+        let feeder_shared = shared.clone();
+        let feeder_path = path.clone();
+        let feeder = thread::Builder::new()
+            .name("tio-server-feeder".into())
+            .spawn(move || {
+                if let Err(e) = Feeder::new(feeder_shared.clone(), options).run(&feeder_path) {
+                    eprintln!("[TioServer] Playback stopped: {}", e);
+                }
+            })?;
+
+        let accept_shared = shared.clone();
+        let acceptor = thread::Builder::new()
+            .name("tio-server-accept".into())
+            .spawn(move || accept_loop(listener, accept_shared))?;
+
+        println!(
+            "[TioServer] Serving {} on tcp://{}",
+            path.to_string_lossy(),
+            local_addr
+        );
+        Ok(Self {
+            path,
+            local_addr,
+            shared,
+            threads: vec![feeder, acceptor],
+        })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    pub fn stop(self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        self.wait();
+    }
+
+    /// Blocks until the server stops, either through `stop` or because a non-looping log ended.
+    pub fn wait(self) {
+        for handle in self.threads {
+            let _ = handle.join();
+        }
+        println!(
+            "[TioServer] Stopped serving {}",
+            self.path.to_string_lossy()
+        );
+    }
+}
+
+fn accept_loop(listener: TcpListener, shared: Arc<Shared>) {
+    while !shared.stop.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, addr)) => {
+                println!("[TioServer] Client connected: {}", addr);
+                if let Err(e) = spawn_client(stream, shared.clone()) {
+                    eprintln!("[TioServer] Failed to set up client {}: {}", addr, e);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => {
+                eprintln!("[TioServer] Accept failed: {}", e);
+                thread::sleep(Duration::from_millis(500));
+            }
+        }
+    }
+}
+
+fn spawn_client(stream: TcpStream, shared: Arc<Shared>) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    let writer = stream.try_clone()?;
+
+    let (tx, rx) = bounded::<Arc<Vec<u8>>>(CLIENT_QUEUE);
+    shared.clients.lock().unwrap().push(tx.clone());
+
+    let writer_shared = shared.clone();
+    thread::Builder::new()
+        .name("tio-server-writer".into())
+        .spawn(move || write_loop(writer, rx, writer_shared))?;
+    thread::Builder::new()
+        .name("tio-server-reader".into())
+        .spawn(move || read_loop(stream, tx, shared))?;
+    Ok(())
+}
+
+fn write_loop(mut stream: TcpStream, rx: Receiver<Arc<Vec<u8>>>, shared: Arc<Shared>) {
+    let heartbeat = tick(Duration::from_secs(1));
+    loop {
+        if shared.stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let result = select! {
+            recv(rx) -> bytes => match bytes {
+                Ok(bytes) => stream.write_all(&bytes),
+                Err(_) => break,
+            },
+            recv(heartbeat) -> _ => heartbeats(&shared)
+                .iter()
+                .try_for_each(|bytes| stream.write_all(bytes)),
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+    let _ = stream.shutdown(std::net::Shutdown::Both);
+}
+
+fn read_loop(mut stream: TcpStream, tx: Sender<Arc<Vec<u8>>>, shared: Arc<Shared>) {
+    let peer = stream
+        .peer_addr()
+        .map(|a| a.to_string())
+        .unwrap_or_default();
+    let result = read_packets(&mut stream, &shared.stop, |packet| {
+        match handle_packet(&shared, packet) {
+            Some(reply) => tx.send(Arc::new(reply)).is_ok(),
+            None => true,
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("[TioServer] Dropping client {}: {}", peer, e);
+    }
+    println!("[TioServer] Client disconnected: {}", peer);
+}
+
+fn heartbeats(shared: &Shared) -> Vec<Vec<u8>> {
+    shared
+        .devices
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|(route, device)| {
+            Packet {
+                payload: Payload::Heartbeat(HeartbeatPayload::Session(device.session_id)),
+                routing: route.clone(),
+                ttl: 0,
+            }
+            .serialize()
+            .ok()
+        })
+        .collect()
+}
+
+fn handle_packet(shared: &Shared, packet: Packet) -> Option<Vec<u8>> {
+    let Payload::RpcRequest(request) = packet.payload else {
+        return None;
+    };
+    let payload = {
+        let devices = shared.devices.read().unwrap();
+        match devices.get(&packet.routing) {
+            Some(device) => answer_rpc(&device.ui, &request),
+            None => rpc_error(request.id, RpcErrorCode::NotFound),
+        }
+    };
+    Packet {
+        payload,
+        routing: packet.routing,
+        ttl: 0,
+    }
+    .serialize()
+    .ok()
+}
+
+fn rpc_error(id: u16, error: RpcErrorCode) -> Payload {
+    Payload::RpcError(RpcErrorPayload {
+        id,
+        error,
+        extra: Vec::new(),
+    })
+}
+
+fn rpc_reply(id: u16, reply: Vec<u8>) -> Payload {
+    Payload::RpcReply(RpcReplyPayload { id, reply })
+}
+
+/// Answers the RPCs a client issues while discovering a device, plus reads of any recorded RPC
+/// value. The recording can't be changed, so writes are refused.
+fn answer_rpc(device: &UiDevice, request: &RpcRequestPayload) -> Payload {
+    let name = match &request.method {
+        RpcMethod::Name(name) => name.clone(),
+        RpcMethod::Id(id) => match device.rpcs.get(*id as usize) {
+            Some(rpc) => rpc.name.clone(),
+            None => return rpc_error(request.id, RpcErrorCode::NotFound),
+        },
+    };
+
+    match name.as_str() {
+        "rpc.listinfo" => match request.arg.as_slice() {
+            [] => rpc_reply(
+                request.id,
+                (device.rpcs.len() as u16).to_le_bytes().to_vec(),
+            ),
+            [lo, hi] => match device.rpcs.get(u16::from_le_bytes([*lo, *hi]) as usize) {
+                Some(rpc) => {
+                    let mut reply = rpc_meta_bits(rpc).to_le_bytes().to_vec();
+                    reply.extend_from_slice(rpc.name.as_bytes());
+                    rpc_reply(request.id, reply)
+                }
+                None => rpc_error(request.id, RpcErrorCode::OutOfRange),
+            },
+            _ => rpc_error(request.id, RpcErrorCode::WrongSizeArgs),
+        },
+        "dev.metadata" => match metadata_reply(device, &request.arg) {
+            Some(reply) => rpc_reply(request.id, reply),
+            None => rpc_error(request.id, RpcErrorCode::OutOfRange),
+        },
+        _ => {
+            let Some(rpc) = device.rpcs.iter().find(|r| r.name == name) else {
+                return rpc_error(request.id, RpcErrorCode::NotFound);
+            };
+            if !request.arg.is_empty() {
+                return rpc_error(request.id, RpcErrorCode::ReadOnly);
+            }
+            // Only values the device reported while recording are known.
+            let Some(value) = rpc.value.clone() else {
+                return rpc_error(request.id, RpcErrorCode::Undefined);
+            };
+            match util::json_to_bytes(Some(value), &rpc.arg_type) {
+                Ok(bytes) => rpc_reply(request.id, bytes),
+                Err(_) => rpc_error(request.id, RpcErrorCode::Internal),
+            }
+        }
+    }
+}
+
+/// Inverse of `util::parse_arg_type_and_size` and the permission bits. Always read-only.
+fn rpc_meta_bits(rpc: &RpcMeta) -> u16 {
+    if rpc.unknown {
+        return 0;
+    }
+    let base_type = rpc.arg_type.split('<').next().unwrap_or("");
+    let type_code: u16 = match base_type {
+        "i8" | "i16" | "i32" | "i64" => 1,
+        "f32" | "f64" => 2,
+        "string" => 3,
+        _ => 0,
+    };
+    let mut bits = type_code | (((rpc.size as u16) & 0xF) << 4);
+    if rpc.readable {
+        bits |= 0x0100;
+    }
+    if rpc.persistent {
+        bits |= 0x0400;
+    }
+    bits
+}
+
+/// `dev.metadata`: the request is up to 16 `(type, stream id, index)` triples, answered in order,
+/// each as its type, its length and the library's vararg encoding. An empty request, which clients
+/// send to bootstrap, gets the device followed by as many streams, segments and columns as fit.
+fn metadata_reply(device: &UiDevice, arg: &[u8]) -> Option<Vec<u8>> {
+    // Packet header, routing and the RPC id.
+    let max_reply = proto::TIO_PACKET_MAX_TOTAL_SIZE - 4 - 8 - 2;
+    let mut out = Vec::new();
+    if arg.is_empty() {
+        for content in all_metadata(device) {
+            let entry = metadata_entry(content)?;
+            if out.len() + entry.len() > max_reply {
+                break;
+            }
+            out.extend(entry);
+        }
+        return Some(out);
+    }
+
+    if !arg.len().is_multiple_of(3) {
+        return None;
+    }
+    for request in arg.chunks(3) {
+        let content = requested_metadata(device, request[0], request[1], request[2])?;
+        out.extend(metadata_entry(content)?);
+    }
+    (out.len() <= max_reply).then_some(out)
+}
+
+fn requested_metadata(
+    device: &UiDevice,
+    kind: u8,
+    stream_id: u8,
+    index: u8,
+) -> Option<MetadataContent> {
+    let stream = || {
+        device
+            .streams
+            .iter()
+            .find(|s| s.meta.stream_id == stream_id)
+    };
+    Some(match MetadataType::from(kind) {
+        MetadataType::Device => MetadataContent::Device(device_metadata(device)),
+        MetadataType::Stream => MetadataContent::Stream(stream_metadata(stream()?)),
+        MetadataType::Segment => {
+            // Only the segment that was current when the device was recorded is known.
+            let segment = stream()?.segment.as_ref()?;
+            if segment.segment_id != index {
+                return None;
+            }
+            MetadataContent::Segment(segment_metadata(segment))
+        }
+        MetadataType::Column => {
+            let column = stream()?
+                .columns
+                .iter()
+                .find(|c| c.index == index as usize)?;
+            MetadataContent::Column(column_metadata(column)?)
+        }
+        MetadataType::Unknown(_) => return None,
+    })
+}
+
+/// The device, then each stream with its segment and columns.
+fn all_metadata(device: &UiDevice) -> Vec<MetadataContent> {
+    let mut all = vec![MetadataContent::Device(device_metadata(device))];
+    for stream in &device.streams {
+        all.push(MetadataContent::Stream(stream_metadata(stream)));
+        if let Some(segment) = &stream.segment {
+            all.push(MetadataContent::Segment(segment_metadata(segment)));
+        }
+        all.extend(
+            stream
+                .columns
+                .iter()
+                .filter_map(column_metadata)
+                .map(MetadataContent::Column),
+        );
+    }
+    all
+}
+
+fn metadata_entry(content: MetadataContent) -> Option<Vec<u8>> {
+    let ((fixed, varlen), kind) = match content {
+        MetadataContent::Device(m) => (m.serialize(&[], &[]).ok()?, MetadataType::Device),
+        MetadataContent::Stream(m) => (m.serialize(&[], &[]).ok()?, MetadataType::Stream),
+        MetadataContent::Segment(m) => (m.serialize(&[], &[]).ok()?, MetadataType::Segment),
+        MetadataContent::Column(m) => (m.serialize(&[], &[]).ok()?, MetadataType::Column),
+        MetadataContent::Unknown(_) => return None,
+    };
+    let mut entry = vec![kind.into(), u8::try_from(fixed.len() + varlen.len()).ok()?];
+    entry.extend(fixed);
+    entry.extend(varlen);
+    Some(entry)
+}
+
+fn device_metadata(device: &UiDevice) -> DeviceMetadata {
+    DeviceMetadata {
+        serial_number: device.meta.serial_number.clone(),
+        firmware_hash: device.meta.firmware_hash.clone(),
+        // Clients ask for streams 1 to `n_streams`.
+        n_streams: device
+            .streams
+            .iter()
+            .map(|s| s.meta.stream_id as usize)
+            .max()
+            .unwrap_or(0),
+        session_id: device.meta.session_id,
+        name: device.meta.name.clone(),
+    }
+}
+
+fn stream_metadata(stream: &UiStream) -> StreamMetadata {
+    let s = &stream.meta;
+    StreamMetadata {
+        stream_id: s.stream_id,
+        name: s.name.clone(),
+        n_columns: s.n_columns,
+        n_segments: s.n_segments,
+        sample_size: s.sample_size,
+        buf_samples: s.buf_samples,
+    }
+}
+
+fn segment_metadata(s: &SegmentMeta) -> SegmentMetadata {
+    SegmentMetadata {
+        stream_id: s.stream_id,
+        segment_id: s.segment_id,
+        flags: s.flags,
+        time_ref_epoch: (&s.time_ref_epoch).into(),
+        time_ref_serial: s.time_ref_serial.clone(),
+        time_ref_session_id: s.time_ref_session_id,
+        start_time: s.start_time,
+        sampling_rate: s.sampling_rate,
+        decimation: s.decimation,
+        filter_cutoff: s.filter_cutoff,
+        filter_type: (&s.filter_type).into(),
+    }
+}
+
+fn column_metadata(c: &ColumnMeta) -> Option<ColumnMetadata> {
+    Some(ColumnMetadata {
+        stream_id: c.stream_id,
+        index: c.index,
+        data_type: DataType::from(data_type_code(&c.data_type)?),
+        name: c.name.clone(),
+        units: c.units.clone(),
+        description: c.description.clone(),
+    })
+}
+
+/// `ColumnMeta::data_type` holds the `Debug` name of the library's `DataType`.
+fn data_type_code(data_type: &str) -> Option<u8> {
+    Some(match data_type {
+        "UInt8" => 0x10,
+        "Int8" => 0x11,
+        "UInt16" => 0x20,
+        "Int16" => 0x21,
+        "UInt32" => 0x40,
+        "Int32" => 0x41,
+        "Float32" => 0x42,
+        "UInt64" => 0x80,
+        "Int64" => 0x81,
+        "Float64" => 0x82,
+        _ => return None,
+    })
+}
+
+fn encode_value(out: &mut Vec<u8>, data_type: &str, value: f64) {
+    match data_type {
+        "UInt8" => out.push(value as u8),
+        "Int8" => out.push(value as i8 as u8),
+        "UInt16" => out.extend_from_slice(&(value as u16).to_le_bytes()),
+        "Int16" => out.extend_from_slice(&(value as i16).to_le_bytes()),
+        "UInt32" => out.extend_from_slice(&(value as u32).to_le_bytes()),
+        "Int32" => out.extend_from_slice(&(value as i32).to_le_bytes()),
+        "UInt64" => out.extend_from_slice(&(value as u64).to_le_bytes()),
+        "Int64" => out.extend_from_slice(&(value as i64).to_le_bytes()),
+        "Float64" => out.extend_from_slice(&value.to_le_bytes()),
+        _ => out.extend_from_slice(&(value as f32).to_le_bytes()),
+    }
+}
+
+/// Re-joins per-column batches into whole samples for one stream.
+struct RowAssembler {
+    session_id: u32,
+    /// Column indices in wire order, with their data types.
+    columns: Vec<(usize, String)>,
+    segment_id: u8,
+    rows: BTreeMap<u32, Vec<Option<f64>>>,
+    /// Latest sample number seen per column position.
+    latest: Vec<Option<u32>>,
+}
+
+impl RowAssembler {
+    fn new(stream: &UiStream, session_id: u32) -> Self {
+        let mut columns: Vec<(usize, String)> = stream
+            .columns
+            .iter()
+            .map(|c| (c.index, c.data_type.clone()))
+            .collect();
+        columns.sort_by_key(|(index, _)| *index);
+        let n = columns.len();
+        Self {
+            session_id,
+            columns,
+            segment_id: stream.segment.as_ref().map(|s| s.segment_id).unwrap_or(0),
+            rows: BTreeMap::new(),
+            latest: vec![None; n],
+        }
+    }
+
+    fn push(&mut self, batch: &LogBatch) {
+        let Some(pos) = self
+            .columns
+            .iter()
+            .position(|(index, _)| *index == batch.key.column_index)
+        else {
+            return;
+        };
+        let n = self.columns.len();
+        for (sn, p) in batch.sample_numbers.iter().zip(batch.points.iter()) {
+            self.rows.entry(*sn).or_insert_with(|| vec![None; n])[pos] = Some(p.y);
+            self.latest[pos] = Some(*sn);
+        }
+    }
+
+    /// Removes every row that is complete, or that can no longer complete because all columns
+    /// seen so far have moved past it.
+    fn take_ready(&mut self, flush_all: bool) -> Vec<(u32, Vec<Option<f64>>)> {
+        let horizon = self.latest.iter().flatten().min().copied();
+        let pending = self.rows.len();
+        let mut ready = Vec::new();
+        while let Some(entry) = self.rows.first_entry() {
+            let complete = entry.get().iter().all(Option::is_some);
+            let stale = horizon.is_some_and(|h| *entry.key() < h)
+                || ready.len() + MAX_PENDING_ROWS < pending;
+            if !(flush_all || complete || stale) {
+                break;
+            }
+            ready.push(entry.remove_entry());
+        }
+        ready
+    }
+
+    /// Packs consecutive rows into as few `StreamData` packets as fit.
+    fn encode(
+        &self,
+        route: &DeviceRoute,
+        stream_id: u8,
+        rows: &[(u32, Vec<Option<f64>>)],
+    ) -> Vec<Vec<u8>> {
+        let row_size: usize = self.columns.iter().map(|(_, t)| type_size(t)).sum();
+        let max_rows = ((proto::TIO_PACKET_MAX_TOTAL_SIZE - 16) / row_size.max(1)).max(1);
+
+        let mut packets = Vec::new();
+        let mut i = 0;
+        while i < rows.len() {
+            let first = rows[i].0;
+            let mut data = Vec::with_capacity(max_rows * row_size);
+            let mut count = 0;
+            while i < rows.len()
+                && count < max_rows
+                && rows[i].0 == first.wrapping_add(count as u32)
+            {
+                for ((_, data_type), value) in self.columns.iter().zip(&rows[i].1) {
+                    encode_value(&mut data, data_type, value.unwrap_or(f64::NAN));
+                }
+                count += 1;
+                i += 1;
+            }
+            let packet = Packet {
+                payload: Payload::StreamData(StreamDataPayload {
+                    stream_id,
+                    first_sample_n: first,
+                    segment_id: self.segment_id,
+                    data,
+                }),
+                routing: route.clone(),
+                ttl: 0,
+            };
+            if let Ok(bytes) = packet.serialize() {
+                packets.push(bytes);
+            }
+        }
+        packets
+    }
+}
+
+fn type_size(data_type: &str) -> usize {
+    data_type_code(data_type)
+        .map(|code| (code >> 4) as usize)
+        .unwrap_or(4)
+}
+
+/// Reads the log at its recorded pace and broadcasts the samples to every client.
+struct Feeder {
+    shared: Arc<Shared>,
+    options: ServeOptions,
+    streams: HashMap<(DeviceRoute, u8), RowAssembler>,
+}
+
+impl Feeder {
+    fn new(shared: Arc<Shared>, options: ServeOptions) -> Self {
+        Self {
+            shared,
+            options,
+            streams: HashMap::new(),
+        }
+    }
+
+    fn run(mut self, path: &Path) -> io::Result<()> {
+        let mut reader = LogReader::open(path)?;
+        loop {
+            // Recorded host time of the first batch, and when it was sent.
+            let mut origin: Option<(f64, Instant)> = None;
+
+            while let Some(event) = reader.next_event()? {
+                if self.shared.stop.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                match event {
+                    LogEvent::Device(device) => self.shared.update_device(device),
+                    LogEvent::Batch(batch) => {
+                        let (t0, started) =
+                            *origin.get_or_insert((batch.host_time, Instant::now()));
+                        let due = started
+                            + Duration::from_secs_f64(
+                                ((batch.host_time - t0) / self.options.speed).max(0.0),
+                            );
+                        if !self.sleep_until(due) {
+                            return Ok(());
+                        }
+                        self.send_batch(&batch);
+                    }
+                }
+            }
+
+            self.flush_all();
+            if !self.options.looped {
+                println!("[TioServer] Reached end of recording.");
+                self.shared.stop.store(true, Ordering::Relaxed);
+                return Ok(());
+            }
+            reader.rewind()?;
+        }
+    }
+
+    /// Returns `false` if the server was stopped while waiting.
+    fn sleep_until(&self, due: Instant) -> bool {
+        loop {
+            if self.shared.stop.load(Ordering::Relaxed) {
+                return false;
+            }
+            let now = Instant::now();
+            if now >= due {
+                return true;
+            }
+            thread::sleep((due - now).min(Duration::from_millis(50)));
+        }
+    }
+
+    fn send_batch(&mut self, batch: &LogBatch) {
+        let route = batch.key.device_route.clone();
+        let stream_id = batch.key.stream_id;
+        let stream_key = (route.clone(), stream_id);
+
+        let session_changed = self
+            .streams
+            .get(&stream_key)
+            .is_some_and(|s| s.session_id != batch.session_id);
+        if session_changed {
+            self.flush(&stream_key);
+            self.streams.remove(&stream_key);
+            if let Some(device) = self.shared.devices.write().unwrap().get_mut(&route) {
+                device.session_id = batch.session_id;
+            }
+        }
+
+        if !self.streams.contains_key(&stream_key) {
+            let devices = self.shared.devices.read().unwrap();
+            let Some(stream) = devices
+                .get(&route)
+                .and_then(|d| d.ui.streams.iter().find(|s| s.meta.stream_id == stream_id))
+            else {
+                return;
+            };
+            let assembler = RowAssembler::new(stream, batch.session_id);
+            drop(devices);
+            self.streams.insert(stream_key.clone(), assembler);
+        }
+
+        let assembler = self.streams.get_mut(&stream_key).unwrap();
+        assembler.push(batch);
+        let rows = assembler.take_ready(false);
+        if !rows.is_empty() {
+            for bytes in assembler.encode(&route, stream_id, &rows) {
+                self.shared.broadcast(bytes);
+            }
+        }
+    }
+
+    fn flush(&mut self, stream_key: &(DeviceRoute, u8)) {
+        if let Some(assembler) = self.streams.get_mut(stream_key) {
+            let rows = assembler.take_ready(true);
+            for bytes in assembler.encode(&stream_key.0, stream_key.1, &rows) {
+                self.shared.broadcast(bytes);
+            }
+        }
+    }
+
+    fn flush_all(&mut self) {
+        let keys: Vec<_> = self.streams.keys().cloned().collect();
+        for key in keys {
+            self.flush(&key);
+        }
+        self.streams.clear();
+    }
+}
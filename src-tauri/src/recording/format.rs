@@ -0,0 +1,317 @@
+//! On-disk layout of Trendline logs (`.tlrec`).
+//!
+//! A log is a fixed header followed by length-prefixed records:
+//!
+//! ```text
+//! header: b"TLREC\0" | version: u16
+//! record: tag: u8 | len: u32 | payload: [u8; len]
+//! ```
+//!
+//! All integers are little-endian. Metadata records carry JSON so they follow `shared.rs` as it
+//! evolves; sample records are packed since they make up nearly the whole file.
+use crate::shared::{DataColumnId, Point, UiDevice};
+use crate::state::capture::SessionId;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const MAGIC: [u8; 6] = *b"TLREC\0";
+pub const VERSION: u16 = 1;
+pub const EXTENSION: &str = "tlrec";
+
+const TAG_DEVICE: u8 = 1;
+const TAG_COLUMN: u8 = 2;
+const TAG_BATCH: u8 = 3;
+
+const HEADER_SIZE: usize = MAGIC.len() + 2;
+const RECORD_HEADER_SIZE: usize = 1 + 4;
+const BATCH_HEADER_SIZE: usize = 4 + 4 + 8 + 4;
+/// Sample number, device time and value.
+const SAMPLE_SIZE: usize = 4 + 8 + 8;
+
+#[derive(Debug, Clone)]
+pub enum Record {
+    /// Full device/stream/segment metadata. Written whenever it is first seen or changes.
+    Device(UiDevice),
+    /// Binds a file-local handle to a column so batches don't repeat the full key.
+    Column { handle: u32, key: DataColumnId },
+    Batch {
+        handle: u32,
+        session_id: SessionId,
+        /// Host wall-clock time (Unix seconds) at which the batch was written.
+        host_time: f64,
+        sample_numbers: Vec<u32>,
+        points: Vec<Point>,
+    },
+}
+
+/// Path of the `index`-th file of a rotated log. The first file keeps the name the user picked.
+pub fn segment_path(base: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return base.to_path_buf();
+    }
+    let stem = base
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "recording".to_string());
+    let ext = base
+        .extension()
+        .map(|e| e.to_string_lossy().into_owned())
+        .unwrap_or_else(|| EXTENSION.to_string());
+    base.with_file_name(format!("{}-{:04}.{}", stem, index, ext))
+}
+
+pub fn write_header<W: Write>(w: &mut W) -> io::Result<usize> {
+    w.write_all(&MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    Ok(HEADER_SIZE)
+}
+
+pub fn read_header<R: Read>(r: &mut R) -> io::Result<u16> {
+    let mut buf = [0u8; HEADER_SIZE];
+    r.read_exact(&mut buf)?;
+    if buf[..MAGIC.len()] != MAGIC {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            "Not a Trendline recording (bad magic).",
+        ));
+    }
+    let version = u16::from_le_bytes([buf[6], buf[7]]);
+    if version > VERSION {
+        return Err(io::Error::new(
+            ErrorKind::InvalidData,
+            format!("Unsupported recording version {} (max {}).", version, VERSION),
+        ));
+    }
+    Ok(version)
+}
+
+fn write_frame<W: Write>(w: &mut W, tag: u8, payload: &[u8]) -> io::Result<usize> {
+    w.write_all(&[tag])?;
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(payload)?;
+    Ok(RECORD_HEADER_SIZE + payload.len())
+}
+
+pub fn write_device<W: Write>(w: &mut W, device: &UiDevice) -> io::Result<usize> {
+    let payload = serde_json::to_vec(device)?;
+    write_frame(w, TAG_DEVICE, &payload)
+}
+
+pub fn write_column<W: Write>(w: &mut W, handle: u32, key: &DataColumnId) -> io::Result<usize> {
+    let mut payload = handle.to_le_bytes().to_vec();
+    serde_json::to_writer(&mut payload, key)?;
+    write_frame(w, TAG_COLUMN, &payload)
+}
+
+/// Writes as many samples as there are both points and sample numbers for. Returns the bytes
+/// and the samples written.
+pub fn write_batch<W: Write>(
+    w: &mut W,
+    handle: u32,
+    session_id: SessionId,
+    host_time: f64,
+    sample_numbers: &[u32],
+    points: &[Point],
+) -> io::Result<(usize, usize)> {
+    let n = points.len().min(sample_numbers.len());
+    let mut payload = Vec::with_capacity(BATCH_HEADER_SIZE + n * SAMPLE_SIZE);
+    payload.extend_from_slice(&handle.to_le_bytes());
+    payload.extend_from_slice(&session_id.to_le_bytes());
+    payload.extend_from_slice(&host_time.to_le_bytes());
+    payload.extend_from_slice(&(n as u32).to_le_bytes());
+    for (sn, p) in sample_numbers.iter().zip(points.iter()) {
+        payload.extend_from_slice(&sn.to_le_bytes());
+        payload.extend_from_slice(&p.x.to_le_bytes());
+        payload.extend_from_slice(&p.y.to_le_bytes());
+    }
+    Ok((write_frame(w, TAG_BATCH, &payload)?, n))
+}
+
+/// Reads the next record. Returns `Ok(None)` at end of file, including a record cut short by
+/// a crash mid-write, so a log is always readable up to the last complete record.
+pub fn read_record<R: Read>(r: &mut R) -> io::Result<Option<Record>> {
+    loop {
+        let mut header = [0u8; RECORD_HEADER_SIZE];
+        match r.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+        let tag = header[0];
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        let mut payload = vec![0u8; len];
+        match r.read_exact(&mut payload) {
+            Ok(()) => {}
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        match tag {
+            TAG_DEVICE => return Ok(Some(Record::Device(serde_json::from_slice(&payload)?))),
+            TAG_COLUMN => {
+                if payload.len() < 4 {
+                    return Err(invalid("Column record too short."));
+                }
+                let handle = u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]);
+                let key = serde_json::from_slice(&payload[4..])?;
+                return Ok(Some(Record::Column { handle, key }));
+            }
+            TAG_BATCH => return decode_batch(&payload).map(Some),
+            // Unknown tags come from newer writers; skip them.
+            _ => continue,
+        }
+    }
+}
+
+fn decode_batch(payload: &[u8]) -> io::Result<Record> {
+    if payload.len() < BATCH_HEADER_SIZE {
+        return Err(invalid("Batch record too short."));
+    }
+    let handle = u32::from_le_bytes(payload[0..4].try_into().unwrap());
+    let session_id = u32::from_le_bytes(payload[4..8].try_into().unwrap());
+    let host_time = f64::from_le_bytes(payload[8..16].try_into().unwrap());
+    let n = u32::from_le_bytes(payload[16..20].try_into().unwrap()) as usize;
+    if payload.len() < BATCH_HEADER_SIZE + n * SAMPLE_SIZE {
+        return Err(invalid("Batch record truncated."));
+    }
+
+    let mut sample_numbers = Vec::with_capacity(n);
+    let mut points = Vec::with_capacity(n);
+    for chunk in payload[BATCH_HEADER_SIZE..].chunks_exact(SAMPLE_SIZE).take(n) {
+        sample_numbers.push(u32::from_le_bytes(chunk[0..4].try_into().unwrap()));
+        points.push(Point {
+            x: f64::from_le_bytes(chunk[4..12].try_into().unwrap()),
+            y: f64::from_le_bytes(chunk[12..20].try_into().unwrap()),
+        });
+    }
+
+    Ok(Record::Batch {
+        handle,
+        session_id,
+        host_time,
+        sample_numbers,
+        points,
+    })
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use twinleaf::tio::proto::DeviceRoute;
+
+    fn key() -> DataColumnId {
+        DataColumnId {
+            port_url: "serial:///dev/ttyUSB0".to_string(),
+            device_route: DeviceRoute::root(),
+            stream_id: 1,
+            column_index: 2,
+        }
+    }
+
+    fn points(n: usize) -> Vec<Point> {
+        (0..n)
+            .map(|i| Point {
+                x: i as f64 * 0.1,
+                y: -(i as f64),
+            })
+            .collect()
+    }
+
+    /// A header, a column and a batch of three samples.
+    fn log() -> Vec<u8> {
+        let mut buf = Vec::new();
+        let mut bytes = write_header(&mut buf).unwrap();
+        bytes += write_column(&mut buf, 7, &key()).unwrap();
+        let (batch_bytes, samples) =
+            write_batch(&mut buf, 7, 4, 1.7e9, &[10, 11, 12], &points(3)).unwrap();
+        assert_eq!(samples, 3);
+        assert_eq!(bytes + batch_bytes, buf.len());
+        buf
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let buf = log();
+        let mut r = Cursor::new(buf);
+        assert_eq!(read_header(&mut r).unwrap(), VERSION);
+        match read_record(&mut r).unwrap() {
+            Some(Record::Column { handle, key: k }) => {
+                assert_eq!(handle, 7);
+                assert_eq!(k, key());
+            }
+            other => panic!("expected a column, got {:?}", other),
+        }
+        match read_record(&mut r).unwrap() {
+            Some(Record::Batch {
+                handle,
+                session_id,
+                host_time,
+                sample_numbers,
+                points: p,
+            }) => {
+                assert_eq!((handle, session_id, host_time), (7, 4, 1.7e9));
+                assert_eq!(sample_numbers, vec![10, 11, 12]);
+                assert_eq!(p, points(3));
+            }
+            other => panic!("expected a batch, got {:?}", other),
+        }
+        assert!(read_record(&mut r).unwrap().is_none());
+    }
+
+    #[test]
+    fn batch_writes_only_complete_samples() {
+        let mut buf = Vec::new();
+        let (bytes, samples) = write_batch(&mut buf, 0, 0, 0.0, &[1, 2], &points(5)).unwrap();
+        assert_eq!(samples, 2);
+        assert_eq!(
+            bytes,
+            RECORD_HEADER_SIZE + BATCH_HEADER_SIZE + 2 * SAMPLE_SIZE
+        );
+        match read_record(&mut Cursor::new(buf)).unwrap() {
+            Some(Record::Batch { points: p, .. }) => assert_eq!(p, points(2)),
+            other => panic!("expected a batch, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn truncated_trailing_record_reads_as_eof() {
+        let full = log();
+        // Cut inside the batch's payload, then inside its record header.
+        for cut in [
+            full.len() - 1,
+            full.len() - 3 * SAMPLE_SIZE - BATCH_HEADER_SIZE - 2,
+        ] {
+            let mut r = Cursor::new(&full[..cut]);
+            read_header(&mut r).unwrap();
+            assert!(matches!(
+                read_record(&mut r),
+                Ok(Some(Record::Column { .. }))
+            ));
+            assert!(read_record(&mut r).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn unknown_records_are_skipped() {
+        let mut buf = Vec::new();
+        write_frame(&mut buf, 99, b"from a newer writer").unwrap();
+        write_column(&mut buf, 1, &key()).unwrap();
+        assert!(matches!(
+            read_record(&mut Cursor::new(buf)),
+            Ok(Some(Record::Column { handle: 1, .. }))
+        ));
+    }
+
+    #[test]
+    fn bad_magic_is_rejected() {
+        let mut buf = log();
+        buf[0] = b'X';
+        let err = read_header(&mut Cursor::new(buf)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,5 @@
+pub mod format;
+pub mod reader;
+pub mod recorder;
+pub mod replay;
+pub mod session;
@@ -0,0 +1,158 @@
+//! Sequential reader over a rotated `.tlrec` log, shared by replay and the headless TIO server.
+use super::format::{self, Record};
+use crate::shared::{DataColumnId, Point, UiDevice};
+use crate::state::capture::SessionId;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+pub struct LogBatch {
+    pub key: DataColumnId,
+    pub session_id: SessionId,
+    /// Host wall-clock time (Unix seconds) at which the batch was recorded.
+    pub host_time: f64,
+    pub sample_numbers: Vec<u32>,
+    pub points: Vec<Point>,
+}
+
+pub enum LogEvent {
+    Device(UiDevice),
+    Batch(LogBatch),
+}
+
+/// Tracks the byte offset of a reader so positions cost no extra syscalls.
+struct CountingReader<R> {
+    inner: R,
+    pos: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+pub struct LogReader {
+    segments: Vec<PathBuf>,
+    segment: usize,
+    reader: CountingReader<BufReader<File>>,
+    /// Column handles of the current file; they restart with every rotated file.
+    handles: HashMap<u32, DataColumnId>,
+}
+
+impl LogReader {
+    /// All files of the log starting at `base`, in the order they were written.
+    pub fn segments(base: &Path) -> Vec<PathBuf> {
+        let mut segments = vec![base.to_path_buf()];
+        loop {
+            let next = format::segment_path(base, segments.len());
+            if !next.exists() {
+                break;
+            }
+            segments.push(next);
+        }
+        segments
+    }
+
+    pub fn open(base: &Path) -> io::Result<Self> {
+        let segments = Self::segments(base);
+        let reader = Self::open_segment(&segments[0])?;
+        Ok(Self {
+            segments,
+            segment: 0,
+            reader,
+            handles: HashMap::new(),
+        })
+    }
+
+    fn open_segment(path: &Path) -> io::Result<CountingReader<BufReader<File>>> {
+        let mut reader = CountingReader {
+            inner: BufReader::new(File::open(path)?),
+            pos: 0,
+        };
+        format::read_header(&mut reader)?;
+        Ok(reader)
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    /// File index and byte offset of the next record.
+    pub fn position(&self) -> (usize, u64) {
+        (self.segment, self.reader.pos)
+    }
+
+    /// Column handles seen so far in the current file.
+    pub fn handles(&self) -> &HashMap<u32, DataColumnId> {
+        &self.handles
+    }
+
+    /// Jumps to a position previously returned by `position`. `handles` must hold the column
+    /// handles of that file, since their definitions may lie before `offset`.
+    pub fn seek(
+        &mut self,
+        segment: usize,
+        offset: u64,
+        handles: HashMap<u32, DataColumnId>,
+    ) -> io::Result<()> {
+        let mut inner = BufReader::new(File::open(&self.segments[segment])?);
+        inner.seek(SeekFrom::Start(offset))?;
+        self.reader = CountingReader { inner, pos: offset };
+        self.segment = segment;
+        self.handles = handles;
+        Ok(())
+    }
+
+    /// Back to the first record of the first file.
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.reader = Self::open_segment(&self.segments[0])?;
+        self.segment = 0;
+        self.handles.clear();
+        Ok(())
+    }
+
+    /// Next device or sample batch, moving on to the next rotated file as each one ends.
+    /// Batches for columns whose handle was never defined are skipped.
+    pub fn next_event(&mut self) -> io::Result<Option<LogEvent>> {
+        loop {
+            let Some(record) = format::read_record(&mut self.reader)? else {
+                if self.segment + 1 >= self.segments.len() {
+                    return Ok(None);
+                }
+                self.segment += 1;
+                self.reader = Self::open_segment(&self.segments[self.segment])?;
+                self.handles.clear();
+                continue;
+            };
+
+            match record {
+                Record::Device(device) => return Ok(Some(LogEvent::Device(device))),
+                Record::Column { handle, key } => {
+                    self.handles.insert(handle, key);
+                }
+                Record::Batch {
+                    handle,
+                    session_id,
+                    host_time,
+                    sample_numbers,
+                    points,
+                } => {
+                    let Some(key) = self.handles.get(&handle) else {
+                        continue;
+                    };
+                    return Ok(Some(LogEvent::Batch(LogBatch {
+                        key: key.clone(),
+                        session_id,
+                        host_time,
+                        sample_numbers,
+                        points,
+                    })));
+                }
+            }
+        }
+    }
+}
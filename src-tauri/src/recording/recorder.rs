@@ -0,0 +1,414 @@
+use super::format;
+use crate::shared::{DataColumnId, Point, RecordingStatus, UiDevice};
+use crate::state::capture::{BatchedData, CaptureCommand, CaptureState};
+use crate::util;
+use crossbeam::channel::{bounded, select, tick, unbounded, Receiver, Sender};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tauri::menu::MenuItemKind;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Whichever limit is hit first closes the current file and opens the next one.
+#[derive(Clone, Debug)]
+pub struct RotationPolicy {
+    pub max_file_bytes: u64,
+    pub max_file_duration: Duration,
+}
+
+impl Default for RotationPolicy {
+    fn default() -> Self {
+        Self {
+            max_file_bytes: 512 * 1024 * 1024,
+            max_file_duration: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+enum WriterMessage {
+    Device(Box<UiDevice>),
+    Stop,
+}
+
+#[derive(Default)]
+struct WriterStats {
+    files_written: AtomicUsize,
+    bytes_written: AtomicU64,
+    samples_written: AtomicU64,
+    /// Samples the capture thread dropped because the writer fell behind.
+    samples_dropped: Arc<AtomicU64>,
+    current_file: Mutex<Option<PathBuf>>,
+    error: Mutex<Option<String>>,
+}
+
+struct ActiveRecording {
+    path: PathBuf,
+    tap_id: usize,
+    msg_tx: Sender<WriterMessage>,
+    handle: JoinHandle<()>,
+    stats: Arc<WriterStats>,
+}
+
+/// Streams every active column that reaches `CaptureState` to a rotating `.tlrec` log.
+pub struct Recorder {
+    capture: CaptureState,
+    app: AppHandle,
+    rotation: RotationPolicy,
+    active: Mutex<Option<ActiveRecording>>,
+    next_tap_id: AtomicUsize,
+}
+
+impl Recorder {
+    pub fn new(capture: CaptureState, app: AppHandle) -> Self {
+        Self {
+            capture,
+            app,
+            rotation: RotationPolicy::default(),
+            active: Mutex::new(None),
+            next_tap_id: AtomicUsize::new(1),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.reap_failed();
+        self.active.lock().unwrap().is_some()
+    }
+
+    /// Starts a new log at `path`. `devices` seeds the metadata for ports that were already
+    /// streaming; devices discovered later arrive through `record_device`.
+    pub fn start(&self, path: PathBuf, devices: Vec<UiDevice>) -> Result<RecordingStatus, String> {
+        self.reap_failed();
+        {
+            let mut active = self.active.lock().unwrap();
+            if let Some(rec) = active.as_ref() {
+                return Err(format!(
+                    "Already recording to '{}'.",
+                    rec.path.to_string_lossy()
+                ));
+            }
+
+            let stats = Arc::new(WriterStats::default());
+            let writer = LogWriter::create(path.clone(), self.rotation.clone(), stats.clone())
+                .map_err(|e| format!("Failed to create '{}': {}", path.to_string_lossy(), e))?;
+
+            let (data_tx, data_rx) = bounded::<Arc<BatchedData>>(16_384);
+            let (msg_tx, msg_rx) = unbounded::<WriterMessage>();
+            for device in devices {
+                let _ = msg_tx.send(WriterMessage::Device(Box::new(device)));
+            }
+
+            let app = self.app.clone();
+            let handle = thread::Builder::new()
+                .name("recorder".into())
+                .spawn(move || writer.run(data_rx, msg_rx, app))
+                .map_err(|e| format!("Failed to spawn recorder thread: {}", e))?;
+
+            let tap_id = self.next_tap_id.fetch_add(1, Ordering::Relaxed);
+            self.capture
+                .inner
+                .command_tx
+                .send(CaptureCommand::Tap {
+                    id: tap_id,
+                    tx: data_tx,
+                    dropped: stats.samples_dropped.clone(),
+                })
+                .map_err(|e| format!("Failed to attach recorder to capture: {}", e))?;
+
+            println!("[Recorder] Recording to {}", path.to_string_lossy());
+            *active = Some(ActiveRecording {
+                path,
+                tap_id,
+                msg_tx,
+                handle,
+                stats,
+            });
+        }
+
+        let status = self.status();
+        self.emit_status(&status);
+        Ok(status)
+    }
+
+    pub fn stop(&self) -> Result<RecordingStatus, String> {
+        let Some(rec) = self.active.lock().unwrap().take() else {
+            return Err("Not recording.".to_string());
+        };
+        let status = self.detach(rec);
+        self.emit_status(&status);
+        Ok(status)
+    }
+
+    /// Untaps the capture and joins the writer, returning the final status.
+    fn detach(&self, rec: ActiveRecording) -> RecordingStatus {
+        let _ = self
+            .capture
+            .inner
+            .command_tx
+            .send(CaptureCommand::Untap { id: rec.tap_id });
+        let _ = rec.msg_tx.send(WriterMessage::Stop);
+        if let Err(e) = rec.handle.join() {
+            eprintln!("[Recorder] Writer thread panicked: {:?}", e);
+        }
+        println!(
+            "[Recorder] Stopped. Wrote {} samples across {} file(s), dropped {}.",
+            rec.stats.samples_written.load(Ordering::Relaxed),
+            rec.stats.files_written.load(Ordering::Relaxed),
+            rec.stats.samples_dropped.load(Ordering::Relaxed)
+        );
+
+        let mut status = Self::status_from(&rec.path, &rec.stats);
+        status.is_recording = false;
+        status
+    }
+
+    /// Detaches the active recording if its writer has failed, so a new one can start. Returns
+    /// its final status, error included.
+    fn reap_failed(&self) -> Option<RecordingStatus> {
+        let rec = {
+            let mut active = self.active.lock().unwrap();
+            if active.as_ref()?.stats.error.lock().unwrap().is_none() {
+                return None;
+            }
+            active.take()?
+        };
+        let status = self.detach(rec);
+        self.emit_status(&status);
+        Some(status)
+    }
+
+    /// Queues fresh device metadata (discovery, segment change) for the active log.
+    pub fn record_device(&self, device: &UiDevice) {
+        if let Some(rec) = self.active.lock().unwrap().as_ref() {
+            let _ = rec.msg_tx.send(WriterMessage::Device(Box::new(device.clone())));
+        }
+    }
+
+    pub fn status(&self) -> RecordingStatus {
+        if let Some(status) = self.reap_failed() {
+            return status;
+        }
+        match self.active.lock().unwrap().as_ref() {
+            Some(rec) => Self::status_from(&rec.path, &rec.stats),
+            None => RecordingStatus::default(),
+        }
+    }
+
+    fn status_from(path: &Path, stats: &WriterStats) -> RecordingStatus {
+        let error = stats.error.lock().unwrap().clone();
+        RecordingStatus {
+            is_recording: true,
+            path: Some(path.to_string_lossy().into_owned()),
+            current_file: stats
+                .current_file
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map(|p| p.to_string_lossy().into_owned()),
+            files_written: stats.files_written.load(Ordering::Relaxed),
+            bytes_written: stats.bytes_written.load(Ordering::Relaxed),
+            samples_written: stats.samples_written.load(Ordering::Relaxed),
+            samples_dropped: stats.samples_dropped.load(Ordering::Relaxed),
+            error,
+        }
+    }
+
+    fn emit_status(&self, status: &RecordingStatus) {
+        if let Err(e) = self.app.emit("recording-state-changed", status.clone()) {
+            eprintln!("[Recorder] Failed to emit recording-state-changed: {}", e);
+        }
+
+        if let Some(window) = self.app.get_webview_window("main") {
+            if let Some(menu) = window.menu() {
+                if let Some(device_menu) = util::find_submenu_by_text(&menu, "Device") {
+                    if let Some(MenuItemKind::MenuItem(item)) = device_menu.get("toggle_logging") {
+                        let text = if status.is_recording {
+                            "Stop Logging"
+                        } else {
+                            "Start Logging"
+                        };
+                        let _ = item.set_text(text);
+                    }
+                }
+            }
+        }
+    }
+}
+
+struct LogWriter {
+    base_path: PathBuf,
+    rotation: RotationPolicy,
+    stats: Arc<WriterStats>,
+    file: BufWriter<File>,
+    file_index: usize,
+    file_opened: Instant,
+    file_bytes: u64,
+    /// Latest metadata per `(url, route)`, replayed at the top of every rotated file.
+    devices: HashMap<(String, String), UiDevice>,
+    /// Column handles are file-local so each rotated file can be read on its own.
+    handles: HashMap<DataColumnId, u32>,
+}
+
+impl LogWriter {
+    fn create(base_path: PathBuf, rotation: RotationPolicy, stats: Arc<WriterStats>) -> io::Result<Self> {
+        let (file, header_bytes) = Self::open_file(&base_path)?;
+        stats.files_written.store(1, Ordering::Relaxed);
+        stats.bytes_written.store(header_bytes as u64, Ordering::Relaxed);
+        *stats.current_file.lock().unwrap() = Some(base_path.clone());
+        Ok(Self {
+            base_path,
+            rotation,
+            stats,
+            file,
+            file_index: 0,
+            file_opened: Instant::now(),
+            file_bytes: header_bytes as u64,
+            devices: HashMap::new(),
+            handles: HashMap::new(),
+        })
+    }
+
+    fn open_file(path: &Path) -> io::Result<(BufWriter<File>, usize)> {
+        let mut file = BufWriter::with_capacity(1 << 20, File::create(path)?);
+        let n = format::write_header(&mut file)?;
+        Ok((file, n))
+    }
+
+    fn run(mut self, data_rx: Receiver<Arc<BatchedData>>, msg_rx: Receiver<WriterMessage>, app: AppHandle) {
+        let result = self.pump(&data_rx, &msg_rx).and_then(|_| self.file.flush());
+        if let Err(e) = result {
+            let msg = format!("Recording stopped: {}", e);
+            eprintln!("[Recorder] {}", msg);
+            *self.stats.error.lock().unwrap() = Some(msg.clone());
+            let _ = app.emit("recording-error", msg);
+            // Detach from the capture now rather than on the next start or status query; this
+            // thread is about to exit, so the join in `detach` can't wait on it.
+            thread::spawn(move || {
+                if let Some(recorder) = app.try_state::<Arc<Recorder>>() {
+                    recorder.reap_failed();
+                }
+            });
+        }
+    }
+
+    fn pump(
+        &mut self,
+        data_rx: &Receiver<Arc<BatchedData>>,
+        msg_rx: &Receiver<WriterMessage>,
+    ) -> io::Result<()> {
+        let flush_ticker = tick(Duration::from_secs(1));
+        loop {
+            select! {
+                recv(data_rx) -> msg => match msg {
+                    Ok(batch) => self.write_batch(&batch)?,
+                    Err(_) => return Ok(()),
+                },
+                recv(msg_rx) -> msg => match msg {
+                    Ok(WriterMessage::Device(device)) => self.write_device(*device)?,
+                    Ok(WriterMessage::Stop) | Err(_) => {
+                        // Drain what the capture thread already handed us before closing.
+                        while let Ok(batch) = data_rx.try_recv() {
+                            self.write_batch(&batch)?;
+                        }
+                        return Ok(());
+                    }
+                },
+                recv(flush_ticker) -> _ => self.file.flush()?,
+            }
+        }
+    }
+
+    fn account(&mut self, n: usize) {
+        self.file_bytes += n as u64;
+        self.stats.bytes_written.fetch_add(n as u64, Ordering::Relaxed);
+    }
+
+    fn rotate_if_needed(&mut self) -> io::Result<()> {
+        if self.file_bytes < self.rotation.max_file_bytes
+            && self.file_opened.elapsed() < self.rotation.max_file_duration
+        {
+            return Ok(());
+        }
+        self.file.flush()?;
+        self.file_index += 1;
+        let path = format::segment_path(&self.base_path, self.file_index);
+        let (file, header_bytes) = Self::open_file(&path)?;
+        println!("[Recorder] Rotated to {}", path.to_string_lossy());
+
+        self.file = file;
+        self.file_opened = Instant::now();
+        self.file_bytes = 0;
+        self.account(header_bytes);
+        self.handles.clear();
+        self.stats.files_written.fetch_add(1, Ordering::Relaxed);
+        *self.stats.current_file.lock().unwrap() = Some(path);
+
+        let devices: Vec<UiDevice> = self.devices.values().cloned().collect();
+        for device in &devices {
+            let n = format::write_device(&mut self.file, device)?;
+            self.account(n);
+        }
+        Ok(())
+    }
+
+    fn write_device(&mut self, device: UiDevice) -> io::Result<()> {
+        let key = (device.url.clone(), device.route.clone());
+        if self.devices.get(&key) == Some(&device) {
+            return Ok(());
+        }
+        self.rotate_if_needed()?;
+        let n = format::write_device(&mut self.file, &device)?;
+        self.account(n);
+        self.devices.insert(key, device);
+        Ok(())
+    }
+
+    fn write_batch(&mut self, batch: &BatchedData) -> io::Result<()> {
+        if batch.points.is_empty() {
+            return Ok(());
+        }
+        self.rotate_if_needed()?;
+
+        let handle = match self.handles.get(&batch.key) {
+            Some(h) => *h,
+            None => {
+                let h = self.handles.len() as u32;
+                let n = format::write_column(&mut self.file, h, &batch.key)?;
+                self.account(n);
+                self.handles.insert(batch.key.clone(), h);
+                h
+            }
+        };
+
+        let host_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        // Logs keep device time; the unified offset is recomputed on replay.
+        let points: Vec<Point> = batch
+            .points
+            .iter()
+            .map(|p| Point {
+                x: p.x - batch.offset,
+                y: p.y,
+            })
+            .collect();
+        let (bytes, samples) = format::write_batch(
+            &mut self.file,
+            handle,
+            batch.session_id,
+            host_time,
+            &batch.sample_numbers,
+            &points,
+        )?;
+        self.account(bytes);
+        self.stats
+            .samples_written
+            .fetch_add(samples as u64, Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -0,0 +1,517 @@
+//! Plays a `.tlrec` log back into `CaptureState` as a pseudo-port (`file:///path/to/log`).
+//!
+//! Samples go through the same `InsertBatch` path as a live `PortManager`, so plots, FFTs and the
+//! stream monitor work on recorded data unchanged. Column keys and devices are re-homed under the
+//! replay URL so a recording never collides with the live port it was captured from.
+use super::reader::{LogBatch, LogEvent, LogReader};
+use crate::pipeline::manager::ProcessingManager;
+use crate::shared::{DataColumnId, PortState, ReplayStatus, UiDevice};
+use crate::state::capture::{CaptureCommand, CaptureState};
+use crossbeam::channel::{select, tick, unbounded, Receiver, Sender};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+pub const URL_SCHEME: &str = "file://";
+
+/// Seconds of history pushed into `CaptureState` immediately after a seek, so plots have context.
+const PREROLL_SECONDS: f64 = 30.0;
+/// Spacing of the seek index, in recorded host seconds.
+const CHECKPOINT_INTERVAL_SECONDS: f64 = 0.5;
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Debug)]
+enum ReplayCommand {
+    Play,
+    Pause,
+    Seek(f64),
+    SetSpeed(f64),
+    Shutdown,
+}
+
+struct Checkpoint {
+    segment: usize,
+    offset: u64,
+    host_time: f64,
+    /// Column handles known at this point of the file.
+    handles: Arc<HashMap<u32, DataColumnId>>,
+}
+
+/// Everything learned from one pass over the log before playback starts.
+struct LogIndex {
+    segment_count: usize,
+    checkpoints: Vec<Checkpoint>,
+    devices: Vec<UiDevice>,
+    start_time: f64,
+    end_time: f64,
+}
+
+impl LogIndex {
+    fn build(reader: &mut LogReader) -> io::Result<Self> {
+        let mut index = LogIndex {
+            segment_count: reader.segment_count(),
+            checkpoints: Vec::new(),
+            devices: Vec::new(),
+            start_time: f64::NAN,
+            end_time: f64::NAN,
+        };
+        let mut seen_devices = HashMap::new();
+        let mut last_checkpoint = f64::NEG_INFINITY;
+        let mut handles = Arc::new(HashMap::new());
+
+        loop {
+            let (segment, offset) = reader.position();
+            let Some(event) = reader.next_event()? else {
+                break;
+            };
+            match event {
+                LogEvent::Device(device) => {
+                    seen_devices
+                        .entry((device.url.clone(), device.route.clone()))
+                        .or_insert(device);
+                }
+                LogEvent::Batch(batch) => {
+                    if index.start_time.is_nan() {
+                        index.start_time = batch.host_time;
+                    }
+                    index.end_time = batch.host_time;
+                    if batch.host_time - last_checkpoint < CHECKPOINT_INTERVAL_SECONDS {
+                        continue;
+                    }
+                    // Handles only grow within a file, so the snapshot can be shared until then.
+                    let stale = index
+                        .checkpoints
+                        .last()
+                        .is_none_or(|c| c.segment != segment)
+                        || handles.len() != reader.handles().len();
+                    if stale {
+                        handles = Arc::new(reader.handles().clone());
+                    }
+                    index.checkpoints.push(Checkpoint {
+                        segment,
+                        offset,
+                        host_time: batch.host_time,
+                        handles: handles.clone(),
+                    });
+                    last_checkpoint = batch.host_time;
+                }
+            }
+        }
+
+        index.devices = seen_devices.into_values().collect();
+        Ok(index)
+    }
+
+    fn duration(&self) -> f64 {
+        (self.end_time - self.start_time).max(0.0)
+    }
+}
+
+pub struct ReplayPort {
+    pub url: String,
+    pub path: PathBuf,
+    pub state: Mutex<PortState>,
+    /// Devices keyed by route, already re-homed under `url`.
+    pub devices: RwLock<HashMap<String, UiDevice>>,
+    status: Mutex<ReplayStatus>,
+    command_tx: Sender<ReplayCommand>,
+    app: AppHandle,
+    capture: CaptureState,
+}
+
+impl ReplayPort {
+    pub fn url_for(path: &Path) -> String {
+        format!("{}{}", URL_SCHEME, path.to_string_lossy())
+    }
+
+    /// Indexes the log at `path` and starts its playback thread, paused at the first sample.
+    pub fn open(path: PathBuf, app: AppHandle, capture: CaptureState) -> Result<Arc<Self>, String> {
+        let read_err = |e: io::Error| format!("Failed to read '{}': {}", path.to_string_lossy(), e);
+        let mut reader = LogReader::open(&path).map_err(read_err)?;
+        let index = LogIndex::build(&mut reader).map_err(read_err)?;
+        if index.checkpoints.is_empty() {
+            return Err(format!(
+                "'{}' contains no samples.",
+                path.to_string_lossy()
+            ));
+        }
+
+        let url = Self::url_for(&path);
+        let (command_tx, command_rx) = unbounded::<ReplayCommand>();
+        let port = Arc::new(Self {
+            url: url.clone(),
+            path: path.clone(),
+            state: Mutex::new(PortState::Idle),
+            devices: RwLock::new(HashMap::new()),
+            status: Mutex::new(ReplayStatus {
+                url,
+                path: path.to_string_lossy().into_owned(),
+                is_playing: false,
+                speed: 1.0,
+                position: 0.0,
+                duration: index.duration(),
+            }),
+            command_tx,
+            app,
+            capture,
+        });
+
+        println!(
+            "[{}] Indexed {} file(s), {:.1}s of data.",
+            port.url,
+            index.segment_count,
+            index.duration()
+        );
+
+        let player_port = port.clone();
+        thread::Builder::new()
+            .name(format!("replay-{}", port.url))
+            .spawn(move || Player::new(player_port, index, reader).run(command_rx))
+            .map_err(|e| format!("Failed to spawn replay thread: {}", e))?;
+        Ok(port)
+    }
+
+    pub fn play(&self) {
+        let _ = self.command_tx.send(ReplayCommand::Play);
+    }
+
+    pub fn pause(&self) {
+        let _ = self.command_tx.send(ReplayCommand::Pause);
+    }
+
+    /// `position` is in seconds from the start of the recording.
+    pub fn seek(&self, position: f64) {
+        let _ = self.command_tx.send(ReplayCommand::Seek(position));
+    }
+
+    pub fn set_speed(&self, speed: f64) -> Result<(), String> {
+        if !speed.is_finite() || speed <= 0.0 {
+            return Err(format!("Invalid playback speed: {}", speed));
+        }
+        let _ = self.command_tx.send(ReplayCommand::SetSpeed(speed));
+        Ok(())
+    }
+
+    pub fn shutdown(&self) {
+        let _ = self.command_tx.send(ReplayCommand::Shutdown);
+    }
+
+    pub fn status(&self) -> ReplayStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    pub fn ui_devices(&self) -> Vec<UiDevice> {
+        self.devices.read().unwrap().values().cloned().collect()
+    }
+
+    fn set_state(&self, new_state: PortState) {
+        *self.state.lock().unwrap() = new_state.clone();
+        for device in self.devices.write().unwrap().values_mut() {
+            device.state = new_state.clone();
+        }
+        if let Err(e) = self
+            .app
+            .emit("port-state-changed", (self.url.clone(), new_state.clone()))
+        {
+            eprintln!("[{}] Failed to emit port-state-changed: {}", self.url, e);
+        }
+        println!("[{}] Emit new port state {:?}", self.url, new_state);
+    }
+
+    fn rehome_key(&self, key: &DataColumnId) -> DataColumnId {
+        DataColumnId {
+            port_url: self.url.clone(),
+            ..key.clone()
+        }
+    }
+
+    /// Stores recorded metadata under this port and forwards the sample rates to capture.
+    /// Returns the re-homed device, or `None` if nothing changed.
+    fn apply_device(&self, mut device: UiDevice) -> Option<UiDevice> {
+        device.url = self.url.clone();
+        device.state = self.state.lock().unwrap().clone();
+
+        let mut devices = self.devices.write().unwrap();
+        if devices.get(&device.route) == Some(&device) {
+            return None;
+        }
+
+        if let Ok(route) = twinleaf::tio::proto::DeviceRoute::from_str(&device.route) {
+            for stream in &device.streams {
+                let key = DataColumnId {
+                    port_url: self.url.clone(),
+                    device_route: route.clone(),
+                    stream_id: stream.meta.stream_id,
+                    column_index: 0,
+                };
+                if let Some(segment) = &stream.segment {
+                    let _ = self.capture.inner.command_tx.send(CaptureCommand::UpdateTimeBase {
+                        key: key.clone(),
+                        epoch: segment.time_ref_epoch.clone(),
+                    });
+                }
+                let _ = self.capture.inner.command_tx.send(CaptureCommand::UpdateSampleRate {
+                    key,
+                    rate: stream.effective_sampling_rate,
+                });
+            }
+        }
+        devices.insert(device.route.clone(), device.clone());
+        Some(device)
+    }
+
+    /// Drops everything this replay has put into `CaptureState` and resets the pipelines fed by
+    /// it, so a seek doesn't stitch unrelated stretches of the recording together.
+    fn reset_capture(&self) {
+        // Let batches that are already queued land before they are cleared.
+        self.capture.flush();
+
+        let mut device_keys: Vec<DataColumnId> = Vec::new();
+        for entry in self.capture.inner.streams.iter() {
+            let device_key = entry.key().device_key();
+            if device_key.port_url == self.url && !device_keys.contains(&device_key) {
+                device_keys.push(device_key);
+            }
+        }
+
+        let manager = self.app.try_state::<Arc<Mutex<ProcessingManager>>>();
+        for key in &device_keys {
+            self.capture.clear_device_by_key(key);
+            if let Some(manager) = manager.as_ref() {
+                if let Err(e) = manager.lock().unwrap().clear_device(key, false) {
+                    eprintln!("[{}] Failed to reset pipelines: {}", self.url, e);
+                }
+            }
+        }
+    }
+
+    fn publish_status(&self, status: ReplayStatus) {
+        *self.status.lock().unwrap() = status.clone();
+        if let Err(e) = self.app.emit("replay-status-changed", status) {
+            eprintln!("[{}] Failed to emit replay-status-changed: {}", self.url, e);
+        }
+    }
+}
+
+struct Player {
+    port: Arc<ReplayPort>,
+    index: LogIndex,
+    reader: LogReader,
+    pending: Option<LogBatch>,
+    playing: bool,
+    speed: f64,
+    /// Recorded host time reached by playback, and the wall-clock instant it was reached.
+    clock: (f64, Instant),
+    /// Stand-in for the capture instant of the first batch; keeps session gaps as recorded.
+    epoch: Instant,
+}
+
+impl Player {
+    fn new(port: Arc<ReplayPort>, index: LogIndex, reader: LogReader) -> Self {
+        let start_time = index.start_time;
+        Self {
+            port,
+            index,
+            reader,
+            pending: None,
+            playing: false,
+            speed: 1.0,
+            clock: (start_time, Instant::now()),
+            epoch: Instant::now(),
+        }
+    }
+
+    fn run(mut self, command_rx: Receiver<ReplayCommand>) {
+        self.port.set_state(PortState::Discovery);
+        for device in self.index.devices.clone() {
+            self.port.apply_device(device);
+        }
+        if let Err(e) = self.open_checkpoint(0) {
+            self.fail(e);
+            return;
+        }
+        self.port.set_state(PortState::Streaming);
+        if let Err(e) = self
+            .port
+            .app
+            .emit("port-devices-discovered", self.port.ui_devices())
+        {
+            eprintln!("[{}] Failed to emit port-devices-discovered: {}", self.port.url, e);
+        }
+        self.publish_status();
+
+        let ticker = tick(Duration::from_millis(10));
+        let mut last_status = Instant::now();
+
+        loop {
+            select! {
+                recv(command_rx) -> command => {
+                    let result = match command {
+                        Ok(ReplayCommand::Play) => self.play(),
+                        Ok(ReplayCommand::Pause) => {
+                            self.hold(false);
+                            Ok(())
+                        }
+                        Ok(ReplayCommand::Seek(position)) => {
+                            self.seek(self.index.start_time + position)
+                        }
+                        Ok(ReplayCommand::SetSpeed(speed)) => {
+                            self.clock = (self.position(), Instant::now());
+                            self.speed = speed;
+                            Ok(())
+                        }
+                        Ok(ReplayCommand::Shutdown) | Err(_) => break,
+                    };
+                    if let Err(e) = result {
+                        self.fail(e);
+                        return;
+                    }
+                    self.publish_status();
+                },
+                recv(ticker) -> _ => {
+                    if !self.playing {
+                        continue;
+                    }
+                    match self.advance(self.position()) {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            println!("[{}] Reached end of recording.", self.port.url);
+                            self.clock = (self.index.end_time, Instant::now());
+                            self.playing = false;
+                            self.publish_status();
+                        }
+                        Err(e) => {
+                            self.fail(e);
+                            return;
+                        }
+                    }
+                    if last_status.elapsed() >= STATUS_INTERVAL {
+                        self.publish_status();
+                        last_status = Instant::now();
+                    }
+                }
+            }
+        }
+
+        println!("[{}] Replay thread shutting down.", self.port.url);
+        self.port.set_state(PortState::Disconnected);
+    }
+
+    fn position(&self) -> f64 {
+        let (host_time, at) = self.clock;
+        let position = if self.playing {
+            host_time + at.elapsed().as_secs_f64() * self.speed
+        } else {
+            host_time
+        };
+        position.min(self.index.end_time)
+    }
+
+    fn hold(&mut self, playing: bool) {
+        self.clock = (self.position(), Instant::now());
+        self.playing = playing;
+    }
+
+    fn play(&mut self) -> io::Result<()> {
+        if self.position() >= self.index.end_time {
+            self.seek(self.index.start_time)?;
+        }
+        self.hold(true);
+        Ok(())
+    }
+
+    fn seek(&mut self, target: f64) -> io::Result<()> {
+        let target = target.clamp(self.index.start_time, self.index.end_time);
+        println!(
+            "[{}] Seeking to {:.2}s.",
+            self.port.url,
+            target - self.index.start_time
+        );
+        self.port.reset_capture();
+
+        let preroll_from = target - PREROLL_SECONDS;
+        let checkpoint = self
+            .index
+            .checkpoints
+            .iter()
+            .rposition(|c| c.host_time <= preroll_from)
+            .unwrap_or(0);
+        self.open_checkpoint(checkpoint)?;
+        self.advance(target)?;
+        self.clock = (target, Instant::now());
+        Ok(())
+    }
+
+    fn open_checkpoint(&mut self, checkpoint: usize) -> io::Result<()> {
+        let cp = &self.index.checkpoints[checkpoint];
+        self.reader
+            .seek(cp.segment, cp.offset, (*cp.handles).clone())?;
+        self.pending = None;
+        Ok(())
+    }
+
+    /// Sends every batch recorded up to `until`. Returns `false` once the log is exhausted.
+    fn advance(&mut self, until: f64) -> io::Result<bool> {
+        loop {
+            if self.pending.is_none() {
+                self.pending = self.next_batch()?;
+            }
+            match &self.pending {
+                None => return Ok(false),
+                Some(b) if b.host_time > until => return Ok(true),
+                Some(_) => {}
+            }
+            let Some(batch) = self.pending.take() else {
+                return Ok(false);
+            };
+
+            let since_start = (batch.host_time - self.index.start_time).max(0.0);
+            let command = CaptureCommand::InsertBatch {
+                key: self.port.rehome_key(&batch.key),
+                points: batch.points,
+                sample_numbers: batch.sample_numbers,
+                session_id: batch.session_id,
+                instant: self.epoch + Duration::from_secs_f64(since_start),
+                host_time: Some(batch.host_time),
+            };
+            if self.port.capture.inner.command_tx.send(command).is_err() {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn next_batch(&mut self) -> io::Result<Option<LogBatch>> {
+        loop {
+            match self.reader.next_event()? {
+                None => return Ok(None),
+                Some(LogEvent::Batch(batch)) => return Ok(Some(batch)),
+                Some(LogEvent::Device(device)) => {
+                    if let Some(device) = self.port.apply_device(device) {
+                        let _ = self.port.app.emit("device-metadata-updated", device);
+                    }
+                }
+            }
+        }
+    }
+
+    fn publish_status(&self) {
+        self.port.publish_status(ReplayStatus {
+            url: self.port.url.clone(),
+            path: self.port.path.to_string_lossy().into_owned(),
+            is_playing: self.playing,
+            speed: self.speed,
+            position: (self.position() - self.index.start_time).max(0.0),
+            duration: self.index.duration(),
+        });
+    }
+
+    fn fail(&self, e: io::Error) {
+        let err_msg = format!("Replay failed: {}", e);
+        eprintln!("[{}] {}", self.port.url, err_msg);
+        self.port.set_state(PortState::Error(err_msg));
+    }
+}
@@ -0,0 +1,335 @@
+//! Session files: a JSON snapshot of everything in `CaptureState` plus the plot layout.
+//!
+//! Unlike a `.tlrec` log, a session file is written in one go from the in-memory buffers, so it
+//! only covers the capture window. Reopening it registers one `session://<path>#<port>` port per
+//! saved port, whose data never changes, and hands the saved plot configs back to the front-end
+//! re-homed onto those ports.
+use crate::pipeline::manager::ProcessingManager;
+use crate::shared::{DataColumnId, PortState, SharedPlotConfig, UiDevice, ViewConfig};
+use crate::state::capture::{Buffer, CaptureState, SessionId, SessionMeta, StreamState};
+use crate::state::timebase;
+use crate::state::proxy_register::ProxyRegister;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+pub const URL_SCHEME: &str = "session://";
+pub const EXTENSION: &str = "json";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+struct SavedSession {
+    session_id: SessionId,
+    /// Host instants are stored relative to the earliest session in the file.
+    first_instant: f64,
+    last_instant: f64,
+    /// Unix seconds; absent in files from before the time base existed.
+    #[serde(default)]
+    first_host_time: Option<f64>,
+    first_device_time: f64,
+    last_device_time: f64,
+    offset: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedStream {
+    key: DataColumnId,
+    effective_sampling_rate: f64,
+    sessions: Vec<SavedSession>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedBuffer {
+    session_id: SessionId,
+    timestamps: Vec<f64>,
+    values: Vec<f64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SavedColumn {
+    key: DataColumnId,
+    buffers: Vec<SavedBuffer>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SessionFile {
+    version: u32,
+    /// Unix seconds.
+    saved_at: f64,
+    devices: Vec<UiDevice>,
+    streams: Vec<SavedStream>,
+    columns: Vec<SavedColumn>,
+    /// In the order the plots were created.
+    plots: Vec<SharedPlotConfig>,
+}
+
+/// A session file loaded back into `CaptureState`. Each saved port behaves like a port that is
+/// always streaming but never receives new samples.
+pub struct RestoredSession {
+    /// One per saved port, including virtual ones.
+    pub urls: Vec<String>,
+    pub path: PathBuf,
+    pub devices: Vec<UiDevice>,
+    pub plots: Vec<SharedPlotConfig>,
+}
+
+/// Writes the current capture buffers, devices and plot layout to `path`.
+pub fn save_current(
+    path: &Path,
+    capture: &CaptureState,
+    registry: &ProxyRegister,
+    manager: &Mutex<ProcessingManager>,
+) -> Result<(), String> {
+    let plots = manager.lock().unwrap().plot_configs();
+    SessionFile::capture(capture, registry.ui_devices(), plots).save(path)
+}
+
+impl SessionFile {
+    /// Snapshots every buffered column. Devices and plots that don't touch a buffered port are
+    /// left out.
+    pub fn capture(
+        capture: &CaptureState,
+        devices: Vec<UiDevice>,
+        plots: Vec<SharedPlotConfig>,
+    ) -> Self {
+        let origin = capture
+            .inner
+            .streams
+            .iter()
+            .flat_map(|s| {
+                s.session_meta
+                    .iter()
+                    .map(|m| m.first_instant)
+                    .collect::<Vec<_>>()
+            })
+            .min();
+        let since_origin = |instant: Instant| {
+            origin
+                .map(|o| instant.saturating_duration_since(o).as_secs_f64())
+                .unwrap_or_default()
+        };
+
+        // Offsets are computed through `streams`, so collect the keys first rather than holding
+        // a shard lock across the lookup.
+        let stream_keys: Vec<DataColumnId> =
+            capture.inner.streams.iter().map(|e| e.key().clone()).collect();
+        let streams: Vec<SavedStream> = stream_keys
+            .into_iter()
+            .filter_map(|key| {
+                let offsets = capture.offsets_for_stream(&key);
+                let entry = capture.inner.streams.get(&key)?;
+                let sessions = entry
+                    .session_meta
+                    .iter()
+                    .map(|m| SavedSession {
+                        session_id: *m.key(),
+                        first_instant: since_origin(m.first_instant),
+                        last_instant: since_origin(m.last_instant),
+                        first_host_time: Some(m.first_host_time),
+                        first_device_time: m.first_device_time,
+                        last_device_time: m.last_device_time,
+                        offset: offsets.as_ref().and_then(|o| o.get(m.key()).copied()),
+                    })
+                    .collect();
+                Some(SavedStream {
+                    effective_sampling_rate: entry.effective_sampling_rate,
+                    key,
+                    sessions,
+                })
+            })
+            .collect();
+
+        let columns: Vec<SavedColumn> = capture
+            .inner
+            .buffers
+            .iter()
+            .filter_map(|entry| {
+                let buffers: Vec<SavedBuffer> = entry
+                    .iter()
+                    .map(|b| {
+                        let ring = b.data.read().unwrap();
+                        SavedBuffer {
+                            session_id: *b.key(),
+                            timestamps: ring.timestamps().iter().copied().collect(),
+                            values: ring.values().iter().copied().collect(),
+                        }
+                    })
+                    .filter(|b| !b.timestamps.is_empty())
+                    .collect();
+                (!buffers.is_empty()).then(|| SavedColumn {
+                    key: entry.key().clone(),
+                    buffers,
+                })
+            })
+            .collect();
+
+        let ports: HashSet<&str> = columns.iter().map(|c| c.key.port_url.as_str()).collect();
+        let devices = devices
+            .into_iter()
+            .filter(|d| ports.contains(d.url.as_str()))
+            .collect();
+        let plots = plots
+            .into_iter()
+            .filter(|p| p.data_keys.iter().any(|k| ports.contains(k.port_url.as_str())))
+            .collect();
+
+        Self {
+            version: FORMAT_VERSION,
+            saved_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs_f64())
+                .unwrap_or_default(),
+            devices,
+            streams,
+            columns,
+            plots,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create '{}': {}", path.to_string_lossy(), e))?;
+        serde_json::to_writer(BufWriter::new(file), self)
+            .map_err(|e| format!("Failed to write session: {}", e))?;
+        println!(
+            "[Session] Saved {} columns and {} plots to {}",
+            self.columns.len(),
+            self.plots.len(),
+            path.to_string_lossy()
+        );
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let file = File::open(path)
+            .map_err(|e| format!("Failed to open '{}': {}", path.to_string_lossy(), e))?;
+        let session: Self = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Not a Trendline session file: {}", e))?;
+        if session.version > FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported session version {} (max {}).",
+                session.version, FORMAT_VERSION
+            ));
+        }
+        Ok(session)
+    }
+
+    /// Loads the saved buffers into `capture`, each port under its own URL for `path`, replacing
+    /// whatever an earlier restore of `path` left there.
+    pub fn restore(self, path: PathBuf, capture: &CaptureState) -> RestoredSession {
+        let rehome = |key: &DataColumnId| DataColumnId {
+            port_url: RestoredSession::url_for(&path, &key.port_url),
+            ..key.clone()
+        };
+
+        let prefix = RestoredSession::url_for(&path, "");
+        capture.inner.buffers.retain(|k, _| !k.port_url.starts_with(&prefix));
+        capture.inner.streams.retain(|k, _| !k.port_url.starts_with(&prefix));
+        capture.inner.active.retain(|k, _| !k.port_url.starts_with(&prefix));
+
+        // Rebuild host instants on a fresh origin; only their spacing matters for stitching.
+        let origin = Instant::now();
+        for stream in &self.streams {
+            let state = StreamState {
+                effective_sampling_rate: stream.effective_sampling_rate,
+                ..Default::default()
+            };
+            let mut offsets = HashMap::new();
+            for s in &stream.sessions {
+                let first_instant = origin + Duration::from_secs_f64(s.first_instant.max(0.0));
+                state.session_meta.insert(
+                    s.session_id,
+                    SessionMeta {
+                        first_instant,
+                        first_host_time: s
+                            .first_host_time
+                            .unwrap_or_else(|| timebase::instant_to_unix(first_instant)),
+                        last_instant: origin + Duration::from_secs_f64(s.last_instant.max(0.0)),
+                        first_device_time: s.first_device_time,
+                        last_device_time: s.last_device_time,
+                    },
+                );
+                if let Some(offset) = s.offset {
+                    offsets.insert(s.session_id, offset);
+                }
+            }
+            // Keep the offsets the plots were drawn with; recompute only if some are missing.
+            if offsets.len() == stream.sessions.len() {
+                *state.offsets_cache.lock().unwrap() = Some(Arc::new(offsets));
+            }
+            capture.inner.streams.insert(rehome(&stream.key), state);
+        }
+
+        for column in &self.columns {
+            let key = rehome(&column.key);
+            let sessions = capture.inner.buffers.entry(key.clone()).or_default();
+            for saved in &column.buffers {
+                let buffer = Buffer::from_samples(&saved.timestamps, &saved.values);
+                sessions.insert(saved.session_id, buffer);
+            }
+            drop(sessions);
+            capture.inner.active.insert(key, ());
+        }
+
+        let mut urls: Vec<String> = self
+            .columns
+            .iter()
+            .map(|c| RestoredSession::url_for(&path, &c.key.port_url))
+            .collect();
+        urls.sort();
+        urls.dedup();
+        let devices = self
+            .devices
+            .into_iter()
+            .map(|mut d| {
+                d.url = RestoredSession::url_for(&path, &d.url);
+                d.state = PortState::Streaming;
+                d
+            })
+            .collect();
+        let plots = self
+            .plots
+            .into_iter()
+            .map(|mut p| {
+                p.data_keys = p.data_keys.iter().map(rehome).collect();
+                // Columns the view reads besides the plotted ones.
+                match &mut p.view_config {
+                    ViewConfig::CrossSpectrum(config) => {
+                        config.reference = rehome(&config.reference)
+                    }
+                    ViewConfig::Trigger(config) => config.source = rehome(&config.source),
+                    ViewConfig::Xy(config) => config.x = rehome(&config.x),
+                    _ => {}
+                }
+                p
+            })
+            .collect();
+
+        println!(
+            "[Session] Restored {} columns from {}",
+            self.columns.len(),
+            path.to_string_lossy()
+        );
+        RestoredSession {
+            urls,
+            path,
+            devices,
+            plots,
+        }
+    }
+}
+
+impl RestoredSession {
+    /// The URL that the port `origin_url` saved in the file at `path` is restored under.
+    pub fn url_for(path: &Path, origin_url: &str) -> String {
+        format!("{}{}#{}", URL_SCHEME, path.to_string_lossy(), origin_url)
+    }
+
+    pub fn port_devices(&self, url: &str) -> Vec<UiDevice> {
+        self.devices.iter().filter(|d| d.url == url).cloned().collect()
+    }
+}
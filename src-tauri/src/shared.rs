@@ -22,7 +22,7 @@ use twinleaf::tio::proxy::{PortError, RpcError as LibProxyError};
 
 // FSM States -------------------------------------------------------------
 
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub enum PortState {
     Idle,
@@ -35,7 +35,7 @@ pub enum PortState {
 }
 
 // Device -----------------------------------------------------------------
-#[derive(Serialize, Clone, Debug, TS, PartialEq, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct DeviceMeta {
     pub serial_number: String,
@@ -58,7 +58,7 @@ impl From<LibDeviceMeta> for DeviceMeta {
 }
 
 // Stream -----------------------------------------------------------------
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct StreamMeta {
     pub stream_id: u8,
@@ -83,7 +83,7 @@ impl From<LibStreamMeta> for StreamMeta {
 }
 
 // Column -----------------------------------------------------------------
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct ColumnMeta {
     pub stream_id: u8,
@@ -108,7 +108,7 @@ impl From<LibColumnMeta> for ColumnMeta {
 }
 
 // Segment ----------------------------------------------------------------
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct SegmentMeta {
     pub stream_id: u8,
@@ -144,7 +144,7 @@ impl From<LibSegmentMeta> for SegmentMeta {
 
 // RPC -----------------------------------------------------------------
 // TODO: ASK GB TO MOVE IT INTO TWINLEAF LIBRARY
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct RpcMeta {
     pub name: String,
@@ -155,7 +155,7 @@ pub struct RpcMeta {
     pub writable: bool,
     pub persistent: bool,
     pub unknown: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     #[ts(type = "any")]
     pub value: Option<Value>,
 }
@@ -290,7 +290,7 @@ impl From<LibProxyError> for RpcError {
 }
 
 // Misc. Metadata -------------------------------------------------------------
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub enum MetadataEpoch {
     Invalid,
@@ -312,7 +312,19 @@ impl From<LibMetadataEpoch> for MetadataEpoch {
     }
 }
 
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+impl From<&MetadataEpoch> for LibMetadataEpoch {
+    fn from(e: &MetadataEpoch) -> Self {
+        match e {
+            MetadataEpoch::Invalid => Self::Invalid,
+            MetadataEpoch::Zero => Self::Zero,
+            MetadataEpoch::Systime => Self::Systime,
+            MetadataEpoch::Unix => Self::Unix,
+            MetadataEpoch::Unknown(v) => Self::Unknown(*v),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub enum MetadataFilter {
     Unfiltered,
@@ -332,8 +344,19 @@ impl From<LibMetadataFilter> for MetadataFilter {
     }
 }
 
+impl From<&MetadataFilter> for LibMetadataFilter {
+    fn from(f: &MetadataFilter) -> Self {
+        match f {
+            MetadataFilter::Unfiltered => Self::Unfiltered,
+            MetadataFilter::FirstOrderCascade1 => Self::FirstOrderCascade1,
+            MetadataFilter::FirstOrderCascade2 => Self::FirstOrderCascade2,
+            MetadataFilter::Unknown(v) => Self::Unknown(*v),
+        }
+    }
+}
+
 // Frontend structs ----------------------------------------------------------
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct UiStream {
     pub meta: StreamMeta,
@@ -342,7 +365,7 @@ pub struct UiStream {
     pub effective_sampling_rate: f64,
 }
 
-#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct UiDevice {
     pub url: String,
@@ -370,11 +393,142 @@ pub enum DetrendMethod {
     Quadratic, // Remove a quadratic trend (y = at^2 + bt + c)
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum FilterDesign {
+    #[default]
+    Butterworth, // Biquad cascade; notches are second-order IIR sections
+    Fir,         // Windowed-sinc FIR, linear phase
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum FilterKind {
+    LowPass { cutoff_hz: f64 },
+    HighPass { cutoff_hz: f64 },
+    BandPass { low_hz: f64, high_hz: f64 },
+    // Notches `frequency_hz` and its multiples up to `harmonics` (or Nyquist); `q` = f / bandwidth
+    Notch { frequency_hz: f64, harmonics: u32, q: f64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct FilterConfig {
+    pub kind: FilterKind,
+    pub design: FilterDesign,
+    /// Butterworth order, or FIR tap count. Unused for IIR notches.
+    pub order: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum WindowFunction {
+    #[default]
+    Hann,
+    BlackmanHarris, // 4-term, -92 dB sidelobes
+    FlatTop,        // Wide main lobe, but peak amplitudes are accurate to ~0.01 dB
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum SpectrumUnits {
+    Psd, // units²/Hz
+    #[default]
+    Asd, // units/√Hz
+    // 20·log10(ASD / reference), `reference` in units/√Hz
+    Db { reference: f64 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum FrameAveraging {
+    #[default]
+    None,
+    Linear { frames: u32 },      // Mean PSD of the last `frames` spectra
+    Exponential { frames: u32 }, // Each new spectrum weighted 1/frames
+}
+
+/// What to do when an FFT window has dropped samples or NaNs in it.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum GapHandling {
+    #[default]
+    Auto, // Interpolate short gaps, else split into segments, else Lomb-Scargle
+    Interpolate,
+    Segments,
+    LombScargle,
+}
+
+/// How a spectrum was actually estimated.
+#[derive(Serialize, Clone, Copy, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum SpectralMethod {
+    Contiguous, // No gaps; plain Welch
+    Interpolated,
+    Segmented, // Welch over segments that avoid the gaps
+    LombScargle,
+}
+
+/// The estimator an FFT pipeline used for its latest spectrum, and what it had to work around.
+#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct SpectrumStatus {
+    pub key: DataColumnId,
+    pub method: SpectralMethod,
+    /// Samples in the window that were dropped or NaN.
+    pub missing_samples: u32,
+    pub gaps: u32,
+    /// Welch segments averaged; 0 for Lomb-Scargle.
+    pub segments: u32,
+    /// Why there is no spectrum, e.g. a window too long for Lomb-Scargle.
+    pub error: Option<String>,
+}
+
+/// Welch estimator settings. Densities are normalised by the window's equivalent noise
+/// bandwidth, so a white noise floor reads the same whichever window is used.
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct PsdConfig {
+    pub window: WindowFunction,
+    /// Samples per segment; 0 sizes segments so `averages` of them span the FFT window.
+    pub segment_length: u32,
+    /// Fraction of each segment shared with the next, 0 to 0.95.
+    pub overlap: f64,
+    /// Segments averaged per spectrum, the most recent first; 0 uses every segment that fits
+    /// (or 8 when `segment_length` is 0 too).
+    pub averages: u32,
+    pub units: SpectrumUnits,
+    /// Averaging of successive spectra, applied to the PSD before unit conversion.
+    pub frame_averaging: FrameAveraging,
+    #[serde(default)]
+    pub gap_handling: GapHandling,
+}
+
+impl Default for PsdConfig {
+    fn default() -> Self {
+        Self {
+            window: WindowFunction::Hann,
+            segment_length: 0,
+            overlap: 0.5,
+            averages: 0,
+            units: SpectrumUnits::Asd,
+            frame_averaging: FrameAveraging::None,
+            gap_handling: GapHandling::Auto,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub enum ViewConfig {
     Timeseries(TimeseriesConfig),
     Fft(FftConfig),
+    Spectrogram(SpectrogramConfig),
+    CrossSpectrum(CrossSpectrumConfig),
+    Trigger(TriggerConfig),
+    AllanDeviation(AllanConfig),
+    Histogram(HistogramConfig),
+    Xy(XyConfig),
 }
 #[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
@@ -382,12 +536,194 @@ pub struct TimeseriesConfig {
     pub decimation_method: DecimationMethod,
     pub window_seconds: f64,
     pub resolution_multiplier: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub filter: Option<FilterConfig>,
 }
 #[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct FftConfig {
     pub window_seconds: f64,
     pub detrend_method: DetrendMethod,
+    #[serde(default)]
+    pub psd: PsdConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub filter: Option<FilterConfig>,
+}
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum CrossQuantity {
+    #[default]
+    Coherence, // |Pxy|² / (Pxx·Pyy), 0 to 1
+    CrossPsd,          // |Pxy|, reference units × column units / Hz
+    TransferMagnitude, // |H1| = |Pxy| / Pxx
+    TransferPhase,     // arg(H1), degrees
+    IncoherentAsd,     // √((1 − γ²)·Pyy): the column's noise not shared with the reference
+}
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct CrossSpectrumConfig {
+    /// The input (x) of every pair; each plotted column is an output (y).
+    pub reference: DataColumnId,
+    pub window_seconds: f64,
+    pub detrend_method: DetrendMethod,
+    /// Window, segmenting and frame averaging; `units` and `gap_handling` are not used (gaps
+    /// are always interpolated).
+    #[serde(default)]
+    pub psd: PsdConfig,
+    pub quantity: CrossQuantity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub filter: Option<FilterConfig>,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct SpectrogramConfig {
+    /// Length of each PSD frame; frames overlap by half.
+    pub window_seconds: f64,
+    /// How far back the waterfall reaches.
+    pub history_seconds: f64,
+    pub detrend_method: DetrendMethod,
+    /// Window, segmenting and gap handling; `units` and `frame_averaging` are not used (frames
+    /// are always ASD, one per half window).
+    #[serde(default)]
+    pub psd: PsdConfig,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub filter: Option<FilterConfig>,
+}
+/// What fires a trigger, relative to `TriggerConfig::level`.
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum TriggerCondition {
+    #[default]
+    RisingEdge, // Crosses `level` going up
+    FallingEdge,
+    Level, // Crosses `level` either way
+    // Leaves the band between `level` and `upper`
+    Window { upper: f64 },
+    // Changes faster than `rate` units/s between two samples; negative for falling slopes
+    Slope { rate: f64 },
+    // A pulse above `level` (below it unless `positive`) lasting `min_seconds` to `max_seconds`;
+    // fires where the pulse ends
+    PulseWidth { positive: bool, min_seconds: f64, max_seconds: Option<f64> },
+}
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum TriggerMode {
+    #[default]
+    Auto, // Free-runs when nothing has triggered for a frame's length
+    Normal, // Holds the last captured frame until the next trigger
+    Single, // Captures one frame, then waits to be re-armed
+}
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct TriggerConfig {
+    /// Column the condition is evaluated on; it need not be plotted.
+    pub source: DataColumnId,
+    pub condition: TriggerCondition,
+    pub level: f64,
+    /// Captured before and after the trigger; frames are timed relative to it.
+    pub pre_trigger_seconds: f64,
+    pub post_trigger_seconds: f64,
+    pub mode: TriggerMode,
+    /// Triggered segments averaged into each frame, most recent first; 0 or 1 shows every
+    /// capture as it is. Averaged frames come with a standard deviation series per column.
+    #[serde(default)]
+    pub averages: u32,
+}
+#[derive(Serialize, Clone, Copy, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum TriggerState {
+    Armed,     // Waiting for a trigger
+    Capturing, // Triggered; waiting for the post-trigger data
+    Stopped,   // Single mode, after its frame
+}
+#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct TriggerStatus {
+    pub state: TriggerState,
+    /// Frames captured on a trigger since the plot was configured or re-armed.
+    pub triggers: u32,
+    /// Unified time of the trigger of the frame on screen, `None` for a free-running frame.
+    pub last_trigger_time: Option<f64>,
+    /// Segments in the running average; 0 when not averaging.
+    pub averaged: u32,
+}
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum AllanDeviation {
+    #[default]
+    Overlapping, // σ_y(τ), overlapping Allan deviation
+    Modified,    // Mod σ_y(τ); separates white from flicker phase noise
+    Time,        // σ_x(τ) = τ/√3 · Mod σ_y(τ), in column units × seconds
+}
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct AllanConfig {
+    /// History the pipeline keeps of a live column, beyond the capture buffer. Columns of an
+    /// open recording are analysed over the whole log instead.
+    pub window_seconds: f64,
+    pub deviation: AllanDeviation,
+    /// Averaging times per decade of τ, log-spaced from one sample period.
+    pub taus_per_decade: u32,
+}
+#[derive(Serialize, Clone, Copy, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum AllanSource {
+    Live,      // Accumulated from capture since the plot was configured
+    Recording, // Read from the `.tlrec` log behind a `file://` port
+}
+#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct AllanStatus {
+    pub key: DataColumnId,
+    pub source: AllanSource,
+    /// Length of the data analysed, gaps included.
+    pub span_seconds: f64,
+    /// Shortest τ: the sample period, or a multiple of it once long histories are pre-averaged.
+    pub tau0: f64,
+    /// Sample periods that were dropped or NaN.
+    pub missing_samples: u32,
+}
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum HistogramBins {
+    #[default]
+    Auto, // Freedman-Diaconis width; whole codes for integer-valued columns
+    Fixed { bins: u32 },
+}
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct HistogramConfig {
+    pub window_seconds: f64,
+    pub bins: HistogramBins,
+    /// Adds a Gaussian with the window's mean and standard deviation, scaled to the counts, as
+    /// a second series per column.
+    pub fit_gaussian: bool,
+}
+#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct HistogramStatus {
+    pub key: DataColumnId,
+    pub bins: u32,
+    pub bin_width: f64,
+    /// Statistics of the binned values, which the Gaussian fit uses.
+    pub window: StatisticSet,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct XyConfig {
+    /// The horizontal column; each plotted column is drawn against it.
+    pub x: DataColumnId,
+    /// Length of the trace kept on screen.
+    pub persistence_seconds: f64,
+    /// Fades the trace with age over `persistence_seconds`; drawn by the front-end.
+    pub decay: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub filter: Option<FilterConfig>,
 }
 #[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
@@ -399,6 +735,21 @@ pub struct SharedPlotConfig {
 }
 
 // Plot Structs -----------------------------------------------------------------
+/// Waterfall frames of one column, sent on the plot's spectrogram channel.
+#[derive(Serialize, Clone, Debug, TS)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct SpectrogramData {
+    pub key: DataColumnId,
+    /// Whether these frames replace everything sent before instead of appending to it.
+    pub reset: bool,
+    /// Unified time at the end of each frame's window, oldest first.
+    pub timestamps: Vec<f64>,
+    /// Bin centre frequencies (DC excluded), shared by every frame.
+    pub frequencies: Vec<f64>,
+    /// ASD values, row-major: one row of `frequencies.len()` per timestamp.
+    pub values: Vec<f64>,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../../src/lib/bindings/")]
 pub struct DataColumnId {
@@ -410,7 +761,15 @@ pub struct DataColumnId {
     pub column_index: usize,
 }
 
+/// Reserved `port_url` scheme of columns computed by the app rather than read from a device.
+pub const VIRTUAL_URL_SCHEME: &str = "virtual://";
+
 impl DataColumnId {
+    /// Whether this column is written by the app (see `CaptureState::register_virtual_column`).
+    pub fn is_virtual(&self) -> bool {
+        self.port_url.starts_with(VIRTUAL_URL_SCHEME)
+    }
+
     pub fn device_key(&self) -> Self {
         Self {
             stream_id: 0,
@@ -498,3 +857,181 @@ pub struct ColumnStatistics {
     pub persistent_health: HealthSet,
     pub window_health: HealthSet,
 }
+
+// Recording ------------------------------------------------------------------
+#[derive(Clone, Debug, Serialize, TS, Default, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct RecordingStatus {
+    pub is_recording: bool,
+    pub path: Option<String>,
+    pub current_file: Option<String>,
+    pub files_written: usize,
+    pub bytes_written: u64,
+    pub samples_written: u64,
+    /// Samples that never reached the log because the writer fell behind the capture.
+    pub samples_dropped: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, TS, Default, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct ReplayStatus {
+    pub url: String,
+    pub path: String,
+    pub is_playing: bool,
+    pub speed: f64,
+    /// Seconds since the first sample in the recording.
+    pub position: f64,
+    pub duration: f64,
+}
+
+#[derive(Clone, Debug, Serialize, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct SessionRestored {
+    /// One `session://` port per port saved in the file.
+    pub urls: Vec<String>,
+    pub path: String,
+    /// Saved plot configs, with their keys re-homed onto `urls`.
+    pub plots: Vec<SharedPlotConfig>,
+}
+
+#[derive(Clone, Debug, Serialize, TS, Default, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct TcpProxyStatus {
+    pub port_url: String,
+    /// `host:port` clients connect to, while the proxy is running.
+    pub address: Option<String>,
+    pub clients: usize,
+    pub error: Option<String>,
+}
+
+// Math channels --------------------------------------------------------------
+/// Binds a variable of a math expression to a column.
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct MathInput {
+    pub variable: String,
+    pub key: DataColumnId,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct MathChannelConfig {
+    pub name: String,
+    /// e.g. `sqrt(Bx^2 + By^2 + Bz^2)`, over the variables in `inputs`.
+    pub expression: String,
+    pub inputs: Vec<MathInput>,
+    #[serde(default)]
+    pub units: String,
+    /// Applied to the expression's output before it is written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub filter: Option<FilterConfig>,
+}
+
+#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct MathChannelInfo {
+    /// The virtual column the channel writes to, on the `virtual://math` port.
+    pub key: DataColumnId,
+    pub config: MathChannelConfig,
+}
+
+// Lock-in amplifiers ---------------------------------------------------------
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum LockInReference {
+    // Internal oscillator, at phase 0 on the first signal sample
+    Frequency { hz: f64 },
+    // Rising zero crossings of another column (e.g. the drive of a modulated field) are phase 0
+    Column { key: DataColumnId },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, TS, PartialEq, Default)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub enum LockInOutput {
+    #[default]
+    Cartesian, // In-phase X and quadrature Y
+    Polar,     // Magnitude R and phase θ in degrees
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct LockInConfig {
+    pub name: String,
+    pub signal: DataColumnId,
+    pub reference: LockInReference,
+    /// Demodulates at this multiple of the reference frequency.
+    pub harmonic: u32,
+    /// Added to the reference phase, so a signal leading the reference by this much reads as X
+    /// only.
+    pub phase_deg: f64,
+    /// Time constant of each low-pass stage, in seconds.
+    pub time_constant: f64,
+    /// Number of first-order low-pass stages, 1 to 4 (6 dB/octave each).
+    pub filter_order: u32,
+    pub output: LockInOutput,
+    /// Units of the signal, for X, Y and R.
+    #[serde(default)]
+    pub units: String,
+}
+
+#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct LockInInfo {
+    /// The two virtual columns the lock-in writes to (X and Y, or R and θ), on the
+    /// `virtual://lockin` port.
+    pub keys: Vec<DataColumnId>,
+    pub config: LockInConfig,
+}
+
+// Capture retention ----------------------------------------------------------
+/// How long one stream's samples are kept, in place of `RetentionConfig::default_seconds`.
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct StreamRetention {
+    /// Any column of the stream; the column index is ignored.
+    pub stream: DataColumnId,
+    pub seconds: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct RetentionConfig {
+    pub default_seconds: f64,
+    pub streams: Vec<StreamRetention>,
+    /// Ceiling on the estimated size of the active columns' buffers. When the retention asks for
+    /// more, every stream's window shrinks by the same factor.
+    pub memory_budget_mb: u32,
+    /// Keep samples evicted from memory on disk for this many more seconds; none discards them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[ts(optional)]
+    pub spill_seconds: Option<f64>,
+}
+
+#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct StreamMemory {
+    /// Column 0 of the stream.
+    pub stream: DataColumnId,
+    pub columns: u32,
+    pub sample_rate: f64,
+    /// From the config, before scaling to the budget.
+    pub requested_seconds: f64,
+    pub retained_seconds: f64,
+    /// Allocated by the stream's buffers, across all sessions.
+    pub bytes: f64,
+    /// Written to the spill files of the stream's buffers.
+    pub spilled_bytes: f64,
+}
+
+#[derive(Serialize, Clone, Debug, TS, PartialEq)]
+#[ts(export, export_to = "../../src/lib/bindings/")]
+pub struct CaptureMemoryStatus {
+    pub config: RetentionConfig,
+    /// Applied to every stream's retention to fit the budget; 1 when it fits.
+    pub scale: f64,
+    pub used_bytes: f64,
+    pub spilled_bytes: f64,
+    pub streams: Vec<StreamMemory>,
+}
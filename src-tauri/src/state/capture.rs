@@ -1,10 +1,19 @@
-use crate::shared::{DataColumnId, PlotData, Point};
+use crate::recording::{replay, session};
+use crate::shared::{
+    CaptureMemoryStatus, DataColumnId, MetadataEpoch, PlotData, Point, RetentionConfig,
+    StreamMemory,
+};
+use crate::state::pyramid::{self, TilePyramid};
+use crate::state::ring::SampleRing;
+use crate::state::spill::{self, SpillStore};
+use crate::state::timebase::{self, TimeReference};
 use crossbeam::channel::{bounded, Receiver, Sender};
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use rayon::prelude::*;
-use std::collections::{BTreeMap, HashMap};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 use std::time::Instant;
@@ -13,35 +22,54 @@ pub type SessionId = u32;
 pub type DeviceTime = f64;
 pub type UnifiedTime = f64;
 pub type TimeOffset = f64;
+/// Each column's buffers, one per session.
+type ColumnBuffers = Vec<(DataColumnId, Vec<Buffer>)>;
 
 #[derive(Clone)]
 
 pub struct Buffer {
-    pub data: Arc<RwLock<BTreeMap<u64, f64>>>,
+    pub data: Arc<RwLock<SampleRing>>,
     pub cap: Arc<AtomicUsize>,
+    /// Min/max/mean tiles of `data`; lock after `data`.
+    tiles: Arc<RwLock<TilePyramid>>,
+    /// Where evicted samples go, if spilling is on; lock after `tiles`.
+    spill: Arc<Mutex<Option<SpillStore>>>,
 }
 
 impl Buffer {
     fn new(cap: usize) -> Self {
         Self {
-            data: Arc::new(RwLock::new(BTreeMap::new())),
+            data: Arc::new(RwLock::new(SampleRing::default())),
             cap: Arc::new(AtomicUsize::new(cap)),
+            tiles: Arc::new(RwLock::new(TilePyramid::default())),
+            spill: Arc::new(Mutex::new(None)),
         }
     }
 
+    /// Builds a buffer from already-sorted samples, sized to hold all of them.
+    pub fn from_samples(timestamps: &[f64], values: &[f64]) -> Self {
+        let buf = Self::new(timestamps.len().max(100));
+        {
+            let mut ring = buf.data.write().unwrap();
+            let mut tiles = buf.tiles.write().unwrap();
+            *ring = SampleRing::with_capacity(timestamps.len());
+            for (t, y) in timestamps.iter().zip(values) {
+                ring.push(*t, *y);
+                tiles.push(Point { x: *t, y: *y });
+            }
+        }
+        buf
+    }
+
     fn push_many(&self, pts: &[Point]) {
-        let mut map = self.data.write().unwrap();
+        let mut ring = self.data.write().unwrap();
+        let mut tiles = self.tiles.write().unwrap();
         for p in pts {
-            map.insert(p.x.to_bits(), p.y);
+            ring.push(p.x, p.y);
+            tiles.push(*p);
         }
         let cap = self.cap.load(Ordering::Relaxed);
-        while map.len() > cap {
-            if let Some(oldest) = map.keys().next().copied() {
-                map.remove(&oldest);
-            } else {
-                break;
-            }
-        }
+        self.trim(&mut ring, &mut tiles, cap);
     }
 
     pub fn set_capacity(&self, new_cap: usize) {
@@ -49,12 +77,118 @@ impl Buffer {
         if old == new_cap {
             return;
         }
-        let mut map = self.data.write().unwrap();
-        while map.len() > new_cap {
-            if let Some(oldest) = map.keys().next().copied() {
-                map.remove(&oldest);
-            } else {
-                break;
+        let mut ring = self.data.write().unwrap();
+        let mut tiles = self.tiles.write().unwrap();
+        self.trim(&mut ring, &mut tiles, new_cap);
+        if new_cap < old {
+            ring.shrink_to(new_cap);
+        }
+    }
+
+    /// Heap bytes held by the samples and their tiles.
+    pub fn allocated_bytes(&self) -> usize {
+        let ring = self.data.read().unwrap().allocated_bytes();
+        ring + self.tiles.read().unwrap().allocated_bytes()
+    }
+
+    /// Starts, retunes or (with `None`) stops spilling evicted samples to disk, keeping them
+    /// for `seconds` behind the newest one spilled. Stopping deletes what was spilled.
+    pub fn set_spill(&self, seconds: Option<f64>) {
+        let mut spill = self.spill.lock().unwrap();
+        match (spill.as_mut(), seconds) {
+            (Some(store), Some(seconds)) => store.set_retention(seconds),
+            (None, Some(seconds)) => *spill = Some(SpillStore::new(seconds)),
+            (_, None) => *spill = None,
+        }
+    }
+
+    /// Bytes in the spill files.
+    pub fn spilled_bytes(&self) -> u64 {
+        self.spill.lock().unwrap().as_ref().map_or(0, |s| s.bytes())
+    }
+
+    /// The spilled samples with `lo <= t <= hi`. Reads the files without holding any lock.
+    fn spilled(&self, lo: f64, hi: f64) -> Vec<Point> {
+        let segments = match self.spill.lock().unwrap().as_mut() {
+            Some(store) => store.segments(),
+            None => return Vec::new(),
+        };
+        spill::read_spilled(&segments, lo, hi)
+    }
+
+    fn trim(&self, ring: &mut SampleRing, tiles: &mut TilePyramid, cap: usize) {
+        if ring.len() <= cap {
+            return;
+        }
+        match self.spill.lock().unwrap().as_mut() {
+            Some(store) => {
+                let mut evicted = Vec::with_capacity(ring.len() - cap);
+                ring.evict_into(cap, &mut evicted);
+                store.append(&evicted);
+            }
+            None => ring.evict_to(cap),
+        }
+        if let Some(oldest) = ring.first_time() {
+            tiles.trim(oldest);
+        }
+    }
+
+    /// Appends the min/max envelope of the samples in `range` to `out`, in buckets of about
+    /// `samples_per_bucket` samples, shifted by `offset`.
+    fn envelope(
+        &self,
+        ring: &SampleRing,
+        range: Range<usize>,
+        samples_per_bucket: usize,
+        offset: f64,
+        out: &mut Vec<Point>,
+    ) {
+        if self.tiles.read().unwrap().is_stale() {
+            self.tiles.write().unwrap().rebuild(ring);
+        }
+        let tiles = self.tiles.read().unwrap();
+        tiles.envelope(ring, range, samples_per_bucket, offset, out);
+    }
+}
+
+/// What `read_across_sessions` returns for each session buffer.
+#[derive(Clone, Copy)]
+enum ReadMode {
+    Raw,
+    /// Min/max envelope in buckets of about this many samples.
+    Envelope(usize),
+}
+
+impl ReadMode {
+    /// Appends the samples in `range` of `buffer`'s locked `ring`, shifted by `offset`.
+    fn read(
+        self,
+        buffer: &Buffer,
+        ring: &SampleRing,
+        range: Range<usize>,
+        offset: TimeOffset,
+        out: &mut Vec<Point>,
+    ) {
+        match self {
+            ReadMode::Raw => out.extend(ring.points(range).map(|p| Point {
+                x: p.x + offset,
+                y: p.y,
+            })),
+            ReadMode::Envelope(samples_per_bucket) => {
+                buffer.envelope(ring, range, samples_per_bucket, offset, out)
+            }
+        }
+    }
+
+    /// Appends spilled samples, shifted by `offset`.
+    fn read_spilled(self, points: &[Point], offset: TimeOffset, out: &mut Vec<Point>) {
+        match self {
+            ReadMode::Raw => out.extend(points.iter().map(|p| Point {
+                x: p.x + offset,
+                y: p.y,
+            })),
+            ReadMode::Envelope(samples_per_bucket) => {
+                pyramid::envelope_of(points, samples_per_bucket, offset, out)
             }
         }
     }
@@ -63,6 +197,8 @@ impl Buffer {
 #[derive(Clone, Debug)]
 pub struct SessionMeta {
     pub first_instant: Instant,
+    /// Host wall-clock time (Unix seconds) of the first batch.
+    pub first_host_time: f64,
     pub last_instant: Instant,
     pub first_device_time: DeviceTime,
     pub last_device_time: DeviceTime,
@@ -72,6 +208,7 @@ pub struct SessionMeta {
 pub struct StreamState {
     pub effective_sampling_rate: f64,
     pub session_meta: DashMap<SessionId, SessionMeta>,
+    pub time_reference: TimeReference,
     pub offsets_cache: Mutex<Option<Arc<HashMap<SessionId, TimeOffset>>>>,
 }
 
@@ -79,7 +216,10 @@ pub struct StreamState {
 pub struct BatchedData {
     pub key: DataColumnId,
     pub session_id: SessionId,
+    /// In unified time, like everything read back from `CaptureState`.
     pub points: Arc<Vec<Point>>,
+    /// Added to the device timestamps of `points`; subtract it to get them back.
+    pub offset: TimeOffset,
     pub sample_numbers: Arc<Vec<u32>>,
     pub t_min: f64,
     pub t_max: f64,
@@ -92,6 +232,28 @@ pub struct Inner {
     pub active: DashMap<DataColumnId, ()>,
     pub command_tx: Sender<CaptureCommand>,
     pub subscribers: DashMap<DataColumnId, Vec<(usize, Sender<Arc<BatchedData>>)>>,
+    /// Receive every batch of every active column (e.g. the recorder), keyed by tap id, with
+    /// the count of samples in batches the tap was too full to take.
+    pub taps: DashMap<usize, (Sender<Arc<BatchedData>>, Arc<AtomicU64>)>,
+    /// Source of `Subscribe` ids, shared by everything that subscribes to columns.
+    next_subscriber_id: AtomicUsize,
+    retention: RwLock<Retention>,
+}
+
+/// The retention settings, and the factor that fits them into the memory budget.
+struct Retention {
+    config: RetentionConfig,
+    scale: f64,
+}
+
+impl Retention {
+    fn requested_seconds(&self, stream_key: &DataColumnId) -> f64 {
+        self.config
+            .streams
+            .iter()
+            .find(|s| s.stream.stream_key() == *stream_key)
+            .map_or(self.config.default_seconds, |s| s.seconds)
+    }
 }
 #[derive(Debug)]
 pub enum CaptureCommand {
@@ -101,11 +263,18 @@ pub enum CaptureCommand {
         sample_numbers: Vec<u32>,
         session_id: SessionId,
         instant: Instant,
+        /// Wall-clock capture time when it isn't `instant` (replayed batches).
+        host_time: Option<f64>,
     },
     UpdateSampleRate {
         key: DataColumnId,
         rate: f64,
     },
+    /// Segment epoch of a stream; decides how its sessions are placed on the unified axis.
+    UpdateTimeBase {
+        key: DataColumnId,
+        epoch: MetadataEpoch,
+    },
     SetActiveColumns {
         port_url: String,
         keys_for_port: Vec<DataColumnId>,
@@ -128,6 +297,69 @@ pub enum CaptureCommand {
         key: DataColumnId,
         id: usize,
     },
+    Tap {
+        id: usize,
+        tx: Sender<Arc<BatchedData>>,
+        /// Incremented by the number of samples in each batch `tx` had no room for.
+        dropped: Arc<AtomicU64>,
+    },
+    Untap {
+        id: usize,
+    },
+    /// Answered once every command queued before it has been applied.
+    Flush {
+        ack: Sender<()>,
+    },
+    /// Creates a virtual column, or clears it if it exists, and makes it active.
+    RegisterVirtual {
+        key: DataColumnId,
+        rate: f64,
+    },
+    /// Deactivates a virtual column and drops its samples.
+    UnregisterVirtual {
+        key: DataColumnId,
+    },
+}
+
+/// Feeds a virtual column. Points are in unified time and written as-is, in a single session;
+/// columns of one virtual stream (a lock-in's X and Y) each get a writer and write the same
+/// timestamps.
+pub struct VirtualColumnWriter {
+    key: DataColumnId,
+    tx: Sender<CaptureCommand>,
+    next_sample_number: u32,
+}
+
+impl VirtualColumnWriter {
+    pub fn key(&self) -> &DataColumnId {
+        &self.key
+    }
+
+    pub fn write(&mut self, points: Vec<Point>) {
+        if points.is_empty() {
+            return;
+        }
+        let first = self.next_sample_number;
+        self.next_sample_number = first.wrapping_add(points.len() as u32);
+        let sample_numbers = (0..points.len() as u32)
+            .map(|i| first.wrapping_add(i))
+            .collect();
+        let _ = self.tx.send(CaptureCommand::InsertBatch {
+            key: self.key.clone(),
+            points,
+            sample_numbers,
+            session_id: 0,
+            instant: Instant::now(),
+            host_time: None,
+        });
+    }
+
+    pub fn set_sample_rate(&self, rate: f64) {
+        let _ = self.tx.send(CaptureCommand::UpdateSampleRate {
+            key: self.key.clone(),
+            rate,
+        });
+    }
 }
 
 #[derive(Clone)]
@@ -136,10 +368,16 @@ pub struct CaptureState {
 }
 
 impl CaptureState {
-    const BUFFER_WINDOW_SECONDS: f64 = 180.0;
+    /// Default retention per stream.
+    pub const BUFFER_WINDOW_SECONDS: f64 = 180.0;
+    /// Assumed until a stream reports its rate.
     const DEFAULT_SAMPLING_RATE: f64 = 1000.0;
+    const DEFAULT_MEMORY_BUDGET_MB: u32 = 1024;
+    /// Samples and tiles; what the memory budget is estimated with.
+    const BYTES_PER_SAMPLE: usize = SampleRing::BYTES_PER_SAMPLE + TilePyramid::BYTES_PER_SAMPLE;
 
     pub fn new() -> Self {
+        spill::remove_stale_spill_dirs();
         let (command_tx, command_rx) = bounded::<CaptureCommand>(8_192);
         let inner = Arc::new(Inner {
             buffers: DashMap::new(),
@@ -148,6 +386,17 @@ impl CaptureState {
             active: DashMap::new(),
             command_tx,
             subscribers: DashMap::new(),
+            taps: DashMap::new(),
+            next_subscriber_id: AtomicUsize::new(1),
+            retention: RwLock::new(Retention {
+                config: RetentionConfig {
+                    default_seconds: Self::BUFFER_WINDOW_SECONDS,
+                    streams: Vec::new(),
+                    memory_budget_mb: Self::DEFAULT_MEMORY_BUDGET_MB,
+                    spill_seconds: None,
+                },
+                scale: 1.0,
+            }),
         });
 
         let consumer_inner = inner.clone();
@@ -159,6 +408,56 @@ impl CaptureState {
         Self { inner }
     }
 
+    /// Blocks until every command already queued has been applied.
+    pub fn flush(&self) {
+        let (ack, done) = bounded(1);
+        if self
+            .inner
+            .command_tx
+            .send(CaptureCommand::Flush { ack })
+            .is_ok()
+        {
+            let _ = done.recv();
+        }
+    }
+
+    /// A fresh id for `CaptureCommand::Subscribe`.
+    pub fn next_subscriber_id(&self) -> usize {
+        self.inner.next_subscriber_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Starts a virtual column under `key` (whose `port_url` must use `VIRTUAL_URL_SCHEME`). Once
+    /// registered it is hydrated, snapshotted, exported and subscribed to like a device column.
+    pub fn register_virtual_column(
+        &self,
+        key: DataColumnId,
+        rate: f64,
+    ) -> Result<VirtualColumnWriter, String> {
+        if !key.is_virtual() {
+            return Err(format!("{} is not a virtual column URL.", key.port_url));
+        }
+        self.inner
+            .command_tx
+            .send(CaptureCommand::RegisterVirtual {
+                key: key.clone(),
+                rate,
+            })
+            .map_err(|e| e.to_string())?;
+        Ok(VirtualColumnWriter {
+            key,
+            tx: self.inner.command_tx.clone(),
+            next_sample_number: 0,
+        })
+    }
+
+    /// Batches already sent by the column's writer are applied first, so stop it before calling.
+    pub fn unregister_virtual_column(&self, key: &DataColumnId) {
+        let _ = self
+            .inner
+            .command_tx
+            .send(CaptureCommand::UnregisterVirtual { key: key.clone() });
+    }
+
     pub fn get_latest_unified_timestamp(&self, keys: &[DataColumnId]) -> Option<UnifiedTime> {
         let unique_stream_keys: std::collections::HashSet<_> =
             keys.iter().map(|k| k.stream_key()).collect();
@@ -182,12 +481,7 @@ impl CaptureState {
                 let binding = self.inner.buffers.get(key)?;
                 let buffer = binding.get(latest_session_id)?;
 
-                let last_raw_ts_bits = {
-                    let map = buffer.data.read().unwrap();
-                    map.iter().next_back().map(|(k, _)| *k)
-                }?;
-
-                let last_raw_ts = DeviceTime::from_bits(last_raw_ts_bits);
+                let last_raw_ts: DeviceTime = buffer.data.read().unwrap().last_time()?;
                 let unified_ts = last_raw_ts + max_offset;
                 Some(unified_ts)
             })
@@ -199,6 +493,37 @@ impl CaptureState {
         keys: &[DataColumnId],
         start_time: UnifiedTime,
         end_time: UnifiedTime,
+    ) -> Vec<Vec<Point>> {
+        self.read_across_sessions(keys, start_time, end_time, ReadMode::Raw)
+    }
+
+    /// Like `get_data_across_sessions_for_keys`, but decimated: each run of about
+    /// `samples_per_bucket` samples becomes its minimum and maximum, in time order. Served from
+    /// the buffers' tiles, so the cost follows the number of buckets rather than of samples; the
+    /// part of the range only on disk is bucketed from its samples.
+    pub fn get_envelope_across_sessions_for_keys(
+        &self,
+        keys: &[DataColumnId],
+        start_time: UnifiedTime,
+        end_time: UnifiedTime,
+        samples_per_bucket: usize,
+    ) -> Vec<Vec<Point>> {
+        self.read_across_sessions(
+            keys,
+            start_time,
+            end_time,
+            ReadMode::Envelope(samples_per_bucket),
+        )
+    }
+
+    /// Reads each session buffer of each key overlapping the range, in unified-time order. A
+    /// session's samples older than its buffer come from its spill files, if any.
+    fn read_across_sessions(
+        &self,
+        keys: &[DataColumnId],
+        start_time: UnifiedTime,
+        end_time: UnifiedTime,
+        mode: ReadMode,
     ) -> Vec<Vec<Point>> {
         let unique_stream_keys: std::collections::HashSet<_> =
             keys.iter().map(|k| k.stream_key()).collect();
@@ -252,21 +577,27 @@ impl CaptureState {
                     }
 
                     if let Some(buf_ref) = session_map.get(&session_id) {
-                        let session_min_query = (start_time - offset).max(0.0);
+                        let session_min_query = start_time - offset;
                         let session_max_query = end_time - offset;
                         if session_min_query > session_max_query {
                             continue;
                         }
 
-                        let min_bits = session_min_query.to_bits();
-                        let max_bits = session_max_query.to_bits();
-
-                        let map = buf_ref.data.read().unwrap();
-                        for (t_bits, y) in map.range(min_bits..=max_bits) {
-                            result_points.push(Point {
-                                x: f64::from_bits(*t_bits) + offset,
-                                y: *y,
-                            });
+                        let session_start = result_points.len();
+                        let first_in_memory = {
+                            let ring = buf_ref.data.read().unwrap();
+                            let range = ring.range(session_min_query, session_max_query);
+                            mode.read(buf_ref.value(), &ring, range, offset, &mut result_points);
+                            ring.first_time().unwrap_or(f64::INFINITY)
+                        };
+                        if session_min_query < first_in_memory {
+                            // Samples evicted since the ring was read are both on disk and
+                            // in `result_points`; keep the copy from memory.
+                            let mut spilled = buf_ref.spilled(session_min_query, session_max_query);
+                            spilled.retain(|p| p.x < first_in_memory);
+                            let mut older = Vec::new();
+                            mode.read_spilled(&spilled, offset, &mut older);
+                            result_points.splice(session_start..session_start, older);
                         }
                     }
                 }
@@ -287,6 +618,7 @@ impl CaptureState {
                     sample_numbers,
                     session_id,
                     instant,
+                    host_time,
                 } => {
                     if !inner.active.contains_key(&key) {
                         continue;
@@ -308,6 +640,8 @@ impl CaptureState {
                             let last_x = points.last().map(|p| p.x).unwrap_or(first_x);
                             e.insert(SessionMeta {
                                 first_instant: instant,
+                                first_host_time: host_time
+                                    .unwrap_or_else(|| timebase::instant_to_unix(instant)),
                                 last_instant: instant,
                                 first_device_time: first_x,
                                 last_device_time: last_x,
@@ -316,62 +650,86 @@ impl CaptureState {
                     }
 
                     let session_map = inner.buffers.entry(key.clone()).or_default();
-                    let rate =
-                        stream_state.effective_sampling_rate.max(Self::DEFAULT_SAMPLING_RATE);
-                    let cap = ((rate * Self::BUFFER_WINDOW_SECONDS) as usize).max(100);
+                    let follows_retention = Self::follows_retention(&key);
+                    let cap = if follows_retention {
+                        Self::buffer_capacity(
+                            &inner,
+                            &key.stream_key(),
+                            stream_state.effective_sampling_rate,
+                        )
+                    } else {
+                        let rate = Self::rate_or_default(stream_state.effective_sampling_rate);
+                        ((rate * Self::BUFFER_WINDOW_SECONDS) as usize).max(100)
+                    };
 
                     if let Some(mut buf_ref) = session_map.get_mut(&session_id) {
                         buf_ref.value_mut().set_capacity(cap);
                         buf_ref.value_mut().push_many(&points);
                     } else {
                         let buf = Buffer::new(cap);
+                        if follows_retention {
+                            buf.set_spill(inner.retention.read().unwrap().config.spill_seconds);
+                        }
                         buf.push_many(&points);
                         session_map.insert(session_id, buf);
                     }
 
-                    if let Some(subscribers) = inner.subscribers.get(&key) {
-                        if subscribers.is_empty() {
-                            continue;
-                        }
-                        let t_min = points.first().map(|p| p.x).unwrap_or(0.0);
-                        let t_max = points.last().map(|p| p.x).unwrap_or(0.0);
-                        let batch = Arc::new(BatchedData {
-                            key: key.clone(),
-                            session_id,
-                            points: Arc::new(points),
-                            sample_numbers: Arc::new(sample_numbers),
-                            t_min,
-                            t_max,
-                        });
-                        // Fan out the batch to all subscribers for this key.
+                    let subscribers = inner.subscribers.get(&key);
+                    let has_subscribers = subscribers.as_ref().is_some_and(|s| !s.is_empty());
+                    if !has_subscribers && inner.taps.is_empty() {
+                        continue;
+                    }
+                    let offset = Self::stream_offsets(&stream_state)
+                        .and_then(|o| o.get(&session_id).copied())
+                        .unwrap_or_default();
+                    let points: Vec<Point> = points
+                        .into_iter()
+                        .map(|p| Point {
+                            x: p.x + offset,
+                            y: p.y,
+                        })
+                        .collect();
+                    let t_min = points.first().map(|p| p.x).unwrap_or(0.0);
+                    let t_max = points.last().map(|p| p.x).unwrap_or(0.0);
+                    let batch = Arc::new(BatchedData {
+                        key: key.clone(),
+                        session_id,
+                        points: Arc::new(points),
+                        offset,
+                        sample_numbers: Arc::new(sample_numbers),
+                        t_min,
+                        t_max,
+                    });
+                    // Fan out the batch to all subscribers for this key, then to the taps.
+                    if let Some(subscribers) = subscribers {
                         for (_, tx) in subscribers.iter() {
                             let _ = tx.try_send(batch.clone());
                         }
                     }
+                    for tap in inner.taps.iter() {
+                        let (tx, dropped) = tap.value();
+                        if tx.try_send(batch.clone()).is_err() {
+                            dropped.fetch_add(batch.points.len() as u64, Ordering::Relaxed);
+                        }
+                    }
                 }
                 CaptureCommand::UpdateSampleRate { key, rate } => {
-                    let stream_key = key.stream_key();
-                    let mut stream_state = inner.streams.entry(stream_key.clone()).or_default();
-
-                    if (stream_state.effective_sampling_rate - rate).abs() < 1e-9 {
-                        continue;
+                    {
+                        let mut stream_state = inner.streams.entry(key.stream_key()).or_default();
+                        if (stream_state.effective_sampling_rate - rate).abs() < 1e-9 {
+                            continue;
+                        }
+                        stream_state.effective_sampling_rate = rate;
+                    }
+                    Self::apply_retention(&inner);
+                }
+                CaptureCommand::UpdateTimeBase { key, epoch } => {
+                    let reference = TimeReference::from(&epoch);
+                    let mut stream_state = inner.streams.entry(key.stream_key()).or_default();
+                    if stream_state.time_reference != reference {
+                        stream_state.time_reference = reference;
+                        *stream_state.offsets_cache.lock().unwrap() = None;
                     }
-
-                    stream_state.effective_sampling_rate = rate;
-                    let new_cap = ((rate * Self::BUFFER_WINDOW_SECONDS) as usize).max(100);
-
-                    inner
-                        .active
-                        .iter()
-                        .filter(|r| r.key().stream_key() == stream_key)
-                        .for_each(|r| {
-                            let col_key = r.key();
-                            if let Some(mut column_buffers) = inner.buffers.get_mut(col_key) {
-                                for mut session_buffer in column_buffers.value_mut().iter_mut() {
-                                    session_buffer.value_mut().set_capacity(new_cap);
-                                }
-                            }
-                        });
                 }
                 CaptureCommand::SetActiveColumns {
                     port_url,
@@ -386,6 +744,7 @@ impl CaptureState {
                         inner.active.len(),
                         port_url
                     );
+                    Self::apply_retention(&inner);
                 }
                 CaptureCommand::CreateSnapshot {
                     plot_id,
@@ -423,7 +782,219 @@ impl CaptureState {
                         v.retain(|(sid, _)| *sid != id);
                     }
                 }
+                CaptureCommand::Tap { id, tx, dropped } => {
+                    inner.taps.insert(id, (tx, dropped));
+                }
+                CaptureCommand::Untap { id } => {
+                    inner.taps.remove(&id);
+                }
+                CaptureCommand::Flush { ack } => {
+                    let _ = ack.send(());
+                }
+                CaptureCommand::RegisterVirtual { key, rate } => {
+                    inner.buffers.remove(&key);
+                    inner.streams.insert(
+                        key.stream_key(),
+                        StreamState {
+                            effective_sampling_rate: rate,
+                            time_reference: TimeReference::Unified,
+                            ..Default::default()
+                        },
+                    );
+                    inner.active.insert(key.clone(), ());
+                    println!("[Capture] Registered virtual column {:?}", key);
+                    Self::apply_retention(&inner);
+                }
+                CaptureCommand::UnregisterVirtual { key } => {
+                    inner.active.remove(&key);
+                    inner.buffers.remove(&key);
+                    inner.streams.remove(&key.stream_key());
+                    println!("[Capture] Unregistered virtual column {:?}", key);
+                    Self::apply_retention(&inner);
+                }
+            }
+        }
+    }
+
+    /// Whether the retention settings and the memory budget apply to `key`. A restored session
+    /// keeps exactly what was saved, and a replayed recording can be read again from its log, so
+    /// their buffers are neither counted, resized nor spilled.
+    fn follows_retention(key: &DataColumnId) -> bool {
+        !key.port_url.starts_with(session::URL_SCHEME)
+            && !key.port_url.starts_with(replay::URL_SCHEME)
+    }
+
+    /// Seconds of history the buffers of `key` keep: its stream's retention scaled to the budget.
+    /// Restored sessions keep everything that was saved, replays the default window.
+    pub fn retention_seconds(&self, key: &DataColumnId) -> f64 {
+        if key.port_url.starts_with(session::URL_SCHEME) {
+            return f64::INFINITY;
+        }
+        if !Self::follows_retention(key) {
+            return Self::BUFFER_WINDOW_SECONDS;
+        }
+        let retention = self.inner.retention.read().unwrap();
+        retention.requested_seconds(&key.stream_key()) * retention.scale
+    }
+
+    /// Samples a buffer of `stream_key` keeps: its retention, scaled to the budget, at `rate`.
+    fn buffer_capacity(inner: &Inner, stream_key: &DataColumnId, rate: f64) -> usize {
+        let retention = inner.retention.read().unwrap();
+        let seconds = retention.requested_seconds(stream_key) * retention.scale;
+        ((Self::rate_or_default(rate) * seconds) as usize).max(100)
+    }
+
+    fn rate_or_default(rate: f64) -> f64 {
+        if rate > 0.0 {
+            rate
+        } else {
+            Self::DEFAULT_SAMPLING_RATE
+        }
+    }
+
+    /// Each stream's rate and each column's buffers, collected up front: the consumer takes a
+    /// stream, then its buffers, then the retention, so none of them may be held while taking
+    /// another.
+    fn snapshot_buffers(inner: &Inner) -> (HashMap<DataColumnId, f64>, ColumnBuffers) {
+        let rates = inner
+            .streams
+            .iter()
+            .map(|s| (s.key().clone(), s.effective_sampling_rate))
+            .collect();
+        let buffers = inner
+            .buffers
+            .iter()
+            .map(|c| {
+                (
+                    c.key().clone(),
+                    c.value().iter().map(|b| b.value().clone()).collect(),
+                )
+            })
+            .collect();
+        (rates, buffers)
+    }
+
+    /// Fits the retention of the active columns into the memory budget, then resizes every
+    /// buffer that follows it to match.
+    fn apply_retention(inner: &Inner) {
+        let mut columns: HashMap<DataColumnId, usize> = HashMap::new();
+        for entry in inner
+            .active
+            .iter()
+            .filter(|e| Self::follows_retention(e.key()))
+        {
+            *columns.entry(entry.key().stream_key()).or_default() += 1;
+        }
+        let (rates, buffers) = Self::snapshot_buffers(inner);
+        let rate = |stream_key: &DataColumnId| rates.get(stream_key).copied().unwrap_or(0.0);
+        {
+            let mut retention = inner.retention.write().unwrap();
+            let requested: f64 = columns
+                .iter()
+                .map(|(stream_key, n)| {
+                    Self::rate_or_default(rate(stream_key))
+                        * retention.requested_seconds(stream_key)
+                        * (n * Self::BYTES_PER_SAMPLE) as f64
+                })
+                .sum();
+            let budget = retention.config.memory_budget_mb as f64 * 1024.0 * 1024.0;
+            retention.scale = if requested > budget {
+                budget / requested
+            } else {
+                1.0
+            };
+        }
+
+        let spill_seconds = inner.retention.read().unwrap().config.spill_seconds;
+        for (key, sessions) in buffers {
+            if !Self::follows_retention(&key) {
+                continue;
+            }
+            let stream_key = key.stream_key();
+            let cap = Self::buffer_capacity(inner, &stream_key, rate(&stream_key));
+            for buffer in sessions {
+                buffer.set_spill(spill_seconds);
+                buffer.set_capacity(cap);
+            }
+        }
+    }
+
+    /// Replaces the retention settings and resizes the buffers. Shrinking drops the oldest
+    /// samples right away; growing only keeps more from now on.
+    pub fn set_retention(&self, config: RetentionConfig) -> Result<(), String> {
+        let valid = |s: f64| s.is_finite() && s > 0.0;
+        if !valid(config.default_seconds) || !config.streams.iter().all(|s| valid(s.seconds)) {
+            return Err("Retention must be a positive number of seconds.".to_string());
+        }
+        if config.spill_seconds.is_some_and(|s| !valid(s)) {
+            return Err("Disk history must be a positive number of seconds.".to_string());
+        }
+        if config.memory_budget_mb == 0 {
+            return Err("The memory budget must be at least 1 MB.".to_string());
+        }
+        self.inner.retention.write().unwrap().config = config;
+        Self::apply_retention(&self.inner);
+        println!("[Capture] Updated retention settings");
+        Ok(())
+    }
+
+    /// Memory held by the buffers, per stream, against the retention settings.
+    pub fn memory_status(&self) -> CaptureMemoryStatus {
+        let (rates, buffers) = Self::snapshot_buffers(&self.inner);
+        let mut streams: HashMap<DataColumnId, StreamMemory> = HashMap::new();
+        for (key, sessions) in buffers {
+            let stream_key = key.stream_key();
+            let entry = streams
+                .entry(stream_key.clone())
+                .or_insert_with(|| StreamMemory {
+                    sample_rate: rates.get(&stream_key).copied().unwrap_or(0.0),
+                    stream: stream_key,
+                    columns: 0,
+                    requested_seconds: 0.0,
+                    retained_seconds: 0.0,
+                    bytes: 0.0,
+                    spilled_bytes: 0.0,
+                });
+            if self.inner.active.contains_key(&key) {
+                entry.columns += 1;
             }
+            entry.bytes += sessions
+                .iter()
+                .map(|b| b.allocated_bytes() as f64)
+                .sum::<f64>();
+            entry.spilled_bytes += sessions
+                .iter()
+                .map(|b| b.spilled_bytes() as f64)
+                .sum::<f64>();
+        }
+
+        let retention = self.inner.retention.read().unwrap();
+        let mut streams: Vec<StreamMemory> = streams
+            .into_values()
+            .map(|mut s| {
+                s.requested_seconds = retention.requested_seconds(&s.stream);
+                s.retained_seconds = s.requested_seconds * retention.scale;
+                s
+            })
+            .collect();
+        streams.sort_by(|a, b| {
+            (
+                &a.stream.port_url,
+                &a.stream.device_route,
+                a.stream.stream_id,
+            )
+                .cmp(&(
+                    &b.stream.port_url,
+                    &b.stream.device_route,
+                    b.stream.stream_id,
+                ))
+        });
+        CaptureMemoryStatus {
+            config: retention.config.clone(),
+            scale: retention.scale,
+            used_bytes: streams.iter().map(|s| s.bytes).sum(),
+            spilled_bytes: streams.iter().map(|s| s.spilled_bytes).sum(),
+            streams,
         }
     }
 
@@ -474,12 +1045,23 @@ impl CaptureState {
         println!("[Capture] Cleared all streams for device {:?}", device_key);
     }
 
+    pub fn offsets_for_stream(
+        &self,
+        stream_key: &DataColumnId,
+    ) -> Option<Arc<HashMap<SessionId, TimeOffset>>> {
+        self._get_or_compute_offsets_for_stream(stream_key)
+    }
+
     fn _get_or_compute_offsets_for_stream(
         &self,
         stream_key: &DataColumnId,
     ) -> Option<Arc<HashMap<SessionId, TimeOffset>>> {
         let stream_state = self.inner.streams.get(stream_key)?;
+        Self::stream_offsets(&stream_state)
+    }
 
+    /// Cached session offsets of a stream, recomputed when a session was added.
+    fn stream_offsets(stream_state: &StreamState) -> Option<Arc<HashMap<SessionId, TimeOffset>>> {
         {
             let cache = stream_state.offsets_cache.lock().unwrap();
             if let Some(offsets) = &*cache {
@@ -489,37 +1071,19 @@ impl CaptureState {
             }
         }
 
-        let mut sorted_sessions: Vec<_> = stream_state
+        let sessions: Vec<_> = stream_state
             .session_meta
             .iter()
             .map(|entry| (*entry.key(), entry.value().clone()))
             .collect();
-        if sorted_sessions.is_empty() {
+        if sessions.is_empty() {
             return None;
         }
 
-        sorted_sessions.sort_by_key(|(_id, meta)| meta.first_instant);
-
-        let mut new_offsets = HashMap::new();
-        let mut last_session_unified_end_time: UnifiedTime = 0.0;
-        let mut last_meta: Option<SessionMeta> = None;
-
-        for (session_id, current_meta) in sorted_sessions {
-            let new_offset = if let Some(prev_meta) = last_meta.as_ref() {
-                let gap = current_meta
-                    .first_instant
-                    .duration_since(prev_meta.last_instant);
-                last_session_unified_end_time + gap.as_secs_f64() - current_meta.first_device_time
-            } else {
-                0.0
-            };
-
-            new_offsets.insert(session_id, new_offset);
-            last_session_unified_end_time = current_meta.last_device_time + new_offset;
-            last_meta = Some(current_meta);
-        }
-
-        let final_offsets_arc = Arc::new(new_offsets);
+        let final_offsets_arc = Arc::new(timebase::compute_offsets(
+            stream_state.time_reference,
+            sessions,
+        ));
 
         {
             let mut cache = stream_state.offsets_cache.lock().unwrap();
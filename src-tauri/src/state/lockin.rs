@@ -0,0 +1,328 @@
+//! Lock-in channels: a column demodulated at a fixed frequency or against a reference column,
+//! written back into capture as two virtual columns (X and Y, or R and θ) on the
+//! `virtual://lockin` port.
+//!
+//! Each lock-in is a stream of its own. A runner thread subscribes to the signal (and reference)
+//! and feeds `LockInPipeline` one signal sample at a time, after every reference sample up to it,
+//! so a lock-in on a reference column lags the later of the two by up to one batch.
+use crate::pipeline::lockin::LockInPipeline;
+use crate::shared::{
+    ColumnMeta, DataColumnId, LockInConfig, LockInInfo, LockInOutput, LockInReference, Point,
+    StreamMeta, UiDevice, UiStream,
+};
+use crate::state::capture::{CaptureState, VirtualColumnWriter};
+use crate::state::virtual_port::{self, VirtualPort, VirtualRunner, VirtualStreamInfo};
+use std::collections::VecDeque;
+use tauri::AppHandle;
+
+pub const URL: &str = "virtual://lockin";
+const DEVICE_NAME: &str = "Lock-in Amplifiers";
+const MAX_FILTER_ORDER: u32 = 4;
+
+impl VirtualStreamInfo for LockInInfo {
+    fn ui_stream(&self, stream_id: u8, sample_rate: f64) -> UiStream {
+        let config = &self.config;
+        let description = describe(config);
+        let columns = output_columns(config)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (suffix, units))| ColumnMeta {
+                stream_id,
+                index,
+                data_type: "Float64".to_string(),
+                name: format!("{} {}", config.name, suffix),
+                units,
+                description: description.clone(),
+            })
+            .collect::<Vec<_>>();
+        UiStream {
+            meta: StreamMeta {
+                stream_id,
+                name: config.name.clone(),
+                n_columns: columns.len(),
+                n_segments: 0,
+                sample_size: 8 * columns.len(),
+                buf_samples: 0,
+            },
+            segment: None,
+            columns,
+            effective_sampling_rate: sample_rate,
+        }
+    }
+}
+
+pub struct LockIns {
+    /// Keyed by stream id on the `virtual://lockin` port.
+    port: VirtualPort<LockInInfo>,
+    capture: CaptureState,
+}
+
+impl LockIns {
+    pub fn new(app: AppHandle, capture: CaptureState) -> Self {
+        Self {
+            port: VirtualPort::new(URL, DEVICE_NAME, "LockIn", "lock-in", app, capture.clone()),
+            capture,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.port.is_empty()
+    }
+
+    pub fn list(&self) -> Vec<LockInInfo> {
+        self.port.list()
+    }
+
+    /// Validates `config`, starts demodulating (from the buffered history of its inputs on) and
+    /// announces the updated `virtual://lockin` device.
+    pub fn create(&self, config: LockInConfig) -> Result<LockInInfo, String> {
+        let name = config.name.trim().to_string();
+        if name.is_empty() {
+            return Err("Lock-in needs a name.".to_string());
+        }
+        if !(config.time_constant.is_finite() && config.time_constant > 0.0) {
+            return Err("Time constant must be positive.".to_string());
+        }
+        if !(1..=MAX_FILTER_ORDER).contains(&config.filter_order) {
+            return Err(format!(
+                "Filter order must be between 1 and {}.",
+                MAX_FILTER_ORDER
+            ));
+        }
+        if config.harmonic == 0 {
+            return Err("Harmonic must be at least 1.".to_string());
+        }
+        let reference_key = match &config.reference {
+            LockInReference::Frequency { hz } => {
+                if !(hz.is_finite() && *hz > 0.0) {
+                    return Err("Reference frequency must be positive.".to_string());
+                }
+                None
+            }
+            LockInReference::Column { key } => Some(key.clone()),
+        };
+        if std::iter::once(&config.signal)
+            .chain(reference_key.as_ref())
+            .any(|k| k.port_url == URL)
+        {
+            return Err("Lock-in outputs can't be inputs of other lock-ins.".to_string());
+        }
+
+        let sample_rate = self
+            .capture
+            .get_effective_sampling_rate(&config.signal)
+            .unwrap_or(0.0);
+        if let LockInReference::Frequency { hz } = config.reference {
+            let demodulated = hz * config.harmonic as f64;
+            if sample_rate > 0.0 && demodulated >= sample_rate / 2.0 {
+                return Err(format!(
+                    "{} Hz is above the signal's Nyquist frequency ({} Hz).",
+                    demodulated,
+                    sample_rate / 2.0
+                ));
+            }
+        }
+
+        let mut inputs = vec![config.signal.clone()];
+        inputs.extend(reference_key.filter(|k| *k != config.signal));
+
+        let info = self.port.start(2, sample_rate, &inputs, |writers| {
+            let info = LockInInfo {
+                keys: writers.iter().map(|w| w.key().clone()).collect(),
+                config: LockInConfig { name, ..config },
+            };
+            let runner = Runner::new(writers, &info.config, self.capture.clone());
+            (info, runner)
+        })?;
+        println!(
+            "[LockIn] Created lock-in {} '{}' on {:?}",
+            info.keys[0].stream_id, info.config.name, info.config.signal
+        );
+        Ok(info)
+    }
+
+    /// Stops the lock-in writing to `key` (either of its columns) and drops its columns from
+    /// capture.
+    pub fn remove(&self, key: &DataColumnId) -> Result<(), String> {
+        self.port.remove(key)
+    }
+
+    /// The `virtual://lockin` device with one stream per lock-in, or `None` while there are none.
+    pub fn ui_device(&self) -> Option<UiDevice> {
+        self.port.ui_device()
+    }
+}
+
+/// Name suffix and units of each output column.
+fn output_columns(config: &LockInConfig) -> [(&'static str, String); 2] {
+    match config.output {
+        LockInOutput::Cartesian => [("X", config.units.clone()), ("Y", config.units.clone())],
+        LockInOutput::Polar => [("R", config.units.clone()), ("θ", "deg".to_string())],
+    }
+}
+
+fn describe(config: &LockInConfig) -> String {
+    let reference = match &config.reference {
+        LockInReference::Frequency { hz } => format!("{} Hz", hz),
+        LockInReference::Column { .. } => "reference column".to_string(),
+    };
+    let harmonic = match config.harmonic {
+        1 => String::new(),
+        n => format!(" × {}", n),
+    };
+    format!(
+        "Lock-in at {}{}, τ = {} s ({} dB/oct)",
+        reference,
+        harmonic,
+        config.time_constant,
+        6 * config.filter_order
+    )
+}
+
+/// Demodulates one lock-in from the batches of its signal and reference.
+struct Runner {
+    writers: Vec<VirtualColumnWriter>,
+    lock_in: LockInPipeline,
+    signal_key: DataColumnId,
+    reference_key: Option<DataColumnId>,
+    /// Points not yet fed to `lock_in`.
+    signal: VecDeque<Point>,
+    reference: VecDeque<Point>,
+    /// Last point fed from each input, to skip batches that overlap the hydrated history.
+    signal_time: f64,
+    reference_time: f64,
+    sample_rate: f64,
+    capture: CaptureState,
+}
+
+impl Runner {
+    fn new(
+        writers: Vec<VirtualColumnWriter>,
+        config: &LockInConfig,
+        capture: CaptureState,
+    ) -> Self {
+        let reference_key = match &config.reference {
+            LockInReference::Frequency { .. } => None,
+            LockInReference::Column { key } => Some(key.clone()),
+        };
+        Self {
+            writers,
+            lock_in: LockInPipeline::new(config),
+            signal_key: config.signal.clone(),
+            reference_key,
+            signal: VecDeque::new(),
+            reference: VecDeque::new(),
+            signal_time: f64::NEG_INFINITY,
+            reference_time: f64::NEG_INFINITY,
+            sample_rate: 0.0,
+            capture,
+        }
+    }
+
+    fn update_sample_rate(&mut self) {
+        let rate = self
+            .capture
+            .get_effective_sampling_rate(&self.signal_key)
+            .unwrap_or(0.0);
+        if rate > 0.0 && (rate - self.sample_rate).abs() > 1e-9 {
+            self.sample_rate = rate;
+            for writer in &self.writers {
+                writer.set_sample_rate(rate);
+            }
+        }
+    }
+
+    /// Feeds every signal sample the reference has caught up to, with the reference samples
+    /// before it.
+    fn compute(&mut self) {
+        self.update_sample_rate();
+        let horizon = match self.reference_key {
+            Some(_) => self.reference.back().map_or(f64::NEG_INFINITY, |p| p.x),
+            None => f64::INFINITY,
+        };
+
+        let mut outputs = [Vec::new(), Vec::new()];
+        while let Some(p) = self.signal.front().copied() {
+            if p.x > horizon {
+                break;
+            }
+            while let Some(r) = self.reference.front().filter(|r| r.x <= p.x).copied() {
+                self.lock_in.push_reference(r);
+                self.reference_time = r.x;
+                self.reference.pop_front();
+            }
+            self.signal.pop_front();
+            self.signal_time = p.x;
+            if let Some((a, b)) = self.lock_in.push_signal(p) {
+                outputs[0].push(Point::new(p.x, a));
+                outputs[1].push(Point::new(p.x, b));
+            }
+        }
+
+        // An input that stalls must not let the other one pile up past its retention.
+        for (buffer, time, key) in [
+            (
+                &mut self.signal,
+                &mut self.signal_time,
+                Some(&self.signal_key),
+            ),
+            (
+                &mut self.reference,
+                &mut self.reference_time,
+                self.reference_key.as_ref(),
+            ),
+        ] {
+            let Some(key) = key else {
+                continue;
+            };
+            let retention = self.capture.retention_seconds(key);
+            while let (Some(front), Some(back)) = (buffer.front(), buffer.back()) {
+                if back.x - front.x <= retention {
+                    break;
+                }
+                *time = front.x;
+                buffer.pop_front();
+            }
+        }
+
+        for (writer, points) in self.writers.iter_mut().zip(outputs) {
+            writer.write(points);
+        }
+    }
+}
+
+impl VirtualRunner for Runner {
+    fn hydrate(&mut self) {
+        let mut keys = vec![self.signal_key.clone()];
+        keys.extend(self.reference_key.clone());
+        let Some(history) = virtual_port::buffered_history(&self.capture, &keys) else {
+            return;
+        };
+        let mut history = history.into_iter();
+        self.signal.extend(history.next().unwrap_or_default());
+        if let Some(points) = history.next() {
+            self.reference.extend(points);
+        }
+        self.compute();
+    }
+
+    fn push(&mut self, key: &DataColumnId, points: &[Point]) {
+        if *key == self.signal_key {
+            append(&mut self.signal, self.signal_time, points);
+        }
+        if self.reference_key.as_ref() == Some(key) {
+            append(&mut self.reference, self.reference_time, points);
+        }
+        self.compute();
+    }
+}
+
+/// Appends the points of `points` after both the buffer's last point and `consumed`.
+fn append(buffer: &mut VecDeque<Point>, consumed: f64, points: &[Point]) {
+    for p in points {
+        let last = buffer.back().map_or(consumed, |b| b.x.max(consumed));
+        if p.x > last {
+            buffer.push_back(*p);
+        }
+    }
+}
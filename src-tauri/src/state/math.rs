@@ -0,0 +1,336 @@
+//! Math channels: user expressions over several columns, written back into capture as virtual
+//! columns on the `virtual://math` port, so plots, the stream monitor and export treat them like
+//! real ones.
+//!
+//! Each channel is a stream of its own with a single column. A runner thread subscribes to the
+//! inputs and evaluates the expression on the timestamps of the fastest one, interpolating the
+//! others linearly like `k_way_merge_plot_data` does. A sample is computed once every input has
+//! data past it, so the channel lags its slowest input by up to one of that input's batches. An
+//! optional filter is applied to the result before it is written.
+use crate::pipeline::filter::DigitalFilter;
+use crate::pipeline::math::Expression;
+use crate::shared::{
+    ColumnMeta, DataColumnId, FilterConfig, MathChannelConfig, MathChannelInfo, Point, StreamMeta,
+    UiDevice, UiStream,
+};
+use crate::state::capture::{CaptureState, VirtualColumnWriter};
+use crate::state::virtual_port::{self, VirtualPort, VirtualRunner, VirtualStreamInfo};
+use crate::util::lerp;
+use std::collections::VecDeque;
+use tauri::AppHandle;
+
+pub const URL: &str = "virtual://math";
+const DEVICE_NAME: &str = "Math Channels";
+
+impl VirtualStreamInfo for MathChannelInfo {
+    fn ui_stream(&self, stream_id: u8, sample_rate: f64) -> UiStream {
+        UiStream {
+            meta: StreamMeta {
+                stream_id,
+                name: self.config.name.clone(),
+                n_columns: 1,
+                n_segments: 0,
+                sample_size: 8,
+                buf_samples: 0,
+            },
+            segment: None,
+            columns: vec![ColumnMeta {
+                stream_id,
+                index: 0,
+                data_type: "Float64".to_string(),
+                name: self.config.name.clone(),
+                units: self.config.units.clone(),
+                description: self.config.expression.clone(),
+            }],
+            effective_sampling_rate: sample_rate,
+        }
+    }
+}
+
+pub struct MathChannels {
+    /// Keyed by stream id on the `virtual://math` port.
+    port: VirtualPort<MathChannelInfo>,
+    capture: CaptureState,
+}
+
+impl MathChannels {
+    pub fn new(app: AppHandle, capture: CaptureState) -> Self {
+        Self {
+            port: VirtualPort::new(
+                URL,
+                DEVICE_NAME,
+                "Math",
+                "math channel",
+                app,
+                capture.clone(),
+            ),
+            capture,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.port.is_empty()
+    }
+
+    pub fn list(&self) -> Vec<MathChannelInfo> {
+        self.port.list()
+    }
+
+    /// Validates `config`, starts computing it (from the buffered history of its inputs on) and
+    /// announces the updated `virtual://math` device.
+    pub fn create(&self, config: MathChannelConfig) -> Result<MathChannelInfo, String> {
+        let name = config.name.trim().to_string();
+        if name.is_empty() {
+            return Err("Math channel needs a name.".to_string());
+        }
+        if config.inputs.is_empty() {
+            return Err("Math channel needs at least one input.".to_string());
+        }
+        let variables: Vec<String> = config.inputs.iter().map(|i| i.variable.clone()).collect();
+        for (i, v) in variables.iter().enumerate() {
+            if variables[..i].contains(v) {
+                return Err(format!("Variable '{}' is bound twice.", v));
+            }
+        }
+        if let Some(input) = config.inputs.iter().find(|i| i.key.port_url == URL) {
+            return Err(format!(
+                "'{}' is a math channel; math channels can't be inputs of other math channels.",
+                input.variable
+            ));
+        }
+        let expression = Expression::parse(&config.expression, &variables)?;
+        if let Some(unused) = variables
+            .iter()
+            .enumerate()
+            .find(|(i, _)| !expression.uses(*i))
+        {
+            return Err(format!(
+                "Variable '{}' is not used in the expression.",
+                unused.1
+            ));
+        }
+
+        // Each distinct column is subscribed once; variables refer to it by index.
+        let mut inputs: Vec<DataColumnId> = Vec::new();
+        let bindings: Vec<usize> = config
+            .inputs
+            .iter()
+            .map(|input| match inputs.iter().position(|k| *k == input.key) {
+                Some(i) => i,
+                None => {
+                    inputs.push(input.key.clone());
+                    inputs.len() - 1
+                }
+            })
+            .collect();
+        let sample_rate = inputs
+            .iter()
+            .filter_map(|k| self.capture.get_effective_sampling_rate(k))
+            .fold(0.0, f64::max);
+
+        // Catch bad cutoffs now rather than in the runner, when the rate is already known.
+        if let (Some(filter), true) = (config.filter.as_ref(), sample_rate > 0.0) {
+            DigitalFilter::design(filter, sample_rate)?;
+        }
+
+        let info = self.port.start(1, sample_rate, &inputs, |mut writers| {
+            let writer = writers.remove(0);
+            let info = MathChannelInfo {
+                key: writer.key().clone(),
+                config: MathChannelConfig { name, ..config },
+            };
+            let runner = Runner::new(
+                writer,
+                expression,
+                info.config.filter.clone(),
+                bindings,
+                inputs.clone(),
+                self.capture.clone(),
+            );
+            (info, runner)
+        })?;
+        println!(
+            "[Math] Created channel {} '{}' = {}",
+            info.key.stream_id, info.config.name, info.config.expression
+        );
+        Ok(info)
+    }
+
+    /// Stops the channel writing to `key` and drops its column from capture.
+    pub fn remove(&self, key: &DataColumnId) -> Result<(), String> {
+        self.port.remove(key)
+    }
+
+    /// The `virtual://math` device with one stream per channel, or `None` while there are none.
+    pub fn ui_device(&self) -> Option<UiDevice> {
+        self.port.ui_device()
+    }
+}
+
+/// Evaluates one channel from the batches of its inputs.
+struct Runner {
+    writer: VirtualColumnWriter,
+    expression: Expression,
+    filter_config: Option<FilterConfig>,
+    /// Designed once the output rate is known.
+    filter: Option<DigitalFilter>,
+    /// Index into `inputs` for each variable of the expression.
+    bindings: Vec<usize>,
+    inputs: Vec<DataColumnId>,
+    /// Recent points of each input, from the last one at or before `last_time` on.
+    buffers: Vec<VecDeque<Point>>,
+    /// Input whose timestamps the channel is computed on.
+    reference: usize,
+    sample_rate: f64,
+    last_time: f64,
+    capture: CaptureState,
+}
+
+impl Runner {
+    fn new(
+        writer: VirtualColumnWriter,
+        expression: Expression,
+        filter_config: Option<FilterConfig>,
+        bindings: Vec<usize>,
+        inputs: Vec<DataColumnId>,
+        capture: CaptureState,
+    ) -> Self {
+        Self {
+            writer,
+            expression,
+            filter_config,
+            filter: None,
+            bindings,
+            buffers: vec![VecDeque::new(); inputs.len()],
+            inputs,
+            reference: 0,
+            sample_rate: 0.0,
+            last_time: f64::NEG_INFINITY,
+            capture,
+        }
+    }
+
+    fn update_reference(&mut self) {
+        let rates: Vec<f64> = self
+            .inputs
+            .iter()
+            .map(|k| self.capture.get_effective_sampling_rate(k).unwrap_or(0.0))
+            .collect();
+        let (reference, rate) = rates
+            .iter()
+            .copied()
+            .enumerate()
+            .fold(
+                (0, 0.0),
+                |best, (i, r)| if r > best.1 { (i, r) } else { best },
+            );
+        self.reference = reference;
+        if rate > 0.0 && (rate - self.sample_rate).abs() > 1e-9 {
+            self.sample_rate = rate;
+            self.writer.set_sample_rate(rate);
+            self.filter = self.filter_config.as_ref().and_then(|config| {
+                DigitalFilter::design(config, rate)
+                    .inspect_err(|e| eprintln!("[Math] {:?}: {}", self.writer.key(), e))
+                    .ok()
+            });
+        }
+    }
+
+    /// Evaluates every reference timestamp that all inputs have caught up to.
+    fn compute(&mut self) {
+        self.update_reference();
+
+        // An input that stalls, or hasn't started, must not let the others pile up past their
+        // retention. Dropped points are skipped, so `last_time` moves past them.
+        for (buffer, key) in self.buffers.iter_mut().zip(&self.inputs) {
+            let retention = self.capture.retention_seconds(key);
+            while let (Some(front), Some(back)) = (buffer.front(), buffer.back()) {
+                if back.x - front.x <= retention {
+                    break;
+                }
+                self.last_time = self.last_time.max(front.x);
+                buffer.pop_front();
+            }
+        }
+
+        let Some(horizon) = self
+            .buffers
+            .iter()
+            .map(|b| b.back().map(|p| p.x))
+            .try_fold(f64::INFINITY, |h, x| x.map(|x| h.min(x)))
+        else {
+            return;
+        };
+
+        let mut output = Vec::new();
+        let mut values = vec![0.0; self.bindings.len()];
+        let mut last_time = self.last_time;
+        let reference = &self.buffers[self.reference];
+        let start = reference.partition_point(|p| p.x <= last_time);
+        for p in reference.range(start..) {
+            if p.x > horizon {
+                break;
+            }
+            last_time = p.x;
+            let mut complete = true;
+            for (value, &input) in values.iter_mut().zip(&self.bindings) {
+                match interpolate(&self.buffers[input], p.x) {
+                    Some(y) => *value = y,
+                    None => complete = false,
+                }
+            }
+            if complete {
+                output.push(Point::new(p.x, self.expression.eval(&values)));
+            }
+        }
+
+        // Keep the last point at or before `last_time` of each input to interpolate from.
+        self.last_time = last_time;
+        for buffer in &mut self.buffers {
+            while buffer.len() > 1 && buffer[1].x <= last_time {
+                buffer.pop_front();
+            }
+        }
+
+        if let Some(filter) = self.filter.as_mut() {
+            output = filter.process_all(&output);
+        }
+        self.writer.write(output);
+    }
+}
+
+impl VirtualRunner for Runner {
+    fn hydrate(&mut self) {
+        let Some(history) = virtual_port::buffered_history(&self.capture, &self.inputs) else {
+            return;
+        };
+        for (buffer, points) in self.buffers.iter_mut().zip(history) {
+            buffer.extend(points);
+        }
+        self.compute();
+    }
+
+    fn push(&mut self, key: &DataColumnId, points: &[Point]) {
+        let Some(i) = self.inputs.iter().position(|k| k == key) else {
+            return;
+        };
+        let buffer = &mut self.buffers[i];
+        for p in points {
+            if buffer.back().is_none_or(|last| p.x > last.x) {
+                buffer.push_back(*p);
+            }
+        }
+        self.compute();
+    }
+}
+
+/// Value of `buffer` at `x`, linearly interpolated between the points around it.
+fn interpolate(buffer: &VecDeque<Point>, x: f64) -> Option<f64> {
+    let i = buffer.partition_point(|p| p.x < x);
+    let next = buffer.get(i)?;
+    if (next.x - x).abs() < 1e-9 {
+        return Some(next.y);
+    }
+    let prev = buffer.get(i.checked_sub(1)?)?;
+    Some(lerp(prev, next, x))
+}
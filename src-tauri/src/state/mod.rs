@@ -1,2 +1,9 @@
 pub mod capture;
+pub mod lockin;
+pub mod math;
 pub mod proxy_register;
+pub mod pyramid;
+pub mod ring;
+pub mod spill;
+pub mod timebase;
+pub mod virtual_port;
@@ -1,17 +1,38 @@
 // TAKES SERIAL/TCP PORT URL and maps it to a PortManager
 
 use crate::proxy::port_manager::PortManager;
-use crate::shared::{DataColumnId, PortState};
+use crate::recording::replay::{self, ReplayPort};
+use crate::recording::session::{self, RestoredSession, SessionFile};
+use crate::shared::{DataColumnId, PortState, SessionRestored, UiDevice, VIRTUAL_URL_SCHEME};
 use crate::state::capture::CaptureState;
+use crate::state::lockin::{self, LockIns};
+use crate::state::math::{self, MathChannels};
 use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use tauri::{AppHandle, Emitter};
 
 pub struct ProxyRegister {
     pub ports: DashMap<String, Arc<PortManager>>,
+    /// Recordings opened for playback, keyed by their `file://` URL.
+    pub replays: DashMap<String, Arc<ReplayPort>>,
+    /// Session files loaded back into capture, keyed by the `session://` URL of each saved port.
+    pub restored: DashMap<String, Arc<RestoredSession>>,
+    /// Expression channels, served as the `virtual://math` port.
+    pub math: MathChannels,
+    /// Lock-in amplifiers, served as the `virtual://lockin` port.
+    pub lockins: LockIns,
     pub active_selections: DashMap<String, Vec<DataColumnId>>,
-    selected_port: RwLock<Option<String>>,
+    /// Network URLs found by LAN discovery. Like serial ports, these come and go with discovery;
+    /// URLs the user typed in are never pruned.
+    discovered: DashMap<String, ()>,
+    /// Ports the user confirmed, oldest first. All of them stream at once.
+    selected_ports: RwLock<Vec<String>>,
+    /// While the device dialog is open every discovered port is connected, so new ones can be
+    /// picked alongside the ones already streaming.
+    discovery_open: AtomicBool,
     capture: CaptureState,
     app: AppHandle,
 }
@@ -20,32 +41,68 @@ impl ProxyRegister {
     pub fn new(app: AppHandle, capture: CaptureState) -> Self {
         Self {
             ports: DashMap::new(),
+            replays: DashMap::new(),
+            restored: DashMap::new(),
+            math: MathChannels::new(app.clone(), capture.clone()),
+            lockins: LockIns::new(app.clone(), capture.clone()),
             active_selections: DashMap::new(),
-            selected_port: RwLock::new(None),
+            discovered: DashMap::new(),
+            selected_ports: RwLock::new(Vec::new()),
+            discovery_open: AtomicBool::new(false),
             app,
             capture,
         }
     }
 
-    pub fn set_selected_port(&self, url: Option<String>) {
-        *self.selected_port.write().unwrap() = url;
+    /// Adds `url` to the streaming set (or moves it to the back as the most recent).
+    pub fn select_port(&self, url: String) {
+        let mut selected = self.selected_ports.write().unwrap();
+        selected.retain(|u| *u != url);
+        selected.push(url);
     }
 
+    /// Returns whether `url` was selected.
+    pub fn deselect_port(&self, url: &str) -> bool {
+        let mut selected = self.selected_ports.write().unwrap();
+        let before = selected.len();
+        selected.retain(|u| u != url);
+        selected.len() != before
+    }
+
+    pub fn selected_ports(&self) -> Vec<String> {
+        self.selected_ports.read().unwrap().clone()
+    }
+
+    /// The most recently confirmed port.
     pub fn selected(&self) -> Option<String> {
-        self.selected_port.read().unwrap().clone()
+        self.selected_ports.read().unwrap().last().cloned()
     }
 
     fn is_selected(&self, url: &str) -> bool {
-        match &*self.selected_port.read().unwrap() {
-            None => true,
-            Some(sel) => sel == url,
+        if self.discovery_open.load(Ordering::Relaxed) {
+            return true;
         }
+        let selected = self.selected_ports.read().unwrap();
+        selected.is_empty() || selected.iter().any(|u| u == url)
     }
 
-    pub fn shutdown_all_except(&self, keep_url: &str) {
+    /// Closing the dialog releases every discovered port that wasn't selected.
+    pub fn set_discovery_open(&self, open: bool) {
+        self.discovery_open.store(open, Ordering::Relaxed);
+        if !open {
+            self.shutdown_unselected();
+        }
+    }
+
+    /// Ports owned by discovery rather than the user: every serial port, plus LAN endpoints.
+    fn is_discovered(&self, url: &str) -> bool {
+        url.starts_with("serial://") || self.discovered.contains_key(url)
+    }
+
+    pub fn shutdown_unselected(&self) {
         for entry in self.ports.iter() {
             let url = entry.key();
-            if url.starts_with("serial://") && url != keep_url {
+            if self.is_discovered(url) && !self.is_selected(url) {
                 entry.value().shutdown();
             }
         }
@@ -56,7 +113,17 @@ impl ProxyRegister {
     }
 
     pub fn ensure(&self, url: String) {
-        if url.starts_with("serial://") && !self.is_selected(&url) {
+        if let Some(path) = url.strip_prefix(replay::URL_SCHEME) {
+            if let Err(e) = self.open_replay(PathBuf::from(path)) {
+                eprintln!("[Registry] {}", e);
+            }
+            return;
+        }
+        if url.starts_with(session::URL_SCHEME) || url.starts_with(VIRTUAL_URL_SCHEME) {
+            // Restored sessions and virtual columns have no live source to (re)connect to.
+            return;
+        }
+        if self.is_discovered(&url) && !self.is_selected(&url) {
             return;
         }
         let capture_tx = self.capture.inner.command_tx.clone();
@@ -81,18 +148,33 @@ impl ProxyRegister {
         }
     }
 
+    /// `ensure` for a URL found by LAN discovery, so `prune` may drop it once it disappears.
+    pub fn ensure_discovered(&self, url: String) {
+        if !self.ports.contains_key(&url) {
+            self.discovered.insert(url.clone(), ());
+        }
+        self.ensure(url);
+    }
+
+    /// `ensure` for a URL the user asked for; it is kept even if discovery stops seeing it.
+    pub fn ensure_manual(&self, url: String) {
+        self.discovered.remove(&url);
+        self.ensure(url);
+    }
+
     pub fn prune<F>(&self, keep: F)
     where
         F: Fn(&String) -> bool,
     {
         self.ports.retain(|url, pm| {
-            if !url.starts_with("serial://") {
+            if !self.is_discovered(url) {
                 return true;
             }
 
             if keep(url) {
                 true
             } else {
+                self.discovered.remove(url);
                 println!("[Discovery] Pruning disconnected port: {}", url);
                 self.app.emit("device-removed", url.clone()).unwrap();
 
@@ -105,4 +187,177 @@ impl ProxyRegister {
     pub fn get(&self, url: &String) -> Option<Arc<PortManager>> {
         self.ports.get(url).map(|r| r.value().clone())
     }
+
+    /// Opens `path` for playback, or returns the URL of the replay already open for it.
+    pub fn open_replay(&self, path: PathBuf) -> Result<String, String> {
+        let url = ReplayPort::url_for(&path);
+        if let Some(existing) = self.replays.get(&url) {
+            if !matches!(*existing.state.lock().unwrap(), PortState::Disconnected) {
+                return Ok(url);
+            }
+        }
+        let replay = ReplayPort::open(path, self.app.clone(), self.capture.clone())?;
+        self.replays.insert(url.clone(), replay);
+        Ok(url)
+    }
+
+    pub fn get_replay(&self, url: &str) -> Option<Arc<ReplayPort>> {
+        self.replays.get(url).map(|r| r.value().clone())
+    }
+
+    /// Loads a session file into capture and announces it like a freshly discovered port.
+    pub fn restore_session(&self, path: PathBuf) -> Result<Arc<RestoredSession>, String> {
+        let file = SessionFile::load(&path)?;
+        let prefix = RestoredSession::url_for(&path, "");
+        self.restored.retain(|url, _| !url.starts_with(&prefix));
+        self.active_selections.retain(|url, _| !url.starts_with(&prefix));
+        let session = Arc::new(file.restore(path, &self.capture));
+        for url in &session.urls {
+            self.restored.insert(url.clone(), session.clone());
+            self.active_selections.insert(
+                url.clone(),
+                self.capture
+                    .inner
+                    .active
+                    .iter()
+                    .filter(|k| k.key().port_url == *url)
+                    .map(|k| k.key().clone())
+                    .collect(),
+            );
+
+            self.app
+                .emit("port-state-changed", (url.clone(), PortState::Streaming))
+                .unwrap();
+            self.app
+                .emit("port-devices-discovered", session.port_devices(url))
+                .unwrap();
+        }
+        self.app
+            .emit(
+                "session-restored",
+                SessionRestored {
+                    urls: session.urls.clone(),
+                    path: session.path.to_string_lossy().into_owned(),
+                    plots: session.plots.clone(),
+                },
+            )
+            .unwrap();
+        Ok(session)
+    }
+
+    /// Closes a replay or restored session and drops its columns from capture.
+    pub fn close_recording(&self, url: &str) -> Result<(), String> {
+        if let Some((_, replay)) = self.replays.remove(url) {
+            replay.shutdown();
+        } else if self.restored.remove(url).is_some() {
+            self.capture.inner.buffers.retain(|k, _| k.port_url != url);
+            self.capture.inner.streams.retain(|k, _| k.port_url != url);
+        } else {
+            return Err(format!("Recording '{}' is not open.", url));
+        }
+        self.active_selections.remove(url);
+        let _ = self
+            .capture
+            .inner
+            .command_tx
+            .send(crate::state::capture::CaptureCommand::SetActiveColumns {
+                port_url: url.to_string(),
+                keys_for_port: Vec::new(),
+            });
+        self.app.emit("device-removed", url.to_string()).unwrap();
+        Ok(())
+    }
+
+    /// State of a live port, replay, restored session or a virtual port.
+    pub fn port_state(&self, url: &str) -> Option<PortState> {
+        if let Some(replay) = self.replays.get(url) {
+            return Some(replay.state.lock().unwrap().clone());
+        }
+        if self.restored.contains_key(url)
+            || (url == math::URL && !self.math.is_empty())
+            || (url == lockin::URL && !self.lockins.is_empty())
+        {
+            return Some(PortState::Streaming);
+        }
+        self.ports
+            .get(url)
+            .map(|pm| pm.state.lock().unwrap().clone())
+    }
+
+    /// Devices on a single live port, replay, restored session or a virtual port.
+    pub fn port_ui_devices(&self, url: &str) -> Vec<UiDevice> {
+        if let Some(replay) = self.replays.get(url) {
+            return replay.ui_devices();
+        }
+        if let Some(session) = self.restored.get(url) {
+            return session.port_devices(url);
+        }
+        if url == math::URL {
+            return self.math.ui_device().into_iter().collect();
+        }
+        if url == lockin::URL {
+            return self.lockins.ui_device().into_iter().collect();
+        }
+        let Some(port_manager) = self.ports.get(url) else {
+            return Vec::new();
+        };
+        let Ok(devices_map) = port_manager.devices.read() else {
+            return Vec::new();
+        };
+        devices_map
+            .values()
+            .filter_map(|entry| entry.lock().ok().map(|t| t.1.clone()))
+            .collect()
+    }
+
+    pub fn find_ui_device(&self, url: &str, route: &str) -> Option<UiDevice> {
+        self.port_ui_devices(url)
+            .into_iter()
+            .find(|d| d.route == route)
+    }
+
+    /// Snapshot of every device known across all ports and open recordings, skipping any whose lock is poisoned.
+    pub fn ui_devices(&self) -> Vec<UiDevice> {
+        let mut all_devices = Vec::new();
+
+        for entry in self.ports.iter() {
+            let port_manager = entry.value();
+
+            let devices_map = match port_manager.devices.read() {
+                Ok(guard) => guard,
+                Err(e) => {
+                    eprintln!(
+                        "[{}] Could not acquire device lock for state hydration (poisoned: {}). Skipping.",
+                        port_manager.url, e
+                    );
+                    continue;
+                }
+            };
+
+            for device_entry in devices_map.values() {
+                let device_tuple = match device_entry.lock() {
+                    Ok(guard) => guard,
+                    Err(e) => {
+                        eprintln!(
+                            "[{}] A device lock was poisoned during state hydration (poisoned: {}). Skipping device.",
+                            port_manager.url, e
+                        );
+                        continue;
+                    }
+                };
+
+                let (_device, ui_device) = &*device_tuple;
+                all_devices.push(ui_device.clone());
+            }
+        }
+        for entry in self.replays.iter() {
+            all_devices.extend(entry.value().ui_devices());
+        }
+        for entry in self.restored.iter() {
+            all_devices.extend(entry.value().port_devices(entry.key()));
+        }
+        all_devices.extend(self.math.ui_device());
+        all_devices.extend(self.lockins.ui_device());
+        all_devices
+    }
 }
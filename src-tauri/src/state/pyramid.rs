@@ -0,0 +1,280 @@
+//! Min/max/mean tiles over a capture buffer, so a zoomed-out read costs O(buckets) rather than
+//! O(samples).
+//!
+//! Level `l` holds closed tiles of `TILE_FACTOR^(l+1)` consecutive samples; each level is built
+//! from the one below as its tiles close, so appending is amortised O(1). Tiles only ever cover
+//! whole runs of samples still in the buffer: trimming drops every tile that starts before the
+//! oldest remaining sample, and readers fill the gaps at either end from the raw samples. A
+//! sample arriving out of order marks the pyramid stale, and the next read rebuilds it.
+use super::ring::SampleRing;
+use crate::shared::Point;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+/// Samples per tile grow by this factor from one level to the next.
+const TILE_FACTOR: usize = 16;
+/// 16, 256, 4096 and 65536 samples per tile.
+const LEVELS: usize = 4;
+
+/// Summary of a run of consecutive samples.
+#[derive(Clone, Copy, Debug)]
+pub struct Tile {
+    pub t_start: f64,
+    pub t_end: f64,
+    pub min: Point,
+    pub max: Point,
+    /// Sum of the finite values.
+    pub sum: f64,
+    pub finite: usize,
+    /// Samples covered, finite or not.
+    pub count: usize,
+}
+
+impl Tile {
+    fn from_point(p: Point) -> Self {
+        let finite = p.y.is_finite();
+        Self {
+            t_start: p.x,
+            t_end: p.x,
+            min: p,
+            max: p,
+            sum: if finite { p.y } else { 0.0 },
+            finite: finite as usize,
+            count: 1,
+        }
+    }
+
+    fn push(&mut self, p: Point) {
+        self.merge(&Self::from_point(p));
+    }
+
+    /// Extends this tile with `other`, which must come after it.
+    fn merge(&mut self, other: &Tile) {
+        self.t_end = other.t_end;
+        if self.min.y.is_nan() || other.min.y < self.min.y {
+            self.min = other.min;
+        }
+        if self.max.y.is_nan() || other.max.y > self.max.y {
+            self.max = other.max;
+        }
+        self.sum += other.sum;
+        self.finite += other.finite;
+        self.count += other.count;
+    }
+
+    /// Mean of the finite values, `NaN` if there are none.
+    pub fn mean(&self) -> f64 {
+        if self.finite == 0 {
+            f64::NAN
+        } else {
+            self.sum / self.finite as f64
+        }
+    }
+
+    /// The minimum and maximum in time order, once if they are the same sample.
+    fn extremes(&self) -> impl Iterator<Item = Point> {
+        let (first, second) = if self.min.x <= self.max.x {
+            (self.min, self.max)
+        } else {
+            (self.max, self.min)
+        };
+        std::iter::once(first).chain((first.x != second.x).then_some(second))
+    }
+}
+
+#[derive(Default)]
+struct Level {
+    closed: VecDeque<Tile>,
+    open: Option<Tile>,
+}
+
+pub struct TilePyramid {
+    levels: Vec<Level>,
+    last_t: Option<f64>,
+    stale: bool,
+}
+
+impl Default for TilePyramid {
+    fn default() -> Self {
+        Self {
+            levels: (0..LEVELS).map(|_| Level::default()).collect(),
+            last_t: None,
+            stale: false,
+        }
+    }
+}
+
+impl TilePyramid {
+    /// Tile bytes per buffered sample, summed over the levels (1/16 + 1/256 + ... < 1/15).
+    pub const BYTES_PER_SAMPLE: usize = std::mem::size_of::<Tile>() / (TILE_FACTOR - 1);
+
+    pub fn push(&mut self, p: Point) {
+        if self.stale {
+            return;
+        }
+        if self.last_t.is_some_and(|t| p.x <= t) {
+            self.stale = true;
+            return;
+        }
+        self.last_t = Some(p.x);
+
+        let mut carry = Tile::from_point(p);
+        let mut size = 1;
+        for level in &mut self.levels {
+            size *= TILE_FACTOR;
+            let open = match &mut level.open {
+                Some(open) => {
+                    open.merge(&carry);
+                    open
+                }
+                None => level.open.insert(carry),
+            };
+            if open.count < size {
+                return;
+            }
+            carry = *open;
+            level.closed.push_back(carry);
+            level.open = None;
+        }
+    }
+
+    /// Drops the tiles that cover samples from before `oldest`, the buffer's first timestamp.
+    pub fn trim(&mut self, oldest: f64) {
+        for level in &mut self.levels {
+            while level.closed.front().is_some_and(|t| t.t_start < oldest) {
+                level.closed.pop_front();
+            }
+        }
+    }
+
+    pub fn rebuild(&mut self, ring: &SampleRing) {
+        *self = Self::default();
+        for p in ring.points(0..ring.len()) {
+            self.push(p);
+        }
+    }
+
+    /// Heap bytes held, including spare capacity.
+    pub fn allocated_bytes(&self) -> usize {
+        self.levels
+            .iter()
+            .map(|l| l.closed.capacity() * std::mem::size_of::<Tile>())
+            .sum()
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    /// The envelope of `ring`'s samples in `range`, as the extremes of consecutive buckets of
+    /// about `samples_per_bucket` samples, shifted by `offset`.
+    pub fn envelope(
+        &self,
+        ring: &SampleRing,
+        range: Range<usize>,
+        samples_per_bucket: usize,
+        offset: f64,
+        out: &mut Vec<Point>,
+    ) {
+        let mut bucket = Bucketer {
+            size: samples_per_bucket.max(1),
+            current: None,
+            offset,
+            out,
+        };
+        // The deepest level with at least four tiles to a bucket, so bucket sizes stay close.
+        let depth = (1..=LEVELS)
+            .take_while(|&l| 4 * TILE_FACTOR.pow(l as u32) <= samples_per_bucket)
+            .count();
+        let tiles = match depth.checked_sub(1) {
+            Some(i) if !self.stale && !range.is_empty() => {
+                let closed = &self.levels[i].closed;
+                // Tiles starting before the ring may still hold samples trimmed while open.
+                let lo = ring.point(range.start).x;
+                let hi = ring.point(range.end - 1).x;
+                let first = closed.partition_point(|t| t.t_start < lo);
+                let last = closed.partition_point(|t| t.t_end <= hi);
+                (first < last).then_some((closed, first..last))
+            }
+            _ => None,
+        };
+        let Some((closed, covered)) = tiles else {
+            bucket.extend(ring.points(range));
+            bucket.finish();
+            return;
+        };
+
+        let times = ring.timestamps();
+        let head_end = closed[covered.start].t_start;
+        let tail_start = closed[covered.end - 1].t_end;
+        bucket.extend(ring.points(range.start..times.partition_point(|t| *t < head_end)));
+        for tile in closed.range(covered) {
+            bucket.merge(tile);
+        }
+        bucket.extend(ring.points(times.partition_point(|t| *t <= tail_start)..range.end));
+        bucket.finish();
+    }
+}
+
+/// The envelope of `points`, which have no tiles (e.g. read back from disk), bucketed like
+/// `TilePyramid::envelope`.
+pub fn envelope_of(points: &[Point], samples_per_bucket: usize, offset: f64, out: &mut Vec<Point>) {
+    let mut bucket = Bucketer {
+        size: samples_per_bucket.max(1),
+        current: None,
+        offset,
+        out,
+    };
+    bucket.extend(points.iter().copied());
+    bucket.finish();
+}
+
+/// Folds samples and tiles into buckets and writes out each bucket's extremes.
+struct Bucketer<'a> {
+    size: usize,
+    current: Option<Tile>,
+    offset: f64,
+    out: &'a mut Vec<Point>,
+}
+
+impl Bucketer<'_> {
+    fn extend(&mut self, points: impl Iterator<Item = Point>) {
+        for p in points {
+            match &mut self.current {
+                Some(current) => current.push(p),
+                None => self.current = Some(Tile::from_point(p)),
+            }
+            self.flush_if_full();
+        }
+    }
+
+    fn merge(&mut self, tile: &Tile) {
+        // Close the bucket first if the tile would overfill it by more than it fills it.
+        if self
+            .current
+            .is_some_and(|c| 2 * c.count + tile.count > 2 * self.size)
+        {
+            self.finish();
+        }
+        match &mut self.current {
+            Some(current) => current.merge(tile),
+            None => self.current = Some(*tile),
+        }
+        self.flush_if_full();
+    }
+
+    fn flush_if_full(&mut self) {
+        if self.current.is_some_and(|c| c.count >= self.size) {
+            self.finish();
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(tile) = self.current.take() {
+            self.out.extend(tile.extremes().map(|p| Point {
+                x: p.x + self.offset,
+                y: p.y,
+            }));
+        }
+    }
+}
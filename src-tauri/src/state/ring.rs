@@ -0,0 +1,113 @@
+//! Sample storage for one column in one session: timestamps and values in two contiguous,
+//! time-sorted rings, oldest first. Appending in order and evicting from the front are O(1);
+//! range queries are binary searches over the timestamps.
+use crate::shared::Point;
+use std::collections::VecDeque;
+use std::ops::Range;
+
+#[derive(Clone, Debug, Default)]
+pub struct SampleRing {
+    timestamps: VecDeque<f64>,
+    values: VecDeque<f64>,
+}
+
+impl SampleRing {
+    pub const BYTES_PER_SAMPLE: usize = 2 * std::mem::size_of::<f64>();
+
+    pub fn with_capacity(cap: usize) -> Self {
+        Self {
+            timestamps: VecDeque::with_capacity(cap),
+            values: VecDeque::with_capacity(cap),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.timestamps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timestamps.is_empty()
+    }
+
+    pub fn first_time(&self) -> Option<f64> {
+        self.timestamps.front().copied()
+    }
+
+    pub fn last_time(&self) -> Option<f64> {
+        self.timestamps.back().copied()
+    }
+
+    /// Heap bytes held, including spare capacity.
+    pub fn allocated_bytes(&self) -> usize {
+        (self.timestamps.capacity() + self.values.capacity()) * std::mem::size_of::<f64>()
+    }
+
+    pub fn timestamps(&self) -> &VecDeque<f64> {
+        &self.timestamps
+    }
+
+    pub fn values(&self) -> &VecDeque<f64> {
+        &self.values
+    }
+
+    /// Adds a sample, keeping the ring sorted; a sample at an existing timestamp replaces it.
+    /// Returns false if it didn't go on the end.
+    pub fn push(&mut self, t: f64, y: f64) -> bool {
+        if self.timestamps.back().is_none_or(|last| *last < t) {
+            self.timestamps.push_back(t);
+            self.values.push_back(y);
+            return true;
+        }
+        let i = self.timestamps.partition_point(|x| *x < t);
+        if self.timestamps.get(i) == Some(&t) {
+            self.values[i] = y;
+        } else {
+            self.timestamps.insert(i, t);
+            self.values.insert(i, y);
+        }
+        false
+    }
+
+    /// Drops the oldest samples until at most `cap` are left.
+    pub fn evict_to(&mut self, cap: usize) {
+        let excess = self.len().saturating_sub(cap);
+        if excess > 0 {
+            self.timestamps.drain(..excess);
+            self.values.drain(..excess);
+        }
+    }
+
+    /// Like `evict_to`, appending the evicted samples to `out`.
+    pub fn evict_into(&mut self, cap: usize, out: &mut Vec<Point>) {
+        let excess = self.len().saturating_sub(cap);
+        out.extend(self.points(0..excess));
+        self.evict_to(cap);
+    }
+
+    /// Releases spare capacity beyond `cap` samples.
+    pub fn shrink_to(&mut self, cap: usize) {
+        self.timestamps.shrink_to(cap);
+        self.values.shrink_to(cap);
+    }
+
+    /// Indices of the samples with `lo <= t <= hi`.
+    pub fn range(&self, lo: f64, hi: f64) -> Range<usize> {
+        let start = self.timestamps.partition_point(|t| *t < lo);
+        let end = self.timestamps.partition_point(|t| *t <= hi);
+        start..end.max(start)
+    }
+
+    pub fn point(&self, i: usize) -> Point {
+        Point {
+            x: self.timestamps[i],
+            y: self.values[i],
+        }
+    }
+
+    pub fn points(&self, range: Range<usize>) -> impl Iterator<Item = Point> + '_ {
+        self.timestamps
+            .range(range.clone())
+            .zip(self.values.range(range))
+            .map(|(x, y)| Point { x: *x, y: *y })
+    }
+}
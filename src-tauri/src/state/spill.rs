@@ -0,0 +1,239 @@
+//! On-disk overflow for one capture buffer (a column in a session). Samples evicted from memory
+//! are appended, in time order, to segment files of `SEGMENT_SAMPLES` little-endian
+//! `(t, y)` pairs under a per-process directory in the system temp dir. Whole segments are
+//! deleted once they fall out of the retention, and all of them when the store is dropped;
+//! directories left by processes that died are removed at startup.
+use crate::shared::Point;
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use sysinfo::{Pid, ProcessesToUpdate, System};
+use uuid::Uuid;
+
+/// 4 MiB per segment file.
+const SEGMENT_SAMPLES: u64 = 1 << 18;
+const SAMPLE_BYTES: u64 = 16;
+
+fn spill_root() -> PathBuf {
+    std::env::temp_dir().join("trendline-spill")
+}
+
+/// Removes the spill directories of processes that are no longer running.
+pub fn remove_stale_spill_dirs() {
+    let Ok(entries) = fs::read_dir(spill_root()) else {
+        return;
+    };
+    let mut sys = System::new();
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|s| s.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let pid = Pid::from_u32(pid);
+        sys.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+        if sys.process(pid).is_none() && fs::remove_dir_all(entry.path()).is_ok() {
+            println!("[Capture] Removed stale spill directory {:?}", entry.path());
+        }
+    }
+}
+
+/// A segment file as of `SpillStore::segments`; readable without holding the store.
+#[derive(Clone, Debug)]
+pub struct SpilledSegment {
+    path: PathBuf,
+    t_start: f64,
+    t_end: f64,
+    len: u64,
+}
+
+impl SpilledSegment {
+    fn time_at(file: &mut File, i: u64) -> io::Result<f64> {
+        let mut bytes = [0u8; 8];
+        file.seek(SeekFrom::Start(i * SAMPLE_BYTES))?;
+        file.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    /// Appends the samples with `lo <= t <= hi` to `out`.
+    fn read(&self, lo: f64, hi: f64, out: &mut Vec<Point>) -> io::Result<()> {
+        let mut file = File::open(&self.path)?;
+        // First sample at or after `lo`.
+        let (mut start, mut end) = (0, self.len);
+        while start < end {
+            let mid = (start + end) / 2;
+            if Self::time_at(&mut file, mid)? < lo {
+                start = mid + 1;
+            } else {
+                end = mid;
+            }
+        }
+        file.seek(SeekFrom::Start(start * SAMPLE_BYTES))?;
+        let mut reader = BufReader::new(file);
+        let mut record = [0u8; SAMPLE_BYTES as usize];
+        for _ in start..self.len {
+            reader.read_exact(&mut record)?;
+            let (t, y) = record.split_at(8);
+            let x = f64::from_le_bytes(t.try_into().unwrap());
+            if x > hi {
+                break;
+            }
+            out.push(Point {
+                x,
+                y: f64::from_le_bytes(y.try_into().unwrap()),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Reads the samples with `lo <= t <= hi` from `segments`. Segments deleted since they were
+/// listed are skipped.
+pub fn read_spilled(segments: &[SpilledSegment], lo: f64, hi: f64) -> Vec<Point> {
+    let mut out = Vec::new();
+    for segment in segments {
+        if segment.t_end < lo || segment.t_start > hi {
+            continue;
+        }
+        if let Err(e) = segment.read(lo, hi, &mut out) {
+            if e.kind() != io::ErrorKind::NotFound {
+                println!(
+                    "[Capture] Failed to read spill segment {:?}: {}",
+                    segment.path, e
+                );
+            }
+        }
+    }
+    out
+}
+
+pub struct SpillStore {
+    id: Uuid,
+    /// Segments are kept while they end within this many seconds of the newest spilled sample.
+    retain_seconds: f64,
+    segments: VecDeque<SpilledSegment>,
+    /// Appends to the last segment.
+    writer: Option<BufWriter<File>>,
+    next_segment: u32,
+    /// Set after an I/O error; evicted samples are dropped from then on.
+    failed: bool,
+}
+
+impl SpillStore {
+    pub fn new(retain_seconds: f64) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            retain_seconds,
+            segments: VecDeque::new(),
+            writer: None,
+            next_segment: 0,
+            failed: false,
+        }
+    }
+
+    pub fn set_retention(&mut self, retain_seconds: f64) {
+        self.retain_seconds = retain_seconds;
+        self.expire();
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.segments.iter().map(|s| s.len * SAMPLE_BYTES).sum()
+    }
+
+    /// Appends `points`, dropping any at or before the last spilled sample.
+    pub fn append(&mut self, points: &[Point]) {
+        if self.failed || points.is_empty() {
+            return;
+        }
+        if let Err(e) = self.write(points) {
+            println!(
+                "[Capture] Spill to disk failed, evicted samples are dropped: {}",
+                e
+            );
+            self.failed = true;
+            self.writer = None;
+            return;
+        }
+        self.expire();
+    }
+
+    fn write(&mut self, points: &[Point]) -> io::Result<()> {
+        for p in points {
+            if self.segments.back().is_some_and(|s| p.x <= s.t_end) {
+                continue;
+            }
+            if self.writer.is_none()
+                || self
+                    .segments
+                    .back()
+                    .is_some_and(|s| s.len >= SEGMENT_SAMPLES)
+            {
+                self.open_segment(p.x)?;
+            }
+            let writer = self.writer.as_mut().expect("segment was just opened");
+            writer.write_all(&p.x.to_le_bytes())?;
+            writer.write_all(&p.y.to_le_bytes())?;
+            let segment = self.segments.back_mut().expect("segment was just opened");
+            segment.t_end = p.x;
+            segment.len += 1;
+        }
+        Ok(())
+    }
+
+    fn open_segment(&mut self, t_start: f64) -> io::Result<()> {
+        if let Some(mut writer) = self.writer.take() {
+            writer.flush()?;
+        }
+        let dir = spill_root().join(std::process::id().to_string());
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}-{}.bin", self.id, self.next_segment));
+        self.next_segment += 1;
+        self.writer = Some(BufWriter::new(File::create(&path)?));
+        self.segments.push_back(SpilledSegment {
+            path,
+            t_start,
+            t_end: t_start,
+            len: 0,
+        });
+        Ok(())
+    }
+
+    /// Deletes the closed segments that ended before the retention window.
+    fn expire(&mut self) {
+        let Some(newest) = self.segments.back().map(|s| s.t_end) else {
+            return;
+        };
+        while self.segments.len() > 1
+            && self
+                .segments
+                .front()
+                .is_some_and(|s| s.t_end < newest - self.retain_seconds)
+        {
+            if let Some(segment) = self.segments.pop_front() {
+                let _ = fs::remove_file(&segment.path);
+            }
+        }
+    }
+
+    /// The segments written so far, flushed so they can be read.
+    pub fn segments(&mut self) -> Vec<SpilledSegment> {
+        if let Some(writer) = self.writer.as_mut() {
+            if let Err(e) = writer.flush() {
+                println!("[Capture] Failed to flush spill segment: {}", e);
+            }
+        }
+        self.segments.iter().cloned().collect()
+    }
+}
+
+impl Drop for SpillStore {
+    fn drop(&mut self) {
+        self.writer = None;
+        for segment in &self.segments {
+            let _ = fs::remove_file(&segment.path);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+//! Maps each session's device time onto the unified time axis shared by every stream.
+//!
+//! The unified axis is Unix wall-clock seconds. Streams whose segment epoch is `Unix` already
+//! timestamp their samples in Unix seconds and are placed as-is, so devices on different ports
+//! line up by their own clocks. Every other epoch counts from an arbitrary origin (power-up for
+//! `Zero`); those sessions are anchored to the host clock when their first batch arrives, and
+//! later sessions are stitched on by host `Instant` gaps. Virtual columns are written in unified
+//! time to begin with.
+use crate::shared::MetadataEpoch;
+use crate::state::capture::{SessionId, SessionMeta, TimeOffset, UnifiedTime};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// A `Unix` epoch device whose clock was never set reports times near 1970; treat anything
+/// before 2000-01-01 as unset.
+const MIN_PLAUSIBLE_UNIX_TIME: f64 = 946_684_800.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeReference {
+    /// Device time is Unix seconds.
+    Absolute,
+    /// Device time has an arbitrary origin; position it by when the host received it.
+    #[default]
+    HostAnchored,
+    /// Samples are already in unified time (virtual columns).
+    Unified,
+}
+
+impl From<&MetadataEpoch> for TimeReference {
+    fn from(epoch: &MetadataEpoch) -> Self {
+        match epoch {
+            MetadataEpoch::Unix => TimeReference::Absolute,
+            _ => TimeReference::HostAnchored,
+        }
+    }
+}
+
+/// Host wall-clock time (Unix seconds) of `instant`. Anchored once so live sessions agree with
+/// each other even if the system clock is adjusted later.
+pub fn instant_to_unix(instant: Instant) -> f64 {
+    static ANCHOR: OnceLock<(Instant, f64)> = OnceLock::new();
+    let (anchor_instant, anchor_unix) = *ANCHOR.get_or_init(|| {
+        let unix = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        (Instant::now(), unix)
+    });
+    if instant >= anchor_instant {
+        anchor_unix + instant.duration_since(anchor_instant).as_secs_f64()
+    } else {
+        anchor_unix - anchor_instant.duration_since(instant).as_secs_f64()
+    }
+}
+
+/// Offsets that take each session's device time to unified time.
+pub fn compute_offsets(
+    reference: TimeReference,
+    mut sessions: Vec<(SessionId, SessionMeta)>,
+) -> HashMap<SessionId, TimeOffset> {
+    sessions.sort_by_key(|(_id, meta)| meta.first_instant);
+
+    let mut offsets = HashMap::with_capacity(sessions.len());
+    let mut last_session_unified_end_time: UnifiedTime = 0.0;
+    let mut last_meta: Option<SessionMeta> = None;
+
+    for (session_id, current_meta) in sessions {
+        let offset = if reference == TimeReference::Unified
+            || (reference == TimeReference::Absolute
+                && current_meta.first_device_time >= MIN_PLAUSIBLE_UNIX_TIME)
+        {
+            0.0
+        } else if let Some(prev_meta) = last_meta.as_ref() {
+            let gap = current_meta
+                .first_instant
+                .duration_since(prev_meta.last_instant);
+            last_session_unified_end_time + gap.as_secs_f64() - current_meta.first_device_time
+        } else {
+            current_meta.first_host_time - current_meta.first_device_time
+        };
+
+        offsets.insert(session_id, offset);
+        last_session_unified_end_time = current_meta.last_device_time + offset;
+        last_meta = Some(current_meta);
+    }
+    offsets
+}
@@ -0,0 +1,254 @@
+//! Scaffolding shared by the virtual ports (math channels, lock-ins): each entry is a stream of
+//! its own on a root device, computed by a runner thread that subscribes to its inputs and writes
+//! through `VirtualColumnWriter`s. The port is announced to the front-end like a real one that
+//! finished discovery, and removed once its last stream is.
+use crate::shared::{DataColumnId, DeviceMeta, Point, PortState, UiDevice, UiStream};
+use crate::state::capture::{BatchedData, CaptureCommand, CaptureState, VirtualColumnWriter};
+use crossbeam::channel::{bounded, select, Receiver, Sender};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tauri::{AppHandle, Emitter};
+use twinleaf::tio::proto::DeviceRoute;
+
+/// What a virtual port keeps about each of its streams and shows as its `UiStream`.
+pub trait VirtualStreamInfo: Clone {
+    fn ui_stream(&self, stream_id: u8, sample_rate: f64) -> UiStream;
+}
+
+/// Computes one stream from the batches of its inputs, on its own thread.
+pub trait VirtualRunner: Send + 'static {
+    /// Computes the stream over the history already in capture (see `buffered_history`).
+    fn hydrate(&mut self);
+    fn push(&mut self, key: &DataColumnId, points: &[Point]);
+}
+
+struct RunningStream<I> {
+    info: I,
+    columns: Vec<DataColumnId>,
+    sample_rate: f64,
+    subscriptions: Vec<(DataColumnId, usize)>,
+    stop_tx: Sender<()>,
+    handle: JoinHandle<()>,
+}
+
+pub struct VirtualPort<I> {
+    url: &'static str,
+    device_name: &'static str,
+    /// Log prefix, e.g. `Math`.
+    tag: &'static str,
+    /// What one stream is called in messages, e.g. `math channel`.
+    noun: &'static str,
+    /// Keyed by stream id.
+    streams: Mutex<BTreeMap<u8, RunningStream<I>>>,
+    capture: CaptureState,
+    app: AppHandle,
+}
+
+impl<I: VirtualStreamInfo> VirtualPort<I> {
+    pub fn new(
+        url: &'static str,
+        device_name: &'static str,
+        tag: &'static str,
+        noun: &'static str,
+        app: AppHandle,
+        capture: CaptureState,
+    ) -> Self {
+        Self {
+            url,
+            device_name,
+            tag,
+            noun,
+            streams: Mutex::new(BTreeMap::new()),
+            capture,
+            app,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.streams.lock().unwrap().is_empty()
+    }
+
+    pub fn list(&self) -> Vec<I> {
+        self.streams
+            .lock()
+            .unwrap()
+            .values()
+            .map(|s| s.info.clone())
+            .collect()
+    }
+
+    /// Takes a free stream id, registers `n_columns` virtual columns on it, subscribes to
+    /// `inputs` and starts the runner `build` makes from the column writers. Announces the
+    /// updated device.
+    pub fn start<R: VirtualRunner>(
+        &self,
+        n_columns: usize,
+        sample_rate: f64,
+        inputs: &[DataColumnId],
+        build: impl FnOnce(Vec<VirtualColumnWriter>) -> (I, R),
+    ) -> Result<I, String> {
+        let mut streams = self.streams.lock().unwrap();
+        let stream_id = (1..=u8::MAX)
+            .find(|id| !streams.contains_key(id))
+            .ok_or_else(|| format!("Too many {}s.", self.noun))?;
+        let columns: Vec<DataColumnId> = (0..n_columns)
+            .map(|column_index| DataColumnId {
+                port_url: self.url.to_string(),
+                device_route: DeviceRoute::root(),
+                stream_id,
+                column_index,
+            })
+            .collect();
+
+        // Registering also clears whatever an earlier stream with this id left behind.
+        let writers = columns
+            .iter()
+            .map(|key| {
+                self.capture
+                    .register_virtual_column(key.clone(), sample_rate)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let (info, runner) = build(writers);
+
+        let tx = &self.capture.inner.command_tx;
+        let (data_tx, data_rx) = bounded(128);
+        let mut subscriptions = Vec::with_capacity(inputs.len());
+        for input in inputs {
+            let id = self.capture.next_subscriber_id();
+            tx.send(CaptureCommand::Subscribe {
+                key: input.clone(),
+                id,
+                tx: data_tx.clone(),
+            })
+            .map_err(|e| e.to_string())?;
+            subscriptions.push((input.clone(), id));
+        }
+
+        let (stop_tx, stop_rx) = bounded(1);
+        let handle = thread::Builder::new()
+            .name(format!("{}-{}", self.noun.replace(' ', "-"), stream_id))
+            .spawn(move || run(runner, data_rx, stop_rx))
+            .map_err(|e| format!("Failed to spawn {} thread: {}", self.noun, e))?;
+
+        streams.insert(
+            stream_id,
+            RunningStream {
+                info: info.clone(),
+                columns,
+                sample_rate,
+                subscriptions,
+                stop_tx,
+                handle,
+            },
+        );
+        drop(streams);
+
+        self.announce();
+        Ok(info)
+    }
+
+    /// Stops the stream writing to `key` (any of its columns) and drops its columns from capture.
+    pub fn remove(&self, key: &DataColumnId) -> Result<(), String> {
+        let mut streams = self.streams.lock().unwrap();
+        let stream = (key.port_url == self.url)
+            .then(|| streams.remove(&key.stream_id))
+            .flatten()
+            .ok_or_else(|| format!("No {} writes to {:?}.", self.noun, key))?;
+
+        let tx = &self.capture.inner.command_tx;
+        for (input, id) in stream.subscriptions {
+            let _ = tx.send(CaptureCommand::Unsubscribe { key: input, id });
+        }
+        drop(stream.stop_tx);
+        if let Err(e) = stream.handle.join() {
+            eprintln!(
+                "[{}] Thread for {} {} panicked: {:?}",
+                self.tag, self.noun, key.stream_id, e
+            );
+        }
+
+        for column in &stream.columns {
+            self.capture.unregister_virtual_column(column);
+        }
+        println!("[{}] Removed {} {}", self.tag, self.noun, key.stream_id);
+        let now_empty = streams.is_empty();
+        drop(streams);
+
+        if now_empty {
+            self.app
+                .emit("device-removed", self.url.to_string())
+                .unwrap();
+        } else {
+            self.announce();
+        }
+        Ok(())
+    }
+
+    /// The port's device with one stream per entry, or `None` while there are none.
+    pub fn ui_device(&self) -> Option<UiDevice> {
+        let streams = self.streams.lock().unwrap();
+        if streams.is_empty() {
+            return None;
+        }
+        let streams = streams
+            .iter()
+            .map(|(&stream_id, s)| s.info.ui_stream(stream_id, s.sample_rate))
+            .collect::<Vec<_>>();
+        Some(UiDevice {
+            url: self.url.to_string(),
+            route: DeviceRoute::root().to_string(),
+            state: PortState::Streaming,
+            meta: DeviceMeta {
+                serial_number: String::new(),
+                firmware_hash: String::new(),
+                n_streams: streams.len(),
+                session_id: 0,
+                name: self.device_name.to_string(),
+            },
+            streams,
+            rpcs: Vec::new(),
+        })
+    }
+
+    /// Tells the front-end about the current streams, like a port finishing discovery.
+    fn announce(&self) {
+        let Some(device) = self.ui_device() else {
+            return;
+        };
+        self.app
+            .emit(
+                "port-state-changed",
+                (self.url.to_string(), PortState::Streaming),
+            )
+            .unwrap();
+        self.app
+            .emit("port-devices-discovered", vec![device])
+            .unwrap();
+    }
+}
+
+fn run(mut runner: impl VirtualRunner, data_rx: Receiver<Arc<BatchedData>>, stop_rx: Receiver<()>) {
+    runner.hydrate();
+    loop {
+        select! {
+            recv(data_rx) -> msg => match msg {
+                Ok(batch) => runner.push(&batch.key, &batch.points),
+                Err(_) => break,
+            },
+            recv(stop_rx) -> _ => break,
+        }
+    }
+}
+
+/// The buffered history of `keys`, as far back as the longest of their retentions and up to their
+/// latest common timestamp. Batches that arrive while a runner computes over it overlap it, so
+/// `push` has to skip them point by point.
+pub fn buffered_history(capture: &CaptureState, keys: &[DataColumnId]) -> Option<Vec<Vec<Point>>> {
+    let latest = capture.get_latest_unified_timestamp(keys)?;
+    let seconds = keys
+        .iter()
+        .map(|k| capture.retention_seconds(k))
+        .fold(0.0, f64::max);
+    Some(capture.get_data_across_sessions_for_keys(keys, latest - seconds, latest))
+}
@@ -3,7 +3,10 @@ use serde_json::{json, Value};
 use sysinfo::System;
 use tauri::menu::{Menu, MenuItemKind, Submenu};
 use tauri::Runtime;
-use twinleaf::tio::proto::DeviceRoute;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::time::{Duration, Instant};
+use twinleaf::tio::proto::{DeviceRoute, Packet, Payload, RpcMethod, RpcRequestPayload};
 use twinleaf::tio::util::TioRpcReplyable;
 
 use crate::shared::{HealthSet, PlotData, Point, StatisticSet};
@@ -32,10 +35,10 @@ pub struct SerialDevice {
     url: String,
     ifc: TwinleafPortInterface,
 }
-#[expect(unused)]
 pub struct LANDevice {
-    ip: String,
-    mac: String,
+    pub url: String,
+    /// `dev.name` as reported in the probe reply.
+    pub name: String,
 }
 
 pub fn enum_serial_devices(all: bool) -> Vec<SerialDevice> {
@@ -68,11 +71,73 @@ pub fn enum_serial_devices(all: bool) -> Vec<SerialDevice> {
     ports
 }
 
-#[expect(unused)]
-pub fn enum_lan_devices(all: bool) -> Vec<LANDevice> {
-    let mut ips: Vec<LANDevice> = Vec::new();
+/// Port TIO network endpoints (and `tio-proxy`) listen on, for both TCP and UDP.
+pub const TIO_NET_PORT: u16 = 7855;
 
-    ips
+/// Broadcasts a `dev.name` RPC over UDP and collects every endpoint that answers with a TIO
+/// reply within `timeout`. A local `tio-proxy` is reported as `tcp://localhost`.
+pub fn enum_lan_devices(timeout: Duration) -> Vec<LANDevice> {
+    let mut devices: Vec<LANDevice> = Vec::new();
+
+    if is_process_running("tio-proxy") {
+        devices.push(LANDevice {
+            url: "tcp://localhost".to_string(),
+            name: "tio-proxy".to_string(),
+        });
+    }
+    if let Err(e) = probe_udp_broadcast(timeout, &mut devices) {
+        eprintln!("[Discovery] LAN probe failed: {}", e);
+    }
+    devices
+}
+
+fn probe_udp_broadcast(timeout: Duration, devices: &mut Vec<LANDevice>) -> std::io::Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_broadcast(true)?;
+
+    let probe = Packet {
+        payload: Payload::RpcRequest(RpcRequestPayload {
+            id: 0,
+            method: RpcMethod::Name("dev.name".to_string()),
+            arg: Vec::new(),
+        }),
+        routing: DeviceRoute::root(),
+        ttl: 0,
+    }
+    .serialize()
+    .map_err(|_| std::io::Error::other("failed to encode probe"))?;
+    socket.send_to(&probe, (Ipv4Addr::BROADCAST, TIO_NET_PORT))?;
+
+    let deadline = Instant::now() + timeout;
+    let mut buf = [0u8; 2048];
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(());
+        }
+        socket.set_read_timeout(Some(remaining))?;
+        match socket.recv_from(&mut buf) {
+            Ok((n, addr)) => {
+                let Ok((packet, _)) = Packet::deserialize(&buf[..n]) else {
+                    continue;
+                };
+                let Payload::RpcReply(reply) = packet.payload else {
+                    continue;
+                };
+                let url = format!("tcp://{}", addr.ip());
+                if devices.iter().all(|d| d.url != url) {
+                    devices.push(LANDevice {
+                        url,
+                        name: String::from_utf8_lossy(&reply.reply).into_owned(),
+                    });
+                }
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                return Ok(())
+            }
+            Err(e) => return Err(e),
+        }
+    }
 }
 
 pub fn get_valid_twinleaf_serial_urls() -> Vec<String> {
@@ -368,6 +433,26 @@ pub fn lerp(p1: &Point, p2: &Point, x: f64) -> f64 {
     p1.y + (p2.y - p1.y) * (x - p1.x) / (p2.x - p1.x)
 }
 
+/// Linear interpolation of `(t, v)`, sampled at `sample_rate`, at `grid`. NaN where a grid time
+/// falls outside `t` or between two samples more than 1.5 periods apart, i.e. across dropped
+/// samples.
+pub fn resample_linear(t: &[f64], v: &[f64], grid: &[f64], sample_rate: f64) -> Vec<f64> {
+    let max_step = 1.5 / sample_rate;
+    grid.iter()
+        .map(|&g| {
+            let i = t.partition_point(|&ti| ti < g);
+            if i < t.len() && (t[i] - g).abs() < 1e-9 {
+                return v[i];
+            }
+            if i == 0 || i >= t.len() || t[i] - t[i - 1] > max_step {
+                return f64::NAN;
+            }
+            let frac = (g - t[i - 1]) / (t[i] - t[i - 1]);
+            v[i - 1] + (v[i] - v[i - 1]) * frac
+        })
+        .collect()
+}
+
 pub fn k_way_merge_plot_data(all_series_data: Vec<PlotData>) -> PlotData {
     if all_series_data.is_empty() {
         return PlotData::empty();
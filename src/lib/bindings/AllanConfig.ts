@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AllanDeviation } from "./AllanDeviation";
+
+export type AllanConfig = { 
+/**
+ * History the pipeline keeps of a live column, beyond the capture buffer. Columns of an
+ * open recording are analysed over the whole log instead.
+ */
+window_seconds: number, deviation: AllanDeviation, 
+/**
+ * Averaging times per decade of τ, log-spaced from one sample period.
+ */
+taus_per_decade: number, };
@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AllanSource } from "./AllanSource";
+import type { DataColumnId } from "./DataColumnId";
+
+export type AllanStatus = { key: DataColumnId, source: AllanSource, 
+/**
+ * Length of the data analysed, gaps included.
+ */
+span_seconds: number, 
+/**
+ * Shortest τ: the sample period, or a multiple of it once long histories are pre-averaged.
+ */
+tau0: number, 
+/**
+ * Sample periods that were dropped or NaN.
+ */
+missing_samples: number, };
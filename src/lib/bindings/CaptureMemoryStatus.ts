@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { RetentionConfig } from "./RetentionConfig";
+import type { StreamMemory } from "./StreamMemory";
+
+export type CaptureMemoryStatus = { config: RetentionConfig, 
+/**
+ * Applied to every stream's retention to fit the budget; 1 when it fits.
+ */
+scale: number, used_bytes: number, spilled_bytes: number, streams: Array<StreamMemory>, };
@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { CrossQuantity } from "./CrossQuantity";
+import type { DataColumnId } from "./DataColumnId";
+import type { DetrendMethod } from "./DetrendMethod";
+import type { FilterConfig } from "./FilterConfig";
+import type { PsdConfig } from "./PsdConfig";
+
+export type CrossSpectrumConfig = { 
+/**
+ * The input (x) of every pair; each plotted column is an output (y).
+ */
+reference: DataColumnId, window_seconds: number, detrend_method: DetrendMethod, 
+/**
+ * Window, segmenting and frame averaging; `units` and `gap_handling` are not used (gaps
+ * are always interpolated).
+ */
+psd: PsdConfig, quantity: CrossQuantity, filter?: FilterConfig, };
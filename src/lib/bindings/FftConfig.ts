@@ -1,4 +1,6 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { DetrendMethod } from "./DetrendMethod";
+import type { FilterConfig } from "./FilterConfig";
+import type { PsdConfig } from "./PsdConfig";
 
-export type FftConfig = { window_seconds: number, detrend_method: DetrendMethod, };
+export type FftConfig = { window_seconds: number, detrend_method: DetrendMethod, psd: PsdConfig, filter?: FilterConfig, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FilterDesign } from "./FilterDesign";
+import type { FilterKind } from "./FilterKind";
+
+export type FilterConfig = { kind: FilterKind, design: FilterDesign, 
+/**
+ * Butterworth order, or FIR tap count. Unused for IIR notches.
+ */
+order: number, };
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type FilterKind = { "LowPass": { cutoff_hz: number, } } | { "HighPass": { cutoff_hz: number, } } | { "BandPass": { low_hz: number, high_hz: number, } } | { "Notch": { frequency_hz: number, harmonics: number, q: number, } };
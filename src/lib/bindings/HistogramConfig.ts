@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { HistogramBins } from "./HistogramBins";
+
+export type HistogramConfig = { window_seconds: number, bins: HistogramBins, 
+/**
+ * Adds a Gaussian with the window's mean and standard deviation, scaled to the counts, as
+ * a second series per column.
+ */
+fit_gaussian: boolean, };
@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+import type { StatisticSet } from "./StatisticSet";
+
+export type HistogramStatus = { key: DataColumnId, bins: number, bin_width: number, 
+/**
+ * Statistics of the binned values, which the Gaussian fit uses.
+ */
+window: StatisticSet, };
@@ -0,0 +1,27 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+import type { LockInOutput } from "./LockInOutput";
+import type { LockInReference } from "./LockInReference";
+
+export type LockInConfig = { name: string, signal: DataColumnId, reference: LockInReference, 
+/**
+ * Demodulates at this multiple of the reference frequency.
+ */
+harmonic: number, 
+/**
+ * Added to the reference phase, so a signal leading the reference by this much reads as X
+ * only.
+ */
+phase_deg: number, 
+/**
+ * Time constant of each low-pass stage, in seconds.
+ */
+time_constant: number, 
+/**
+ * Number of first-order low-pass stages, 1 to 4 (6 dB/octave each).
+ */
+filter_order: number, output: LockInOutput, 
+/**
+ * Units of the signal, for X, Y and R.
+ */
+units: string, };
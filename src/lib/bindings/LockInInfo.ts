@@ -0,0 +1,10 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+import type { LockInConfig } from "./LockInConfig";
+
+export type LockInInfo = { 
+/**
+ * The two virtual columns the lock-in writes to (X and Y, or R and θ), on the
+ * `virtual://lockin` port.
+ */
+keys: Array<DataColumnId>, config: LockInConfig, };
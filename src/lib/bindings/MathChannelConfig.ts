@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FilterConfig } from "./FilterConfig";
+import type { MathInput } from "./MathInput";
+
+export type MathChannelConfig = { name: string, 
+/**
+ * e.g. `sqrt(Bx^2 + By^2 + Bz^2)`, over the variables in `inputs`.
+ */
+expression: string, inputs: Array<MathInput>, units: string, 
+/**
+ * Applied to the expression's output before it is written.
+ */
+filter?: FilterConfig, };
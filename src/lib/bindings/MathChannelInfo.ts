@@ -0,0 +1,9 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+import type { MathChannelConfig } from "./MathChannelConfig";
+
+export type MathChannelInfo = { 
+/**
+ * The virtual column the channel writes to, on the `virtual://math` port.
+ */
+key: DataColumnId, config: MathChannelConfig, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+
+/**
+ * Binds a variable of a math expression to a column.
+ */
+export type MathInput = { variable: string, key: DataColumnId, };
@@ -0,0 +1,24 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { FrameAveraging } from "./FrameAveraging";
+import type { GapHandling } from "./GapHandling";
+import type { SpectrumUnits } from "./SpectrumUnits";
+import type { WindowFunction } from "./WindowFunction";
+
+export type PsdConfig = { window: WindowFunction, 
+/**
+ * Samples per segment; 0 sizes segments so `averages` of them span the FFT window.
+ */
+segment_length: number, 
+/**
+ * Fraction of each segment shared with the next, 0 to 0.95.
+ */
+overlap: number, 
+/**
+ * Segments averaged per spectrum, the most recent first; 0 uses every segment that fits
+ * (or 8 when `segment_length` is 0 too).
+ */
+averages: number, units: SpectrumUnits, 
+/**
+ * Averaging of successive spectra, applied to the PSD before unit conversion.
+ */
+frame_averaging: FrameAveraging, gap_handling: GapHandling, };
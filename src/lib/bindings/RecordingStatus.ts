@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type RecordingStatus = { is_recording: boolean, path: string | null, current_file: string | null, files_written: number, bytes_written: bigint, samples_written: bigint, 
+/**
+ * Samples that never reached the log because the writer fell behind the capture.
+ */
+samples_dropped: bigint, error: string | null, };
@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type ReplayStatus = { url: string, path: string, is_playing: boolean, speed: number, 
+/**
+ * Seconds since the first sample in the recording.
+ */
+position: number, duration: number, };
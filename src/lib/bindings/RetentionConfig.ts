@@ -0,0 +1,13 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { StreamRetention } from "./StreamRetention";
+
+export type RetentionConfig = { default_seconds: number, streams: Array<StreamRetention>, 
+/**
+ * Ceiling on the estimated size of the active columns' buffers. When the retention asks for
+ * more, every stream's window shrinks by the same factor.
+ */
+memory_budget_mb: number, 
+/**
+ * Keep samples evicted from memory on disk for this many more seconds; none discards them.
+ */
+spill_seconds?: number, };
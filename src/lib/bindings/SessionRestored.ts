@@ -0,0 +1,12 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { SharedPlotConfig } from "./SharedPlotConfig";
+
+export type SessionRestored = { 
+/**
+ * One `session://` port per port saved in the file.
+ */
+urls: Array<string>, path: string, 
+/**
+ * Saved plot configs, with their keys re-homed onto `urls`.
+ */
+plots: Array<SharedPlotConfig>, };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DetrendMethod } from "./DetrendMethod";
+import type { FilterConfig } from "./FilterConfig";
+import type { PsdConfig } from "./PsdConfig";
+
+export type SpectrogramConfig = { 
+/**
+ * Length of each PSD frame; frames overlap by half.
+ */
+window_seconds: number, 
+/**
+ * How far back the waterfall reaches.
+ */
+history_seconds: number, detrend_method: DetrendMethod, 
+/**
+ * Window, segmenting and gap handling; `units` and `frame_averaging` are not used (frames
+ * are always ASD, one per half window).
+ */
+psd: PsdConfig, filter?: FilterConfig, };
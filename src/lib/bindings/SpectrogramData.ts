@@ -0,0 +1,23 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+
+/**
+ * Waterfall frames of one column, sent on the plot's spectrogram channel.
+ */
+export type SpectrogramData = { key: DataColumnId, 
+/**
+ * Whether these frames replace everything sent before instead of appending to it.
+ */
+reset: boolean, 
+/**
+ * Unified time at the end of each frame's window, oldest first.
+ */
+timestamps: Array<number>, 
+/**
+ * Bin centre frequencies (DC excluded), shared by every frame.
+ */
+frequencies: Array<number>, 
+/**
+ * ASD values, row-major: one row of `frequencies.len()` per timestamp.
+ */
+values: Array<number>, };
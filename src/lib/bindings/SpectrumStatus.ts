@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+import type { SpectralMethod } from "./SpectralMethod";
+
+export type SpectrumStatus = { key: DataColumnId, method: SpectralMethod, 
+/**
+ * Samples in the window that were dropped or NaN.
+ */
+missing_samples: number, gaps: number, 
+/**
+ * Welch segments averaged; 0 for Lomb-Scargle.
+ */
+segments: number, 
+/**
+ * Why there is no spectrum, e.g. a window too long for Lomb-Scargle.
+ */
+error: string | null, };
@@ -0,0 +1,20 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+
+export type StreamMemory = { 
+/**
+ * Column 0 of the stream.
+ */
+stream: DataColumnId, columns: number, sample_rate: number, 
+/**
+ * From the config, before scaling to the budget.
+ */
+requested_seconds: number, retained_seconds: number, 
+/**
+ * Allocated by the stream's buffers, across all sessions.
+ */
+bytes: number, 
+/**
+ * Written to the spill files of the stream's buffers.
+ */
+spilled_bytes: number, };
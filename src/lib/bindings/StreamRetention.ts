@@ -0,0 +1,11 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+
+/**
+ * How long one stream's samples are kept, in place of `RetentionConfig::default_seconds`.
+ */
+export type StreamRetention = { 
+/**
+ * Any column of the stream; the column index is ignored.
+ */
+stream: DataColumnId, seconds: number, };
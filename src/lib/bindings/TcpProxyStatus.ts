@@ -0,0 +1,7 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TcpProxyStatus = { port_url: string, 
+/**
+ * `host:port` clients connect to, while the proxy is running.
+ */
+address: string | null, clients: number, error: string | null, };
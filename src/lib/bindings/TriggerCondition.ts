@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export type TriggerCondition = "RisingEdge" | "FallingEdge" | "Level" | { "Window": { upper: number, } } | { "Slope": { rate: number, } } | { "PulseWidth": { positive: boolean, min_seconds: number, max_seconds: number | null, } };
@@ -0,0 +1,19 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+import type { TriggerCondition } from "./TriggerCondition";
+import type { TriggerMode } from "./TriggerMode";
+
+export type TriggerConfig = { 
+/**
+ * Column the condition is evaluated on; it need not be plotted.
+ */
+source: DataColumnId, condition: TriggerCondition, level: number, 
+/**
+ * Captured before and after the trigger; frames are timed relative to it.
+ */
+pre_trigger_seconds: number, post_trigger_seconds: number, mode: TriggerMode, 
+/**
+ * Triggered segments averaged into each frame, most recent first; 0 or 1 shows every
+ * capture as it is. Averaged frames come with a standard deviation series per column.
+ */
+averages: number, };
@@ -0,0 +1,16 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { TriggerState } from "./TriggerState";
+
+export type TriggerStatus = { state: TriggerState, 
+/**
+ * Frames captured on a trigger since the plot was configured or re-armed.
+ */
+triggers: number, 
+/**
+ * Unified time of the trigger of the frame on screen, `None` for a free-running frame.
+ */
+last_trigger_time: number | null, 
+/**
+ * Segments in the running average; 0 when not averaging.
+ */
+averaged: number, };
@@ -1,5 +1,11 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { AllanConfig } from "./AllanConfig";
+import type { CrossSpectrumConfig } from "./CrossSpectrumConfig";
 import type { FftConfig } from "./FftConfig";
+import type { HistogramConfig } from "./HistogramConfig";
+import type { SpectrogramConfig } from "./SpectrogramConfig";
 import type { TimeseriesConfig } from "./TimeseriesConfig";
+import type { TriggerConfig } from "./TriggerConfig";
+import type { XyConfig } from "./XyConfig";
 
-export type ViewConfig = { "Timeseries": TimeseriesConfig } | { "Fft": FftConfig };
+export type ViewConfig = { "Timeseries": TimeseriesConfig } | { "Fft": FftConfig } | { "Spectrogram": SpectrogramConfig } | { "CrossSpectrum": CrossSpectrumConfig } | { "Trigger": TriggerConfig } | { "AllanDeviation": AllanConfig } | { "Histogram": HistogramConfig } | { "Xy": XyConfig };
@@ -0,0 +1,17 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { DataColumnId } from "./DataColumnId";
+import type { FilterConfig } from "./FilterConfig";
+
+export type XyConfig = { 
+/**
+ * The horizontal column; each plotted column is drawn against it.
+ */
+x: DataColumnId, 
+/**
+ * Length of the trace kept on screen.
+ */
+persistence_seconds: number, 
+/**
+ * Fades the trace with age over `persistence_seconds`; drawn by the front-end.
+ */
+decay: boolean, filter?: FilterConfig, };
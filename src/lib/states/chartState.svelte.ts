@@ -3,15 +3,79 @@ import { uiState } from '$lib/states/uiState.svelte';
 import type { DataColumnId } from '$lib/bindings/DataColumnId';
 import type { DecimationMethod } from '$lib/bindings/DecimationMethod';
 import type { DetrendMethod } from '$lib/bindings/DetrendMethod';
+import type { FilterConfig } from '$lib/bindings/FilterConfig';
+import type { PsdConfig } from '$lib/bindings/PsdConfig';
+import type { CrossQuantity } from '$lib/bindings/CrossQuantity';
+import type { TriggerConfig } from '$lib/bindings/TriggerConfig';
+import type { AllanConfig } from '$lib/bindings/AllanConfig';
+import type { HistogramConfig } from '$lib/bindings/HistogramConfig';
+import type { XyConfig } from '$lib/bindings/XyConfig';
 import type { RowSelectionState } from '@tanstack/table-core';
 import type { ExpandedState } from '@tanstack/table-core';
 import { untrack } from 'svelte';
 import { Channel, invoke } from '@tauri-apps/api/core';
 import type { PlotData } from '$lib/bindings/PlotData';
+import type { SpectrogramData } from '$lib/bindings/SpectrogramData';
 import { SvelteMap } from 'svelte/reactivity';
+import { isRootRoute } from '$lib/utils';
 import type { SharedPlotConfig } from '$lib/bindings/SharedPlotConfig';
+import type { ViewConfig } from '$lib/bindings/ViewConfig';
+import type { SessionRestored } from '$lib/bindings/SessionRestored';
+import { listen } from '@tauri-apps/api/event';
 
 export type ChartLayout = 'carousel' | 'vertical' | 'horizontal';
+
+/** Cursor of every uPlot view: no drag-to-zoom or double-click reset. */
+const PLOT_CURSOR: uPlot.Cursor = {
+	drag: {
+		setScale: false
+	},
+	show: true,
+	points: { show: false },
+	move: (_, t, l) => [t, l],
+	bind: {
+		dblclick: (u) => {
+			return null;
+		}
+	}
+};
+
+/** Opacity steps of a decaying XY trace, oldest first. */
+export const XY_DECAY_BANDS = 6;
+
+/** Joins an XY series' (x, y) points in order, breaking the line at missing values. */
+const xyPath: uPlot.Series.PathBuilder = (u, seriesIdx) => {
+	const [xs, ys] = u.data[seriesIdx] as unknown as [ArrayLike<number>, ArrayLike<number>];
+	const stroke = new Path2D();
+	let drawing = false;
+	for (let i = 0; i < xs.length; i++) {
+		if (!Number.isFinite(xs[i]) || !Number.isFinite(ys[i])) {
+			drawing = false;
+			continue;
+		}
+		const cx = u.valToPos(xs[i], 'x', true);
+		const cy = u.valToPos(ys[i], 'y', true);
+		if (drawing) stroke.lineTo(cx, cy);
+		else stroke.moveTo(cx, cy);
+		drawing = true;
+	}
+	return { stroke };
+};
+export type ViewType =
+	| 'timeseries'
+	| 'trigger'
+	| 'fft'
+	| 'cross'
+	| 'spectrogram'
+	| 'allan'
+	| 'histogram'
+	| 'xy';
+/** Trigger settings of a plot; the source column is kept separately. */
+export type TriggerSettings = Omit<TriggerConfig, 'source'>;
+/** Histogram settings of a plot; it bins the timeseries window. */
+export type HistogramSettings = Omit<HistogramConfig, 'window_seconds'>;
+/** XY settings of a plot; the x column and filter are kept separately. */
+export type XySettings = Omit<XyConfig, 'x' | 'filter'>;
 export type StreamLayout = 'grouped' | 'vertical' | 'horizontal';
 
 export interface PlotSeries {
@@ -19,6 +83,14 @@ export interface PlotSeries {
     uPlotSeries: uPlot.Series;
 }
 
+/** The waterfall of one column, accumulated from `SpectrogramData` updates. */
+export interface SpectrogramSeries {
+	timestamps: number[];
+	frequencies: number[];
+	/** Row-major ASD values, one row of `frequencies.length` per timestamp. */
+	values: number[];
+}
+
 /**
  * A utility class to assign and retrieve consistent colors for data columns
  * based on their unique key, ensuring a stable color palette across the application.
@@ -91,14 +163,55 @@ export class PlotConfig {
 	fftSeconds = $state<number>(10.0);
 	/** The detrending method to apply before performing an FFT. */
 	fftDetrendMethod = $state<DetrendMethod>('None');
+	/** Welch estimator settings, output units and frame averaging for the FFT view. */
+	psd = $state<PsdConfig>({
+		window: 'Hann',
+		segment_length: 0,
+		overlap: 0.5,
+		averages: 0,
+		units: 'Asd',
+		frame_averaging: 'None',
+		gap_handling: 'Auto'
+	});
+	/** Digital filter applied to the data before either view, or `null` for raw data. */
+	filter = $state<FilterConfig | null>(null);
 	/** A flag indicating if the plot has received any data from the backend. */
 	hasData = $state(false);
 	/** The most recent timestamp of data received by the plot, used for tracking data flow. */
 	latestTimestamp = $state(0);
 	/** A local pause state for this specific plot, which can override the global pause state. */
 	isPaused = $state(false);
-	/** The type of visualization to display ('timeseries' or 'fft'). */
-	viewType = $state<'timeseries' | 'fft'>('timeseries');
+	/** Input (x) column of the cross-spectrum view; `null` uses the first plotted column. */
+	crossReference = $state<DataColumnId | null>(null);
+	/** What the cross-spectrum view plots for each column against the reference. */
+	crossQuantity = $state<CrossQuantity>('Coherence');
+	/** Column the trigger view watches; `null` uses the first plotted column. */
+	triggerSource = $state<DataColumnId | null>(null);
+	/** Condition, level, capture lengths and mode of the trigger view. */
+	trigger = $state<TriggerSettings>({
+		condition: 'RisingEdge',
+		level: 0,
+		pre_trigger_seconds: 0.1,
+		post_trigger_seconds: 0.4,
+		mode: 'Auto',
+		averages: 0
+	});
+	/** How far back the spectrogram waterfall reaches, in seconds. */
+	spectrogramSeconds = $state<number>(120);
+	/** History, deviation type and τ spacing of the Allan deviation view. */
+	allan = $state<AllanConfig>({
+		window_seconds: 3600,
+		deviation: 'Overlapping',
+		taus_per_decade: 10
+	});
+	/** Horizontal column of the XY view; `null` uses the first plotted column. */
+	xySource = $state<DataColumnId | null>(null);
+	/** Trace length and fading of the XY view. */
+	xy = $state<XySettings>({ persistence_seconds: 5, decay: true });
+	/** Bin width rule and Gaussian overlay of the histogram view. */
+	histogram = $state<HistogramSettings>({ bins: 'Auto', fit_gaussian: true });
+	/** The type of visualization to display ('timeseries', 'trigger', 'fft', 'cross', 'spectrogram', 'allan', 'histogram' or 'xy'). */
+	viewType = $state<ViewType>('timeseries');
 
 	/**
 	 * The data decimation method for the timeseries view.
@@ -159,6 +272,134 @@ export class PlotConfig {
 		return Math.max(...rates);
 	});
 
+	#spectrumLabel(unit: string): string {
+		const units = this.psd.units;
+		if (units === 'Psd') return `${unit}²/Hz`;
+		if (units === 'Asd') return `${unit}/√Hz`;
+		return `dB re ${units.Db.reference} ${unit}/√Hz`;
+	}
+
+	#crossLabel(unit: string): string {
+		switch (this.crossQuantity) {
+			case 'Coherence':
+				return 'Coherence (γ²)';
+			case 'CrossPsd':
+				return `|CSD| (${unit}·ref/Hz)`;
+			case 'TransferMagnitude':
+				return `|H1| (${unit}/ref)`;
+			case 'TransferPhase':
+				return 'Phase (°)';
+			case 'IncoherentAsd':
+				return `Incoherent ${unit}/√Hz`;
+		}
+	}
+
+	#allanLabel(unit: string): string {
+		switch (this.allan.deviation) {
+			case 'Overlapping':
+				return `σ_y(τ) (${unit})`;
+			case 'Modified':
+				return `Mod σ_y(τ) (${unit})`;
+			case 'Time':
+				return `σ_x(τ) (${unit}·s)`;
+		}
+	}
+
+	/**
+	 * Values on x and counts per bin on a single y scale; each column's fit follows all of the
+	 * columns' counts, as the backend merges them.
+	 */
+	#histogramOptions(): uPlot.Options {
+		const units = [...new Set(this.series.map((s) => s.uPlotSeries.scale))].filter((u) => u);
+		const series: uPlot.Series[] = [
+			{},
+			...this.series.map((s) => ({ ...s.uPlotSeries, scale: 'count' }))
+		];
+		if (this.histogram.fit_gaussian) {
+			for (const s of this.series) {
+				series.push({
+					...s.uPlotSeries,
+					label: `${s.uPlotSeries.label} fit`,
+					scale: 'count',
+					dash: [4, 4]
+				});
+			}
+		}
+		return {
+			width: 800,
+			height: 400,
+			series,
+			scales: {
+				x: { time: false },
+				count: { auto: true, range: (_u, _min, max) => [0, max > 0 ? max * 1.05 : 1] }
+			},
+			axes: [
+				{ scale: 'x', label: units.join(', ') },
+				{ scale: 'count', label: 'Counts per bin', labelGap: 5 }
+			],
+			pxAlign: 0,
+			legend: { show: false },
+			cursor: PLOT_CURSOR
+		};
+	}
+
+	/**
+	 * uPlot's faceted (XY) mode, each plotted column against the x column. A decaying trace is
+	 * split into `XY_DECAY_BANDS` series per column, fading with age.
+	 */
+	#xyOptions(): uPlot.Options {
+		const xKey = this.xySource ?? this.series[0].dataKey;
+		const xColumn = deviceState
+			.getDevice(xKey.port_url, xKey.device_route)
+			?.streams.find((s) => s.meta.stream_id === xKey.stream_id)
+			?.columns.find((c) => c.index === xKey.column_index);
+		const xLabel = xColumn
+			? xColumn.units
+				? `${xColumn.name} (${xColumn.units})`
+				: xColumn.name
+			: 'x';
+		const units = [...new Set(this.series.map((s) => s.uPlotSeries.scale))].filter((u) => u);
+		const bands = this.xy.decay ? XY_DECAY_BANDS : 1;
+		const series: uPlot.Series[] = [{}];
+		for (const s of this.series) {
+			for (let b = 0; b < bands; b++) {
+				series.push({
+					label: s.uPlotSeries.label,
+					stroke: s.uPlotSeries.stroke,
+					alpha: (b + 1) / bands,
+					paths: xyPath,
+					points: { show: false },
+					facets: [
+						{ scale: 'x', auto: true },
+						{ scale: 'y', auto: true }
+					]
+				});
+			}
+		}
+		return {
+			mode: 2,
+			width: 800,
+			height: 400,
+			series,
+			scales: { x: { time: false }, y: { auto: true } },
+			axes: [
+				{ scale: 'x', label: xLabel },
+				{ scale: 'y', label: units.join(', '), labelGap: 5 }
+			],
+			pxAlign: 0,
+			legend: { show: false },
+			cursor: PLOT_CURSOR
+		};
+	}
+
+	/** Fixed y range of the bounded cross-spectrum quantities, else `null`. */
+	#crossRange(): [number, number] | null {
+		if (this.viewType !== 'cross') return null;
+		if (this.crossQuantity === 'Coherence') return [0, 1];
+		if (this.crossQuantity === 'TransferPhase') return [-180, 180];
+		return null;
+	}
+
 	/**
 	 * A large derived object that generates the complete configuration for the `uPlot`
 	 * charting library based on the current plot state (series, viewType, axes, etc.).
@@ -173,11 +414,22 @@ export class PlotConfig {
 				axes: [{}, { show: false }]
 			};
 		}
+		if (this.viewType === 'histogram') return this.#histogramOptions();
+		if (this.viewType === 'xy') return this.#xyOptions();
 		const uniqueUnits = new Set(this.series.map((s) => s.uPlotSeries.scale));
+		const isFrequencyView = this.viewType === 'fft' || this.viewType === 'cross';
+		// dB spectra, coherence and phase are plotted on a linear y scale.
+		const fixedRange = this.#crossRange();
+		const isLogY =
+			(this.viewType === 'fft' && typeof this.psd.units !== 'object') ||
+			(this.viewType === 'cross' && !fixedRange) ||
+			this.viewType === 'allan';
 		const scalesConfig: Record<string, uPlot.Scale> = {};
 		for (const unit of uniqueUnits) {
 			if (unit) {
-				if (this.viewType === 'fft') {
+				if (fixedRange) {
+					scalesConfig[unit] = { auto: false, range: fixedRange };
+				} else if (isLogY) {
 					scalesConfig[unit] = {
 						auto: true,
 						range: (u, dataMin, dataMax): [number | null, number | null] => {
@@ -215,7 +467,7 @@ export class PlotConfig {
 			}
 		}
 		const axesConfig: uPlot.Axis[] = [{}];
-		if (this.viewType === 'fft') {
+		if (isFrequencyView) {
 			scalesConfig['x'] = { time: false, distr: 3, log: 10 };
 			const formatHz = (v: number) => {
 				if (v == null || !isFinite(v)) return '';
@@ -233,11 +485,39 @@ export class PlotConfig {
 				return `${n.toFixed(dp)} MHz`;
 			};
 			axesConfig[0] = { scale: 'x', values: (_u, ticks) => ticks.map(formatHz)};
+			for (const unit of uniqueUnits)
+				if (unit && scalesConfig[unit] && isLogY) {
+					scalesConfig[unit].distr = 3;
+					scalesConfig[unit].log = 10;
+				}
+		} else if (this.viewType === 'allan') {
+			scalesConfig['x'] = { time: false, distr: 3, log: 10 };
+			const formatTau = (v: number) => {
+				if (v == null || !isFinite(v)) return '';
+				if (v < 1) return `${+(v * 1e3).toPrecision(3)} ms`;
+				if (v < 1e3) return `${+v.toPrecision(3)} s`;
+				return `${+(v / 1e3).toPrecision(3)} ks`;
+			};
+			axesConfig[0] = { scale: 'x', label: 'τ', values: (_u, ticks) => ticks.map(formatTau) };
 			for (const unit of uniqueUnits)
 				if (unit && scalesConfig[unit]) {
 					scalesConfig[unit].distr = 3;
 					scalesConfig[unit].log = 10;
 				}
+		} else if (this.viewType === 'trigger') {
+			// Frames are timed relative to the trigger; short frames read better in ms.
+			const inMs = this.trigger.pre_trigger_seconds + this.trigger.post_trigger_seconds < 2;
+			scalesConfig['x'] = { time: false };
+			axesConfig[0] = {
+				scale: 'x',
+				space: 80,
+				values: (_, ticks) =>
+					ticks.map((raw) => {
+						if (Math.abs(raw) < 1e-9) return 'T';
+						const sign = raw > 0 ? '+' : '';
+						return inMs ? `${sign}${(raw * 1e3).toFixed(0)}ms` : `${sign}${raw.toFixed(2)}s`;
+					})
+			};
 		} else {
 			scalesConfig['x'] = { time: false };
 			axesConfig[0] = {
@@ -250,7 +530,14 @@ export class PlotConfig {
 		let yAxisCount = 0;
 		for (const unit of uniqueUnits) {
 			if (!unit) continue;
-			const yAxisLabel = this.viewType === 'fft' ? `${unit}/√Hz` : unit;
+			const yAxisLabel =
+				this.viewType === 'fft'
+					? this.#spectrumLabel(unit)
+					: this.viewType === 'cross'
+						? this.#crossLabel(unit)
+						: this.viewType === 'allan'
+							? this.#allanLabel(unit)
+							: unit;
 			const axisOptions: uPlot.Axis = {
 				scale: unit,
 				label: yAxisLabel,
@@ -275,6 +562,12 @@ export class PlotConfig {
 			yAxisCount++;
 		}
 		const uplotSeriesConfig: uPlot.Series[] = [{}, ...this.series.map((s) => s.uPlotSeries)];
+		// Averaged trigger frames carry a standard deviation series after the means.
+		if (this.viewType === 'trigger' && this.trigger.averages > 1) {
+			for (const s of this.series) {
+				uplotSeriesConfig.push({ ...s.uPlotSeries, label: `${s.uPlotSeries.label} σ`, dash: [4, 4] });
+			}
+		}
 		return {
 			width: 800,
 			height: 400,
@@ -283,19 +576,7 @@ export class PlotConfig {
 			axes: axesConfig,
 			pxAlign: 0,
 			legend: { show: false },
-			cursor: {
-				drag: {
-					setScale: false
-				},
-				show: true,
-				points: { show: false },
-				move: (_, t, l) => [t, l],
-				bind: {
-					dblclick: (u) => {
-						return null;
-					}
-				}
-			}
+			cursor: PLOT_CURSOR
 		};
 	});
 
@@ -328,6 +609,8 @@ class ChartState {
 	plots = $state<PlotConfig[]>([]);
 	/** A map from a plot's unique ID to its latest stream of PlotData. */
 	plotsData = new SvelteMap<string, PlotData>();
+	/** Bumped for a plot whenever its spectrogram waterfalls change; read it to redraw. */
+	spectrogramVersions = new SvelteMap<string, number>();
 	/** A global flag to pause or resume data updates for all plots. */
 	isPaused = $state(false);
 
@@ -352,9 +635,21 @@ class ChartState {
 	#latestDataCache = new Map<string, PlotData>();
 	/** A set of plot IDs that have received new data since the last render frame, marking them as "dirty". */
 	#dirtyPlots = new Set<string>();
+	/** A set of plot IDs that have a spectrogram listener on the backend. */
+	#listeningSpectrograms = new Set<string>();
+	/** Non-reactive waterfalls per plot, keyed by the column's JSON key. */
+	#spectrograms = new Map<string, Map<string, SpectrogramSeries>>();
+	/** Plots whose waterfalls changed since the last render frame. */
+	#dirtySpectrograms = new Set<string>();
 	/** A flag to ensure the render loop is only started once. */
 	#isUpdateLoopRunning = false;
 
+	constructor() {
+		listen<SessionRestored>('session-restored', ({ payload }) => {
+			this.restoreSession(payload);
+		});
+	}
+
 	// --- Private Helper for Rate Limiting ---
 	/**
 	 * A helper to prevent rapid execution of state-mutating UI actions like adding or moving plots.
@@ -398,6 +693,14 @@ class ChartState {
 					this.#dirtyPlots.clear();
 				});
 			}
+			if (this.#dirtySpectrograms.size > 0) {
+				untrack(() => {
+					for (const plotId of this.#dirtySpectrograms) {
+						this.spectrogramVersions.set(plotId, (this.spectrogramVersions.get(plotId) ?? 0) + 1);
+					}
+					this.#dirtySpectrograms.clear();
+				});
+			}
 			requestAnimationFrame(loop);
 		};
 		requestAnimationFrame(loop);
@@ -490,21 +793,87 @@ class ChartState {
 				}
 			}
 
-			const viewConfig =
-				plot.viewType === 'timeseries'
-					? {
-							Timeseries: {
-								decimation_method: plot.decimationMethod,
-								window_seconds: plot.windowSeconds,
-								resolution_multiplier: plot.resolutionMultiplier
-							}
-						}
-					: {
-							Fft: {
-								window_seconds: plot.fftSeconds,
-								detrend_method: plot.fftDetrendMethod
-							}
-						};
+			if (plot.viewType === 'spectrogram' && !this.#listeningSpectrograms.has(plot.id)) {
+				const spectrogramChannel = new Channel<SpectrogramData>();
+
+				spectrogramChannel.onmessage = (data) => {
+					this.#applySpectrogramData(plot, data);
+					if (!plot.isPaused) this.#dirtySpectrograms.add(plot.id);
+				};
+
+				try {
+					await invoke('listen_to_spectrogram_data', {
+						plotId: plot.id,
+						onEvent: spectrogramChannel
+					});
+					this.#listeningSpectrograms.add(plot.id);
+				} catch (e) {
+					console.error(`[IPC] Failed to invoke spectrogram listener for plot ${plot.id}`, e);
+				}
+			}
+
+			let viewConfig: ViewConfig;
+			if (plot.viewType === 'timeseries') {
+				viewConfig = {
+					Timeseries: {
+						decimation_method: plot.decimationMethod,
+						window_seconds: plot.windowSeconds,
+						resolution_multiplier: plot.resolutionMultiplier,
+						filter: plot.filter ?? undefined
+					}
+				};
+			} else if (plot.viewType === 'fft') {
+				viewConfig = {
+					Fft: {
+						window_seconds: plot.fftSeconds,
+						detrend_method: plot.fftDetrendMethod,
+						psd: $state.snapshot(plot.psd),
+						filter: plot.filter ?? undefined
+					}
+				};
+			} else if (plot.viewType === 'trigger') {
+				viewConfig = {
+					Trigger: {
+						source: $state.snapshot(plot.triggerSource) ?? plot.series[0].dataKey,
+						...$state.snapshot(plot.trigger)
+					}
+				};
+			} else if (plot.viewType === 'allan') {
+				viewConfig = { AllanDeviation: $state.snapshot(plot.allan) };
+			} else if (plot.viewType === 'xy') {
+				viewConfig = {
+					Xy: {
+						x: $state.snapshot(plot.xySource) ?? plot.series[0].dataKey,
+						...$state.snapshot(plot.xy),
+						filter: plot.filter ?? undefined
+					}
+				};
+			} else if (plot.viewType === 'histogram') {
+				viewConfig = {
+					Histogram: { window_seconds: plot.windowSeconds, ...$state.snapshot(plot.histogram) }
+				};
+			} else if (plot.viewType === 'cross') {
+				viewConfig = {
+					CrossSpectrum: {
+						reference: $state.snapshot(plot.crossReference) ?? plot.series[0].dataKey,
+						window_seconds: plot.fftSeconds,
+						detrend_method: plot.fftDetrendMethod,
+						psd: $state.snapshot(plot.psd),
+						quantity: plot.crossQuantity,
+						filter: plot.filter ?? undefined
+					}
+				};
+			} else {
+				viewConfig = {
+					Spectrogram: {
+						window_seconds: plot.fftSeconds,
+						history_seconds: plot.spectrogramSeconds,
+						detrend_method: plot.fftDetrendMethod,
+						psd: $state.snapshot(plot.psd),
+						filter: plot.filter ?? undefined
+					}
+				};
+			}
 
 			const configForBackend: SharedPlotConfig = {
 				plot_id: plot.id,
@@ -521,6 +890,49 @@ class ChartState {
 		});
 	}
 
+	/**
+	 * Folds a spectrogram update into the plot's waterfalls, trimming frames older than the plot's
+	 * spectrogram history.
+	 */
+	#applySpectrogramData(plot: PlotConfig, data: SpectrogramData) {
+		let waterfalls = this.#spectrograms.get(plot.id);
+		if (!waterfalls) {
+			waterfalls = new Map();
+			this.#spectrograms.set(plot.id, waterfalls);
+		}
+		const key = JSON.stringify(data.key);
+		let series = waterfalls.get(key);
+		if (!series || data.reset) {
+			series = { timestamps: [], frequencies: data.frequencies, values: [] };
+			waterfalls.set(key, series);
+		}
+		for (let i = 0; i < data.timestamps.length; i++) {
+			series.timestamps.push(data.timestamps[i]);
+		}
+		for (let i = 0; i < data.values.length; i++) {
+			series.values.push(data.values[i]);
+		}
+
+		const latest = series.timestamps[series.timestamps.length - 1];
+		let drop = 0;
+		while (drop < series.timestamps.length && series.timestamps[drop] < latest - plot.spectrogramSeconds) {
+			drop++;
+		}
+		if (drop > 0) {
+			series.timestamps.splice(0, drop);
+			series.values.splice(0, drop * series.frequencies.length);
+		}
+	}
+
+	/**
+	 * The accumulated waterfall of each column in a spectrogram plot, keyed by the column's JSON key.
+	 * Read `spectrogramVersions` first to redraw when it changes.
+	 * @param plotId The ID of the plot.
+	 */
+	getSpectrograms(plotId: string): Map<string, SpectrogramSeries> | undefined {
+		return this.#spectrograms.get(plotId);
+	}
+
 	/**
 	 * Tears down the data pipeline for a specific plot on the backend.
 	 * @param plotId The ID of the plot to destroy on the backend.
@@ -531,6 +943,10 @@ class ChartState {
 		}
 		this.#latestDataCache.delete(plotId);
         this.#dirtyPlots.delete(plotId);
+		this.#listeningSpectrograms.delete(plotId);
+		this.#spectrograms.delete(plotId);
+		this.#dirtySpectrograms.delete(plotId);
+		this.spectrogramVersions.delete(plotId);
 		try {
 			await invoke('destroy_plot_pipeline', { plotId });
 		} catch (e) {
@@ -633,6 +1049,94 @@ class ChartState {
 		});
 	}
 
+	/**
+	 * Replaces the current plots with the ones saved in a session file and selects its ports.
+	 * @param session The restored session, with data keys already pointing at its `session://` ports.
+	 */
+	restoreSession(session: SessionRestored) {
+		this.deleteAllPlots();
+
+		for (const url of session.urls) {
+			const devices = deviceState.getPort(url)?.devices ?? [];
+			deviceState.select({
+				portUrl: url,
+				childrenRoutes: devices.filter((d) => !isRootRoute(d.route)).map((d) => d.route)
+			});
+		}
+
+		for (const config of session.plots) {
+			const selection: RowSelectionState = {};
+			const expansion: ExpandedState = {};
+			for (const key of config.data_keys) {
+				const deviceId = `${key.port_url}:${key.device_route}`;
+				selection[JSON.stringify(key)] = true;
+				expansion[deviceId] = true;
+				expansion[`${deviceId}:${key.stream_id}`] = true;
+			}
+
+			const first = config.data_keys[0];
+			const stream = first
+				? deviceState
+						.getDevice(first.port_url, first.device_route)
+						?.streams.find((s) => s.meta.stream_id === first.stream_id)
+				: undefined;
+			const plot = new PlotConfig(stream?.meta.name ?? 'Restored Plot', selection, expansion, this.isPaused);
+
+			if ('Timeseries' in config.view_config) {
+				const ts = config.view_config.Timeseries;
+				plot.viewType = 'timeseries';
+				plot.windowSeconds = ts.window_seconds;
+				plot.resolutionMultiplier = ts.resolution_multiplier;
+				if (ts.decimation_method !== 'Fpcs') plot.decimationMethod = ts.decimation_method;
+				plot.filter = ts.filter ?? null;
+			} else if ('Fft' in config.view_config) {
+				const fft = config.view_config.Fft;
+				plot.viewType = 'fft';
+				plot.fftSeconds = fft.window_seconds;
+				plot.fftDetrendMethod = fft.detrend_method;
+				plot.psd = fft.psd;
+				plot.filter = fft.filter ?? null;
+			} else if ('Trigger' in config.view_config) {
+				const { source, ...trigger } = config.view_config.Trigger;
+				plot.viewType = 'trigger';
+				plot.triggerSource = source;
+				plot.trigger = trigger;
+			} else if ('AllanDeviation' in config.view_config) {
+				plot.viewType = 'allan';
+				plot.allan = config.view_config.AllanDeviation;
+			} else if ('Xy' in config.view_config) {
+				const { x, filter, ...xy } = config.view_config.Xy;
+				plot.viewType = 'xy';
+				plot.xySource = x;
+				plot.xy = xy;
+				plot.filter = filter ?? null;
+			} else if ('Histogram' in config.view_config) {
+				const { window_seconds, ...histogram } = config.view_config.Histogram;
+				plot.viewType = 'histogram';
+				plot.windowSeconds = window_seconds;
+				plot.histogram = histogram;
+			} else if ('CrossSpectrum' in config.view_config) {
+				const cross = config.view_config.CrossSpectrum;
+				plot.viewType = 'cross';
+				plot.fftSeconds = cross.window_seconds;
+				plot.fftDetrendMethod = cross.detrend_method;
+				plot.psd = cross.psd;
+				plot.crossReference = cross.reference;
+				plot.crossQuantity = cross.quantity;
+				plot.filter = cross.filter ?? null;
+			} else if ('Spectrogram' in config.view_config) {
+				const spec = config.view_config.Spectrogram;
+				plot.viewType = 'spectrogram';
+				plot.fftSeconds = spec.window_seconds;
+				plot.spectrogramSeconds = spec.history_seconds;
+				plot.fftDetrendMethod = spec.detrend_method;
+				plot.psd = spec.psd;
+				plot.filter = spec.filter ?? null;
+			}
+			this.plots.push(plot);
+		}
+	}
+
 	/**
 	 * Moves a plot up or down in the list.
 	 * @param plotId The ID of the plot to move.
@@ -846,9 +1350,9 @@ class ChartState {
 	/**
 	 * Gets the current type of plot. Used for validating context menu options.
 	 * @param plotId The ID of the plot to check.
-	 * @returns A string key ('timeseries', 'fft') or undefined if the plot is not found.
+	 * @returns A string key ('timeseries', 'trigger', 'fft', 'cross', 'spectrogram', 'allan', 'histogram', 'xy') or undefined if the plot is not found.
 	 */
-	getPlotType(plotId: string): 'timeseries' | 'fft' | undefined {
+	getPlotType(plotId: string): ViewType | undefined {
 		const plot = this.plots.find((p) => p.id === plotId);
 		return plot?.viewType;
 	}
@@ -7,6 +7,12 @@ import { sortUiDevicesByRoute, isRootRoute } from "$lib/utils";
 
 type DeviceTreeItem = UiDevice & { children: UiDevice[] };
 
+// Virtual ports the backend serves math channels and lock-ins on. Each streams whenever it has
+// channels.
+export const MATH_PORT_URL = "virtual://math";
+export const LOCKIN_PORT_URL = "virtual://lockin";
+const VIRTUAL_PORT_URLS = [MATH_PORT_URL, LOCKIN_PORT_URL];
+
 export interface Selection {
   portUrl: string;
   childrenRoutes: string[];
@@ -17,7 +23,10 @@ class DeviceState {
     string,
     { state: PortState; devices: UiDevice[] }
   >();
+  // The most recently confirmed port; the footer and replay controls follow it.
   selection = $state<Selection | null>(null);
+  // Every port currently streaming into the capture, keyed by URL.
+  selections = new SvelteMap<string, Selection>();
   // Map<portUrl, Set<route>>
   childrenSelections = new SvelteMap<string, Set<string>>();
 
@@ -51,6 +60,7 @@ class DeviceState {
 
         this.#devicesMap.set(url, { ...entry, devices: new_devices });
         this.#reconcileChildrenSelection(url);
+        if (VIRTUAL_PORT_URLS.includes(url)) this.#includeVirtualPort(url);
       },
     );
 
@@ -82,7 +92,46 @@ class DeviceState {
   #removePort(url: string) {
     this.#devicesMap.delete(url);
     this.childrenSelections.delete(url);
-    if (this.selection?.portUrl === url) this.selection = null;
+    this.#dropSelection(url);
+  }
+
+  // Virtual ports are always selected, without becoming the current selection.
+  #includeVirtualPort(url: string) {
+    if (this.selections.has(url)) return;
+    this.selections.set(url, {
+      portUrl: url,
+      childrenRoutes: [],
+    });
+  }
+
+  #dropSelection(url: string) {
+    this.selections.delete(url);
+    if (this.selection?.portUrl === url) {
+      const remaining = Array.from(this.selections.values()).filter(
+        (s) => !VIRTUAL_PORT_URLS.includes(s.portUrl),
+      );
+      this.selection = remaining[remaining.length - 1] ?? null;
+    }
+  }
+
+  /** Adds (or updates) a port in the streaming set and makes it the current selection. */
+  select(selection: Selection) {
+    this.selections.delete(selection.portUrl);
+    this.selections.set(selection.portUrl, selection);
+    this.selection = selection;
+  }
+
+  async deselect(url: string) {
+    try {
+      await invoke("deselect_port", { portUrl: url });
+    } catch (e) {
+      console.error(`[DeviceState] Failed to deselect ${url}:`, e);
+    }
+    this.#dropSelection(url);
+  }
+
+  isSelected(url: string) {
+    return this.selections.has(url);
   }
 
   async #initializeState() {
@@ -110,6 +159,7 @@ class DeviceState {
 
           if (currentState === "Streaming")
             this.#setDefaultChildrenForPort(url);
+          if (VIRTUAL_PORT_URLS.includes(url)) this.#includeVirtualPort(url);
         } catch {
           const fallbackState = devicesForPort[0]?.state ?? "Disconnected";
           devicesForPort.sort(sortUiDevicesByRoute);
@@ -134,7 +184,7 @@ class DeviceState {
     if (wasStreaming && state !== "Streaming") {
       // Clear selections anchored to a now non-streaming port
       this.childrenSelections.delete(url);
-      if (this.selection?.portUrl === url) this.selection = null;
+      this.#dropSelection(url);
     }
   }
 
@@ -175,11 +225,14 @@ class DeviceState {
       this.childrenSelections.set(url, set);
     }
 
-    if (this.selection?.portUrl === url) {
-      const cur = new Set(this.selection.childrenRoutes);
+    const selected = this.selections.get(url);
+    if (selected) {
+      const cur = new Set(selected.childrenRoutes);
       for (const r of Array.from(cur)) if (!set!.has(r)) cur.delete(r);
       for (const r of set!) if (!cur.has(r)) cur.add(r);
-      this.selection = { portUrl: url, childrenRoutes: Array.from(cur) };
+      const updated = { portUrl: url, childrenRoutes: Array.from(cur) };
+      this.selections.set(url, updated);
+      if (this.selection?.portUrl === url) this.selection = updated;
     }
   }
 
@@ -240,17 +293,21 @@ class DeviceState {
     return portData.devices.find((d) => d.route === route);
   }
 
+  // Devices of every selected port, so plots can mix columns across ports.
   selectedDevices = $derived.by(() => {
-    const sel = this.selection;
-    if (!sel) return [];
-
-    const portData = this.#devicesMap.get(sel.portUrl);
-    if (!portData) return [];
-
-    const childSet = new Set(sel.childrenRoutes);
-    return portData.devices.filter(
-      (d) => isRootRoute(d.route) || childSet.has(d.route),
-    );
+    const out: UiDevice[] = [];
+    for (const sel of this.selections.values()) {
+      const portData = this.#devicesMap.get(sel.portUrl);
+      if (!portData) continue;
+
+      const childSet = new Set(sel.childrenRoutes);
+      out.push(
+        ...portData.devices.filter(
+          (d) => isRootRoute(d.route) || childSet.has(d.route),
+        ),
+      );
+    }
+    return out;
   });
 
   selectedPortState = $derived.by((): PortState | null => {
@@ -1,17 +1,112 @@
+import { listen } from '@tauri-apps/api/event';
+import { invoke } from '@tauri-apps/api/core';
+import type { RecordingStatus } from '$lib/bindings/RecordingStatus';
+import type { ReplayStatus } from '$lib/bindings/ReplayStatus';
+import type { TcpProxyStatus } from '$lib/bindings/TcpProxyStatus';
+import { uiState } from '$lib/states/uiState.svelte';
+
 class IoState {
 	isLogging = $state(false);
-	loggingPath = $state('/Users/nguyen/Projects/GitHub/trendline/session-01.log');
+	loggingPath = $state('');
+	status = $state<RecordingStatus | null>(null);
+	// Keyed by the recording's file:// port URL
+	replays = $state<Record<string, ReplayStatus>>({});
+	// Built-in TCP proxy per live port URL
+	tcpProxies = $state<Record<string, TcpProxyStatus>>({});
+
+	constructor() {
+		invoke<RecordingStatus>('get_recording_status').then((s) => this.#apply(s));
+
+		listen<RecordingStatus>('recording-state-changed', ({ payload }) => {
+			this.#apply(payload);
+		});
+
+		listen<string>('recording-error', ({ payload }) => {
+			console.error(`I/O Store: ${payload}`);
+			this.isLogging = false;
+		});
 
-	startLogging(newPath: string) {
-		this.loggingPath = newPath;
-		this.isLogging = true;
-		console.log(`I/O Store: Logging started to ${newPath}.`);
+		listen<ReplayStatus>('replay-status-changed', ({ payload }) => {
+			this.replays[payload.url] = payload;
+		});
+
+		listen<TcpProxyStatus>('tcp-proxy-changed', ({ payload }) => {
+			this.tcpProxies[payload.port_url] = payload;
+			if (payload.error) {
+				console.error(`I/O Store: ${payload.error}`);
+			}
+		});
+
+		// A recording shows up as a new port; let the user pick its devices like a live one.
+		listen<string>('file-opened', () => {
+			uiState.open('discovery');
+		});
 	}
-	stopLogging() {
-		this.isLogging = false;
-		console.log('I/O Store: Logging stopped.');
+
+	isReplay(portUrl: string | undefined | null) {
+		return !!portUrl && portUrl.startsWith('file://');
 	}
-    
+
+	#apply(status: RecordingStatus) {
+		this.status = status;
+		this.isLogging = status.is_recording;
+		this.loggingPath = status.current_file ?? status.path ?? this.loggingPath;
+	}
+
+	async startLogging(newPath: string) {
+		try {
+			await invoke('start_recording', { path: newPath });
+			console.log(`I/O Store: Logging started to ${newPath}.`);
+		} catch (e) {
+			console.error('I/O Store: Failed to start logging:', e);
+		}
+	}
+	async stopLogging() {
+		try {
+			await invoke('stop_recording');
+			console.log('I/O Store: Logging stopped.');
+		} catch (e) {
+			console.error('I/O Store: Failed to stop logging:', e);
+		}
+	}
+
+	async #replayCommand(command: string, args: Record<string, unknown>) {
+		try {
+			await invoke(command, args);
+		} catch (e) {
+			console.error(`I/O Store: ${command} failed:`, e);
+		}
+	}
+
+	playReplay(portUrl: string) {
+		return this.#replayCommand('replay_play', { portUrl });
+	}
+	pauseReplay(portUrl: string) {
+		return this.#replayCommand('replay_pause', { portUrl });
+	}
+	seekReplay(portUrl: string, position: number) {
+		return this.#replayCommand('replay_seek', { portUrl, position });
+	}
+	setReplaySpeed(portUrl: string, speed: number) {
+		return this.#replayCommand('replay_set_speed', { portUrl, speed });
+	}
+	closeReplay(portUrl: string) {
+		return this.#replayCommand('close_recording', { portUrl });
+	}
+
+	isTcpProxyRunning(portUrl: string | undefined | null) {
+		return !!portUrl && !!this.tcpProxies[portUrl]?.address;
+	}
+
+	async setTcpProxy(portUrl: string, enabled: boolean, bind?: string) {
+		try {
+			const status = await invoke<TcpProxyStatus>('set_tcp_proxy', { portUrl, enabled, bind });
+			this.tcpProxies[portUrl] = status;
+		} catch (e) {
+			console.error('I/O Store: Failed to toggle TCP proxy:', e);
+		}
+	}
+
     toggleLogging() {
         if (this.isLogging) {
             this.stopLogging();
@@ -21,4 +116,4 @@ class IoState {
     }
 }
 
-export const ioState = new IoState();
\ No newline at end of file
+export const ioState = new IoState();
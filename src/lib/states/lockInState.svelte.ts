@@ -0,0 +1,39 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { DataColumnId } from '$lib/bindings/DataColumnId';
+import type { LockInConfig } from '$lib/bindings/LockInConfig';
+import type { LockInInfo } from '$lib/bindings/LockInInfo';
+
+class LockInState {
+	lockIns = $state<LockInInfo[]>([]);
+
+	constructor() {
+		this.refresh();
+	}
+
+	async refresh() {
+		try {
+			this.lockIns = await invoke<LockInInfo[]>('get_lock_ins');
+		} catch (e) {
+			console.error('Lock-in Store: failed to list lock-ins:', e);
+		}
+	}
+
+	/** Rejects with the backend's message if the configuration is invalid. */
+	async create(config: LockInConfig) {
+		const info = await invoke<LockInInfo>('create_lock_in', { config });
+		await this.refresh();
+		return info;
+	}
+
+	/** `key` is either output column of the lock-in. */
+	async remove(key: DataColumnId) {
+		try {
+			await invoke('remove_lock_in', { key });
+		} catch (e) {
+			console.error('Lock-in Store: failed to remove lock-in:', e);
+		}
+		await this.refresh();
+	}
+}
+
+export const lockInState = new LockInState();
@@ -0,0 +1,38 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { DataColumnId } from '$lib/bindings/DataColumnId';
+import type { MathChannelConfig } from '$lib/bindings/MathChannelConfig';
+import type { MathChannelInfo } from '$lib/bindings/MathChannelInfo';
+
+class MathState {
+	channels = $state<MathChannelInfo[]>([]);
+
+	constructor() {
+		this.refresh();
+	}
+
+	async refresh() {
+		try {
+			this.channels = await invoke<MathChannelInfo[]>('get_math_channels');
+		} catch (e) {
+			console.error('Math Store: failed to list channels:', e);
+		}
+	}
+
+	/** Rejects with the backend's message if the expression or bindings are invalid. */
+	async create(config: MathChannelConfig) {
+		const info = await invoke<MathChannelInfo>('create_math_channel', { config });
+		await this.refresh();
+		return info;
+	}
+
+	async remove(key: DataColumnId) {
+		try {
+			await invoke('remove_math_channel', { key });
+		} catch (e) {
+			console.error('Math Store: failed to remove channel:', e);
+		}
+		await this.refresh();
+	}
+}
+
+export const mathState = new MathState();
@@ -0,0 +1,22 @@
+import { invoke } from '@tauri-apps/api/core';
+import type { CaptureMemoryStatus } from '$lib/bindings/CaptureMemoryStatus';
+import type { RetentionConfig } from '$lib/bindings/RetentionConfig';
+
+class RetentionState {
+	status = $state<CaptureMemoryStatus | null>(null);
+
+	async refresh() {
+		try {
+			this.status = await invoke<CaptureMemoryStatus>('get_capture_memory');
+		} catch (e) {
+			console.error('Retention Store: failed to get capture memory:', e);
+		}
+	}
+
+	/** Rejects with the backend's message if the settings are invalid. */
+	async apply(config: RetentionConfig) {
+		this.status = await invoke<CaptureMemoryStatus>('set_capture_retention', { config });
+	}
+}
+
+export const retentionState = new RetentionState();